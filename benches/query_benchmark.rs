@@ -0,0 +1,82 @@
+use std::{path::PathBuf, sync::Arc};
+
+use c_sharp_analyzer_provider_cli::c_sharp_graph::{
+    language_config::SourceNodeLanguageConfiguration,
+    loader::{load_graph_for_file, SourceType},
+    query::{Query, QueryOptions, QueryType},
+    symbol_index::SymbolIndex,
+};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use stack_graphs::graph::StackGraph;
+use tree_sitter_stack_graphs::NoCancellation;
+use walkdir::WalkDir;
+
+/// Patterns declared in the project, the dependencies are not part of the graph.
+const PATTERNS: &[&str] = &[
+    "NerdDinner.*",
+    "NerdDinner.Models.*",
+    "NerdDinner.Helpers.*",
+];
+
+/// The graph of the nerd-dinner sources, built the way init builds it without the database.
+fn nerd_dinner_graph() -> (StackGraph, Arc<SourceType>) {
+    let location = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("testdata/nerd-dinner");
+    let lc = SourceNodeLanguageConfiguration::new(&NoCancellation).unwrap();
+    let mut graph = StackGraph::new();
+    let _ = graph.add_from_graph(&lc.language_config.builtins);
+    for entry in WalkDir::new(&location).sort_by_file_name() {
+        let entry = entry.unwrap();
+        if entry.file_type().is_dir() {
+            continue;
+        }
+        if let Err(e) = load_graph_for_file(
+            entry.into_path(),
+            &mut graph,
+            &lc.language_config,
+            &lc.source_type_node_info,
+            &NoCancellation,
+        ) {
+            eprintln!("skipping file: {}", e);
+        }
+    }
+    (graph, lc.source_type_node_info)
+}
+
+fn query_benchmark(c: &mut Criterion) {
+    let (graph, source_type) = nerd_dinner_graph();
+
+    c.bench_function("build symbol index", |b| {
+        b.iter(|| SymbolIndex::new(black_box(&graph)))
+    });
+
+    let index = SymbolIndex::new(&graph);
+    let mut group = c.benchmark_group("referenced query");
+    for pattern in PATTERNS {
+        group.bench_with_input(BenchmarkId::new("scan", pattern), pattern, |b, pattern| {
+            b.iter(|| {
+                QueryType::All {
+                    graph: &graph,
+                    source_type: &source_type,
+                    options: QueryOptions::default(),
+                }
+                .query(pattern.to_string())
+                .unwrap()
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("index", pattern), pattern, |b, pattern| {
+            b.iter(|| {
+                QueryType::All {
+                    graph: &graph,
+                    source_type: &source_type,
+                    options: QueryOptions::indexed(&index),
+                }
+                .query(pattern.to_string())
+                .unwrap()
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, query_benchmark);
+criterion_main!(benches);
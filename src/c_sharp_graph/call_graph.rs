@@ -0,0 +1,787 @@
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+use anyhow::{Error, Ok};
+use serde_json::Value;
+use stack_graphs::{
+    arena::Handle,
+    graph::{File, Node, StackGraph},
+};
+use tracing::{debug, info, trace};
+
+use crate::c_sharp_graph::{
+    loader::SourceType,
+    query::{
+        get_file_uri, get_fqdn, get_location, get_syntax_type, is_source_file, Fqdn, Query,
+        QueryOptions, QueryType, Search, SyntaxType,
+    },
+    results::{Location, ResultNode},
+};
+
+/// How the callee of a call was chosen, the call path of a caller is reported with the weakest
+/// resolution of its calls as `call_resolution`. The stack graph does not resolve calls, so a
+/// callee is always a source method whose name matches the call site.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum CallResolution {
+    /// The callee is a method of the declared type of the qualifier, or of the caller's class
+    /// for a call without one.
+    Type,
+    /// The type of the qualifier is not known, or the caller's class has no method with that
+    /// name, and the callee is the only method in source with the name.
+    Name,
+}
+
+impl CallResolution {
+    fn as_str(&self) -> &'static str {
+        match self {
+            CallResolution::Type => "type",
+            CallResolution::Name => "name",
+        }
+    }
+}
+
+/// The shortest call path from a caller to the target, the caller first.
+#[derive(Debug, Clone)]
+struct CallPath {
+    symbols: Vec<String>,
+    resolution: CallResolution,
+}
+
+/// A method (or constructor) declared in source code.
+#[derive(Debug, Clone)]
+struct MethodDeclaration {
+    fqdn: Fqdn,
+    file_uri: String,
+    location: Location,
+}
+
+/// A reference site found in a source file, the symbol is either a plain identifier or
+/// `<accessor>.<name>` for member access.
+#[derive(Debug)]
+struct ReferenceSite {
+    symbol: String,
+    location: Location,
+}
+
+/// A local variable, parameter, field or property with its declared type.
+#[derive(Debug)]
+struct Variable {
+    name: String,
+    type_name: String,
+    location: Location,
+}
+
+#[derive(Debug, Default)]
+struct FileSites {
+    file_uri: String,
+    references: Vec<ReferenceSite>,
+    variables: Vec<Variable>,
+}
+
+/// The call graph derived from the stack graph, for every method declared in source it holds
+/// the set of source methods that it calls.
+///
+/// Callees are matched by name, not resolved through the stack graph: the qualifier of a member
+/// access (a class in source, or the declared type of a local variable, parameter, field or
+/// property of the file) or the enclosing class is used to choose between methods with the same
+/// name. A call on a qualifier of a known type only reaches the methods of that type, methods
+/// inherited from a base class are not followed. When the type is not known, the call is only kept
+/// when there is a single method with that name in source.
+///
+/// It only depends on the graph, so it is built once per graph snapshot.
+#[derive(Debug, Default)]
+pub(crate) struct CallGraph {
+    /// The methods by file URI, sorted by start, the outer one first when two start at the same
+    /// position.
+    methods: BTreeMap<String, Vec<MethodDeclaration>>,
+    callees: BTreeMap<Fqdn, BTreeMap<Fqdn, CallResolution>>,
+}
+
+impl CallGraph {
+    pub(crate) fn new(
+        graph: &StackGraph,
+        source_type: &SourceType,
+        options: &QueryOptions,
+    ) -> anyhow::Result<CallGraph, Error> {
+        let mut methods: Vec<MethodDeclaration> = vec![];
+        let mut classes: BTreeSet<String> = BTreeSet::new();
+        let mut sites: Vec<FileSites> = vec![];
+
+        let mut files: Vec<Handle<File>> = graph.iter_files().collect();
+        files.sort();
+        for file in files {
+            options.check_cancelled("call_graph")?;
+            if let Some(file_sites) =
+                Self::collect_file(graph, source_type, file, &mut methods, &mut classes)
+            {
+                sites.push(file_sites);
+            }
+        }
+
+        let mut methods_by_name: BTreeMap<String, BTreeSet<Fqdn>> = BTreeMap::new();
+        for method in methods.iter() {
+            if let Some(name) = method.fqdn.method.as_ref() {
+                // Local functions are called by their own name, not `<Outer>.<Local>`.
+                let name = name.rsplit_once('.').map_or(name.as_str(), |(_, n)| n);
+                methods_by_name
+                    .entry(name.to_string())
+                    .or_default()
+                    .insert(method.fqdn.clone());
+            }
+        }
+
+        let mut call_graph = CallGraph::with_methods(methods);
+        for file_sites in sites.iter() {
+            options.check_cancelled("call_graph")?;
+            call_graph.add_file_calls(file_sites, &methods_by_name, &classes);
+        }
+        debug!(
+            "call graph built with {} methods and {} callers",
+            call_graph.methods().count(),
+            call_graph.callees.len()
+        );
+        Ok(call_graph)
+    }
+
+    fn with_methods(methods: Vec<MethodDeclaration>) -> CallGraph {
+        let mut by_file: BTreeMap<String, Vec<MethodDeclaration>> = BTreeMap::new();
+        for method in methods {
+            by_file
+                .entry(method.file_uri.clone())
+                .or_default()
+                .push(method);
+        }
+        for methods in by_file.values_mut() {
+            methods.sort_by(|a, b| {
+                a.location
+                    .start_position
+                    .cmp(&b.location.start_position)
+                    .then_with(|| b.location.end_position.cmp(&a.location.end_position))
+            });
+        }
+        CallGraph {
+            methods: by_file,
+            callees: BTreeMap::new(),
+        }
+    }
+
+    fn methods(&self) -> impl Iterator<Item = &MethodDeclaration> {
+        self.methods.values().flatten()
+    }
+
+    /// Find the innermost method declaration that contains the location. Methods nest, so it is
+    /// the last one starting before the location that also contains it.
+    fn enclosing_method(&self, file_uri: &str, location: &Location) -> Option<&MethodDeclaration> {
+        let methods = self.methods.get(file_uri)?;
+        let starting_before =
+            methods.partition_point(|m| m.location.start_position <= location.start_position);
+        methods[..starting_before]
+            .iter()
+            .rev()
+            .find(|m| m.location.contains(location))
+    }
+
+    /// Walk the call graph backwards from the methods that directly reference the target,
+    /// returning each transitive caller with the shortest call path to the target. The direct
+    /// references are resolved by the stack graph, so they do not weaken the resolution.
+    fn transitive_callers(
+        &self,
+        direct_callers: BTreeMap<Fqdn, String>,
+        max_depth: Option<usize>,
+    ) -> BTreeMap<Fqdn, CallPath> {
+        let mut callers_of: BTreeMap<&Fqdn, BTreeMap<&Fqdn, CallResolution>> = BTreeMap::new();
+        for (caller, callees) in self.callees.iter() {
+            for (callee, resolution) in callees {
+                callers_of
+                    .entry(callee)
+                    .or_default()
+                    .insert(caller, *resolution);
+            }
+        }
+
+        let mut paths: BTreeMap<Fqdn, CallPath> = BTreeMap::new();
+        let mut queue: VecDeque<Fqdn> = VecDeque::new();
+        for (caller, target) in direct_callers {
+            paths.insert(
+                caller.clone(),
+                CallPath {
+                    symbols: vec![caller.get_full_symbol(), target],
+                    resolution: CallResolution::Type,
+                },
+            );
+            queue.push_back(caller);
+        }
+
+        while let Some(callee) = queue.pop_front() {
+            let path = paths[&callee].clone();
+            if max_depth.is_some_and(|max| path.symbols.len() > max) {
+                continue;
+            }
+            let Some(callers) = callers_of.get(&callee) else {
+                continue;
+            };
+            for (caller, resolution) in callers {
+                if paths.contains_key(*caller) {
+                    continue;
+                }
+                let mut symbols = vec![caller.get_full_symbol()];
+                symbols.extend(path.symbols.iter().cloned());
+                paths.insert(
+                    (*caller).clone(),
+                    CallPath {
+                        symbols,
+                        resolution: path.resolution.max(*resolution),
+                    },
+                );
+                queue.push_back((*caller).clone());
+            }
+        }
+        paths
+    }
+}
+
+// Private methods for building the call graph
+impl CallGraph {
+    fn collect_file(
+        graph: &StackGraph,
+        source_type: &SourceType,
+        file: Handle<File>,
+        methods: &mut Vec<MethodDeclaration>,
+        classes: &mut BTreeSet<String>,
+    ) -> Option<FileSites> {
+        let mut file_nodes: Vec<Handle<Node>> = graph.nodes_for_file(file).collect();
+        file_nodes.sort();
+
+        let comp_unit = file_nodes
+            .iter()
+            .find(|n| get_syntax_type(graph, **n).is_some_and(|s| s == SyntaxType::CompUnit))?;
+        if !is_source_file(graph, source_type, file, *comp_unit) {
+            return None;
+        }
+        let file_uri = get_file_uri(graph, file)?;
+
+        let mut file_sites = FileSites {
+            file_uri: file_uri.clone(),
+            ..Default::default()
+        };
+        for node_handle in file_nodes {
+            let node = &graph[node_handle];
+            let Some(symbol) = node.symbol().map(|s| graph[s].to_string()) else {
+                continue;
+            };
+            let Some(location) = get_location(graph, node_handle) else {
+                continue;
+            };
+            if node.is_reference() {
+                file_sites
+                    .references
+                    .push(ReferenceSite { symbol, location });
+                continue;
+            }
+            if !node.is_definition() {
+                continue;
+            }
+            match get_syntax_type(graph, node_handle) {
+                Some(SyntaxType::MethodName) => {
+                    if let Some(fqdn) = get_fqdn(node_handle, graph) {
+                        methods.push(MethodDeclaration {
+                            fqdn,
+                            file_uri: file_uri.clone(),
+                            location,
+                        });
+                    }
+                }
+                Some(SyntaxType::ClassDef) => {
+                    classes.insert(symbol);
+                }
+                Some(SyntaxType::LocalVar) | Some(SyntaxType::PropertyName) => {
+                    // The definition has an edge to the reference of its declared type, which has
+                    // the same source node. The other references are in the initializer.
+                    let type_name = graph
+                        .outgoing_edges(node_handle)
+                        .filter(|e| {
+                            graph[e.sink].is_reference()
+                                && get_location(graph, e.sink).as_ref() == Some(&location)
+                        })
+                        .find_map(|e| graph[e.sink].symbol().map(|s| graph[s].to_string()));
+                    if let Some(type_name) = type_name {
+                        file_sites.variables.push(Variable {
+                            name: symbol,
+                            type_name,
+                            location,
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+        Some(file_sites)
+    }
+
+    fn add_file_calls(
+        &mut self,
+        file_sites: &FileSites,
+        methods_by_name: &BTreeMap<String, BTreeSet<Fqdn>>,
+        classes: &BTreeSet<String>,
+    ) {
+        let member_accesses: Vec<&ReferenceSite> = file_sites
+            .references
+            .iter()
+            .filter(|r| r.symbol.contains('.'))
+            .collect();
+
+        let mut calls: Vec<(Fqdn, Fqdn, CallResolution)> = vec![];
+        for reference in file_sites.references.iter() {
+            let (qualifier, name) = match reference.symbol.rsplit_once('.') {
+                Some((q, n)) => (Some(q), strip_type_arguments(n)),
+                None => (None, strip_type_arguments(&reference.symbol)),
+            };
+            let Some(candidates) = methods_by_name.get(name) else {
+                continue;
+            };
+            let Some(caller) = self.enclosing_method(&file_sites.file_uri, &reference.location)
+            else {
+                continue;
+            };
+            let qualifier_type =
+                qualifier.and_then(|q| self.qualifier_type(file_sites, caller, q, classes));
+            let resolved: Vec<&Fqdn> = match (qualifier, qualifier_type) {
+                (Some(_), Some(qualifier_type)) => candidates
+                    .iter()
+                    .filter(|c| class_name(c) == Some(qualifier_type))
+                    .collect(),
+                (Some(_), None) => vec![],
+                (None, _) => {
+                    // The identifier that names the member of a member access is handled with the
+                    // qualifier above.
+                    if member_accesses.iter().any(|m| {
+                        m.location.contains(&reference.location)
+                            && m.symbol
+                                .rsplit_once('.')
+                                .map(|(_, n)| strip_type_arguments(n))
+                                == Some(name)
+                    }) {
+                        continue;
+                    }
+                    candidates
+                        .iter()
+                        .filter(|c| {
+                            c.namespace == caller.fqdn.namespace && c.class == caller.fqdn.class
+                        })
+                        .collect()
+                }
+            };
+            // A qualifier of a known type only reaches the methods of that type. A call without a
+            // qualifier may reach an inherited method, which is not in the caller's class, so it
+            // falls back to the name like a qualifier of an unknown type.
+            let (resolved, resolution) = if !resolved.is_empty() {
+                (resolved, CallResolution::Type)
+            } else if qualifier_type.is_none() && candidates.len() == 1 {
+                (candidates.iter().collect(), CallResolution::Name)
+            } else {
+                continue;
+            };
+            // Recursion does not change what is reachable, this also skips the name of the
+            // declaration which is itself an identifier reference.
+            for callee in resolved.into_iter().filter(|c| **c != caller.fqdn) {
+                trace!(
+                    "call from {} to {} resolved by {}",
+                    caller.fqdn.get_full_symbol(),
+                    callee.get_full_symbol(),
+                    resolution.as_str()
+                );
+                calls.push((caller.fqdn.clone(), callee.clone(), resolution));
+            }
+        }
+        for (caller, callee, resolution) in calls {
+            let callee_resolution = self
+                .callees
+                .entry(caller)
+                .or_default()
+                .entry(callee)
+                .or_insert(resolution);
+            *callee_resolution = (*callee_resolution).min(resolution);
+        }
+    }
+
+    /// The declared type of the qualifier of a member access: a local variable or parameter of
+    /// the caller, a field or property declared in the file, or a class in source. `var` and
+    /// `dynamic` declarations do not give the type.
+    fn qualifier_type<'a>(
+        &self,
+        file_sites: &'a FileSites,
+        caller: &MethodDeclaration,
+        qualifier: &'a str,
+        classes: &BTreeSet<String>,
+    ) -> Option<&'a str> {
+        let named: Vec<&Variable> = file_sites
+            .variables
+            .iter()
+            .filter(|v| v.name == qualifier)
+            .collect();
+        let variable = named
+            .iter()
+            .find(|v| caller.location.contains(&v.location))
+            .or_else(|| {
+                named.iter().find(|v| {
+                    self.enclosing_method(&file_sites.file_uri, &v.location)
+                        .is_none()
+                })
+            });
+        match variable {
+            Some(variable) => {
+                let type_name = strip_type_arguments(&variable.type_name);
+                (type_name != "var" && type_name != "dynamic").then_some(type_name)
+            }
+            None => classes.contains(qualifier).then_some(qualifier),
+        }
+    }
+}
+
+/// Finds the methods in source that transitively call something matching the query, the
+/// query is resolved with the `target` query type.
+pub(crate) struct CallsQuery<'graph> {
+    pub call_graph: &'graph CallGraph,
+    pub target: QueryType<'graph>,
+    /// Only return callers whose FQDN matches this pattern.
+    pub callers: Option<String>,
+    /// Maximum number of calls between the caller and the target.
+    pub max_depth: Option<usize>,
+}
+
+impl Query for CallsQuery<'_> {
+    fn query(self, query: String) -> anyhow::Result<Vec<ResultNode>, Error> {
+        let callers_search = self
+            .callers
+            .as_ref()
+            .map(|c| Search::create_search(c.clone()))
+            .transpose()?;
+
+        // This will return a NotFoundError when nothing references the target at all.
+        let target_results = self.target.query(query.clone())?;
+
+        let call_graph = self.call_graph;
+        let mut direct_callers: BTreeMap<Fqdn, String> = BTreeMap::new();
+        for result in target_results.iter() {
+            let Some(caller) = call_graph.enclosing_method(&result.file_uri, &result.code_location)
+            else {
+                continue;
+            };
+            direct_callers
+                .entry(caller.fqdn.clone())
+                .or_insert_with(|| result.get_fqdn_symbol());
+        }
+
+        let paths = call_graph.transitive_callers(direct_callers, self.max_depth);
+
+        let mut results: Vec<ResultNode> = vec![];
+        for method in call_graph.methods() {
+            let Some(path) = paths.get(&method.fqdn) else {
+                continue;
+            };
+            let full_symbol = method.fqdn.get_full_symbol();
+            if callers_search
+                .as_ref()
+                .is_some_and(|s| !s.match_namespace(&full_symbol))
+            {
+                continue;
+            }
+            results.push(ResultNode {
+                file_uri: method.file_uri.clone(),
+                line_number: method.location.start_position.line,
+                variables: call_variables(method, path),
+                code_location: method.location.clone(),
+            });
+        }
+        results.sort();
+        info!(
+            "found {} callers of '{}' from {} direct callers",
+            results.len(),
+            query,
+            target_results.len()
+        );
+        Ok(results)
+    }
+}
+
+fn call_variables(method: &MethodDeclaration, path: &CallPath) -> BTreeMap<String, Value> {
+    let mut var: BTreeMap<String, Value> = BTreeMap::new();
+    var.insert("file".to_string(), Value::from(method.file_uri.clone()));
+    if let Some(method_name) = &method.fqdn.method {
+        var.insert("symbol".to_string(), Value::from(method_name.clone()));
+        var.insert("fqdn_method".to_string(), Value::from(method_name.clone()));
+    }
+    if let Some(ns) = &method.fqdn.namespace {
+        var.insert("fqdn_namespace".to_string(), Value::from(ns.clone()));
+    }
+    if let Some(cls) = &method.fqdn.class {
+        var.insert("fqdn_class".to_string(), Value::from(cls.clone()));
+    }
+    var.insert("kind".to_string(), Value::from("declaration"));
+    var.insert(
+        "syntax_type".to_string(),
+        Value::from(SyntaxType::MethodName.to_string()),
+    );
+    var.insert(
+        "call_path".to_string(),
+        Value::from(
+            path.symbols
+                .iter()
+                .map(|p| Value::from(p.clone()))
+                .collect::<Vec<_>>(),
+        ),
+    );
+    var.insert(
+        "call_depth".to_string(),
+        Value::from(path.symbols.len() - 1),
+    );
+    if let Some(callee) = path.symbols.last() {
+        var.insert("callee".to_string(), Value::from(callee.clone()));
+    }
+    var.insert(
+        "call_resolution".to_string(),
+        Value::from(path.resolution.as_str()),
+    );
+    var
+}
+
+fn class_name(fqdn: &Fqdn) -> Option<&str> {
+    fqdn.class
+        .as_deref()
+        .map(|c| c.rsplit_once('.').map_or(c, |(_, name)| name))
+}
+
+fn strip_type_arguments(name: &str) -> &str {
+    name.split_once('<').map_or(name, |(n, _)| n)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::c_sharp_graph::{query::tests::graph_for_source, results::Position};
+
+    fn method_fqdn(namespace: &str, class: &str, method: &str) -> Fqdn {
+        Fqdn {
+            namespace: Some(namespace.to_string()),
+            class: Some(class.to_string()),
+            method: Some(method.to_string()),
+            field: None,
+            property: None,
+            event: None,
+        }
+    }
+
+    fn location(start_line: usize, end_line: usize) -> Location {
+        Location {
+            start_position: Position {
+                line: start_line,
+                character: 0,
+            },
+            end_position: Position {
+                line: end_line,
+                character: 1,
+            },
+        }
+    }
+
+    /// Top -> Outer -> Run, where Run references the target directly.
+    fn build_call_graph() -> CallGraph {
+        let run = method_fqdn("Demo.Client", "Subscriber", "Run");
+        let outer = method_fqdn("Demo.Client", "Subscriber", "Outer");
+        let top = method_fqdn("Demo.Client", "Other", "Top");
+        let methods = vec![
+            MethodDeclaration {
+                fqdn: run.clone(),
+                file_uri: "file:///Client.cs".to_string(),
+                location: location(7, 15),
+            },
+            MethodDeclaration {
+                fqdn: outer.clone(),
+                file_uri: "file:///Client.cs".to_string(),
+                location: location(17, 20),
+            },
+            MethodDeclaration {
+                fqdn: top.clone(),
+                file_uri: "file:///Client.cs".to_string(),
+                location: location(25, 29),
+            },
+        ];
+        let mut call_graph = CallGraph::with_methods(methods);
+        call_graph
+            .callees
+            .insert(outer.clone(), BTreeMap::from([(run, CallResolution::Type)]));
+        call_graph
+            .callees
+            .insert(top, BTreeMap::from([(outer, CallResolution::Name)]));
+        call_graph
+    }
+
+    #[test]
+    fn test_enclosing_method() {
+        let call_graph = build_call_graph();
+        let method = call_graph
+            .enclosing_method("file:///Client.cs", &location(10, 10))
+            .unwrap();
+        assert_eq!(method.fqdn.method, Some("Run".to_string()));
+        assert!(call_graph
+            .enclosing_method("file:///Client.cs", &location(22, 22))
+            .is_none());
+        assert!(call_graph
+            .enclosing_method("file:///Other.cs", &location(10, 10))
+            .is_none());
+
+        // A local function is the innermost method of the references in it.
+        let nested = CallGraph::with_methods(vec![
+            MethodDeclaration {
+                fqdn: method_fqdn("Demo.Client", "Subscriber", "Run.Local"),
+                file_uri: "file:///Client.cs".to_string(),
+                location: location(9, 12),
+            },
+            MethodDeclaration {
+                fqdn: method_fqdn("Demo.Client", "Subscriber", "Run"),
+                file_uri: "file:///Client.cs".to_string(),
+                location: location(7, 15),
+            },
+        ]);
+        let enclosing = |line| {
+            nested
+                .enclosing_method("file:///Client.cs", &location(line, line))
+                .and_then(|m| m.fqdn.method.clone())
+        };
+        assert_eq!(enclosing(10), Some("Run.Local".to_string()));
+        assert_eq!(enclosing(14), Some("Run".to_string()));
+        assert_eq!(enclosing(8), Some("Run".to_string()));
+    }
+
+    #[test]
+    fn test_transitive_callers_paths() {
+        let call_graph = build_call_graph();
+        let direct = BTreeMap::from([(
+            method_fqdn("Demo.Client", "Subscriber", "Run"),
+            "Demo.App.Publisher.Name".to_string(),
+        )]);
+        let paths = call_graph.transitive_callers(direct, None);
+        assert_eq!(paths.len(), 3);
+        assert_eq!(
+            paths[&method_fqdn("Demo.Client", "Subscriber", "Outer")].resolution,
+            CallResolution::Type
+        );
+        let top = &paths[&method_fqdn("Demo.Client", "Other", "Top")];
+        assert_eq!(top.resolution, CallResolution::Name);
+        assert_eq!(
+            top.symbols,
+            vec![
+                "Demo.Client.Other.Top".to_string(),
+                "Demo.Client.Subscriber.Outer".to_string(),
+                "Demo.Client.Subscriber.Run".to_string(),
+                "Demo.App.Publisher.Name".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_transitive_callers_max_depth() {
+        let call_graph = build_call_graph();
+        let direct = BTreeMap::from([(
+            method_fqdn("Demo.Client", "Subscriber", "Run"),
+            "Demo.App.Publisher.Name".to_string(),
+        )]);
+        let paths = call_graph.transitive_callers(direct, Some(2));
+        assert_eq!(paths.len(), 2);
+        assert!(!paths.contains_key(&method_fqdn("Demo.Client", "Other", "Top")));
+    }
+
+    #[test]
+    fn test_call_variables() {
+        let call_graph = build_call_graph();
+        let path = CallPath {
+            symbols: vec![
+                "Demo.Client.Subscriber.Outer".to_string(),
+                "Demo.Client.Subscriber.Run".to_string(),
+                "Demo.App.Publisher.Name".to_string(),
+            ],
+            resolution: CallResolution::Name,
+        };
+        let outer = call_graph.methods().nth(1).unwrap();
+        let var = call_variables(outer, &path);
+        assert_eq!(var["fqdn_method"], Value::from("Outer"));
+        assert_eq!(var["call_depth"], Value::from(2));
+        assert_eq!(var["callee"], Value::from("Demo.App.Publisher.Name"));
+        assert_eq!(var["call_path"].as_array().unwrap().len(), 3);
+        assert_eq!(var["call_resolution"], Value::from("name"));
+    }
+
+    #[test]
+    fn test_calls_resolved_by_qualifier_type() {
+        let (graph, lc) = graph_for_source(
+            r#"using System.Text;
+
+namespace Demo
+{
+    public class Formatter
+    {
+        public void Append(string value) { }
+    }
+
+    public class Repository
+    {
+        public string Get() { return ""; }
+    }
+
+    public class Controller
+    {
+        private Repository _repo;
+
+        public Repository Repo { get; set; }
+
+        public void Build()
+        {
+            StringBuilder sb = new StringBuilder();
+            sb.Append("x");
+        }
+
+        public string FromField() { return _repo.Get(); }
+
+        public string FromProperty() { return Repo.Get(); }
+
+        public void Untyped(dynamic formatter) { formatter.Append("x"); }
+    }
+}
+"#,
+        );
+        let call_graph =
+            CallGraph::new(&graph, &lc.source_type_node_info, &QueryOptions::default()).unwrap();
+        let callees = |method: &str| {
+            call_graph
+                .callees
+                .get(&method_fqdn("Demo", "Controller", method))
+                .cloned()
+                .unwrap_or_default()
+        };
+        let get = method_fqdn("Demo", "Repository", "Get");
+        let append = method_fqdn("Demo", "Formatter", "Append");
+
+        // The only Append in source is not a method of StringBuilder.
+        assert!(callees("Build").is_empty());
+        assert_eq!(
+            callees("FromField"),
+            BTreeMap::from([(get.clone(), CallResolution::Type)])
+        );
+        assert_eq!(
+            callees("FromProperty"),
+            BTreeMap::from([(get, CallResolution::Type)])
+        );
+        assert_eq!(
+            callees("Untyped"),
+            BTreeMap::from([(append, CallResolution::Name)])
+        );
+    }
+
+    #[test]
+    fn test_strip_type_arguments() {
+        assert_eq!(
+            strip_type_arguments("SetInitializer<UsersContext>"),
+            "SetInitializer"
+        );
+        assert_eq!(strip_type_arguments("Run"), "Run");
+    }
+}
@@ -9,7 +9,7 @@ use tracing::{debug, trace};
 
 use crate::c_sharp_graph::{
     namespace_query::NotFoundError,
-    query::{get_fqdn, Fqdn, GetMatcher, Search, SymbolMatcher, SyntaxType},
+    query::{get_fqdn, Fqdn, GetMatcher, QueryOptions, Search, SymbolMatcher, SyntaxType},
 };
 
 pub(crate) struct ClassSymbolsGetter {}
@@ -21,12 +21,13 @@ impl GetMatcher for ClassSymbolsGetter {
         stack_graphs: &StackGraph,
         definition_root_nodes: Vec<Handle<Node>>,
         search: &Search,
+        options: &QueryOptions,
     ) -> Result<Self::Matcher, Error>
     where
         Self: std::marker::Sized,
     {
         debug!("getting FieldSymbols matcher");
-        ClassSymbols::new(stack_graphs, definition_root_nodes, search)
+        ClassSymbols::new(stack_graphs, definition_root_nodes, search, options)
     }
 }
 
@@ -41,12 +42,13 @@ impl ClassSymbols {
         graph: &StackGraph,
         nodes: Vec<Handle<Node>>,
         search: &Search,
+        options: &QueryOptions,
     ) -> anyhow::Result<ClassSymbols, Error> {
         let mut classes: BTreeMap<Fqdn, Handle<Node>> = BTreeMap::new();
 
         for node_handle in nodes {
             //Get all the edges
-            Self::traverse_node(graph, node_handle, search, &mut classes)
+            Self::traverse_node(graph, node_handle, search, options, &mut classes)?;
         }
 
         if classes.is_empty() {
@@ -64,12 +66,19 @@ impl SymbolMatcher for ClassSymbols {
         self.symbol_in_namespace(symbol)
     }
     fn match_fqdn(&self, fqdn: &Fqdn) -> bool {
-        if (fqdn.method.is_some() || fqdn.field.is_some()) && fqdn.class.is_some() {
+        if (fqdn.method.is_some()
+            || fqdn.field.is_some()
+            || fqdn.property.is_some()
+            || fqdn.event.is_some())
+            && fqdn.class.is_some()
+        {
             return self.classes.contains_key(&Fqdn {
                 namespace: fqdn.namespace.clone(),
                 class: fqdn.class.clone(),
                 method: None,
                 field: None,
+                property: None,
+                event: None,
             });
         }
         self.classes.contains_key(fqdn)
@@ -82,8 +91,10 @@ impl ClassSymbols {
         graph: &StackGraph,
         node: Handle<Node>,
         search: &Search,
+        options: &QueryOptions,
         classes: &mut BTreeMap<Fqdn, Handle<Node>>,
-    ) {
+    ) -> anyhow::Result<(), Error> {
+        options.check_cancelled("traverse_node")?;
         // Collect and sort all edges first to ensure deterministic iteration
         let mut edges: Vec<_> = graph.outgoing_edges(node).collect();
         edges.sort_by_key(|e| e.sink);
@@ -127,8 +138,9 @@ impl ClassSymbols {
         }
         // Recursively traverse child edges (already in sorted order)
         for child_edge in child_edges {
-            Self::traverse_node(graph, child_edge, search, classes);
+            Self::traverse_node(graph, child_edge, search, options, classes)?;
         }
+        Ok(())
     }
 
     // Symbol here must be of <thing>.<method_name>.
@@ -207,7 +219,7 @@ mod tests {
         let (graph, roots) = build_mock_graph_with_classes();
         let search = Search::create_search("*".to_string()).unwrap();
 
-        let result = ClassSymbols::new(&graph, roots, &search);
+        let result = ClassSymbols::new(&graph, roots, &search, &QueryOptions::default());
         assert!(result.is_ok());
 
         let class_symbols = result.unwrap();
@@ -218,7 +230,8 @@ mod tests {
     fn test_class_symbols_match_symbol() {
         let (graph, roots) = build_mock_graph_with_classes();
         let search = Search::create_search("*".to_string()).unwrap();
-        let class_symbols = ClassSymbols::new(&graph, roots, &search).unwrap();
+        let class_symbols =
+            ClassSymbols::new(&graph, roots, &search, &QueryOptions::default()).unwrap();
 
         assert!(class_symbols.match_symbol("String".to_string()));
         assert!(class_symbols.match_symbol("StringBuilder".to_string()));
@@ -229,13 +242,16 @@ mod tests {
     fn test_class_symbols_match_fqdn_exact() {
         let (graph, roots) = build_mock_graph_with_classes();
         let search = Search::create_search("*".to_string()).unwrap();
-        let class_symbols = ClassSymbols::new(&graph, roots, &search).unwrap();
+        let class_symbols =
+            ClassSymbols::new(&graph, roots, &search, &QueryOptions::default()).unwrap();
 
         let fqdn = Fqdn {
             namespace: Some("System".to_string()),
             class: Some("String".to_string()),
             method: None,
             field: None,
+            property: None,
+            event: None,
         };
 
         assert!(class_symbols.match_fqdn(&fqdn));
@@ -245,7 +261,8 @@ mod tests {
     fn test_class_symbols_match_fqdn_with_method() {
         let (graph, roots) = build_mock_graph_with_classes();
         let search = Search::create_search("*".to_string()).unwrap();
-        let class_symbols = ClassSymbols::new(&graph, roots, &search).unwrap();
+        let class_symbols =
+            ClassSymbols::new(&graph, roots, &search, &QueryOptions::default()).unwrap();
 
         // When FQDN has method, should still match the class part
         let fqdn = Fqdn {
@@ -253,6 +270,8 @@ mod tests {
             class: Some("String".to_string()),
             method: Some("Format".to_string()),
             field: None,
+            property: None,
+            event: None,
         };
 
         assert!(class_symbols.match_fqdn(&fqdn));
@@ -262,7 +281,8 @@ mod tests {
     fn test_class_symbols_match_fqdn_with_field() {
         let (graph, roots) = build_mock_graph_with_classes();
         let search = Search::create_search("*".to_string()).unwrap();
-        let class_symbols = ClassSymbols::new(&graph, roots, &search).unwrap();
+        let class_symbols =
+            ClassSymbols::new(&graph, roots, &search, &QueryOptions::default()).unwrap();
 
         // When FQDN has field, should still match the class part
         let fqdn = Fqdn {
@@ -270,6 +290,8 @@ mod tests {
             class: Some("String".to_string()),
             field: Some("Empty".to_string()),
             method: None,
+            property: None,
+            event: None,
         };
 
         assert!(class_symbols.match_fqdn(&fqdn));
@@ -279,13 +301,16 @@ mod tests {
     fn test_class_symbols_no_match_fqdn() {
         let (graph, roots) = build_mock_graph_with_classes();
         let search = Search::create_search("*".to_string()).unwrap();
-        let class_symbols = ClassSymbols::new(&graph, roots, &search).unwrap();
+        let class_symbols =
+            ClassSymbols::new(&graph, roots, &search, &QueryOptions::default()).unwrap();
 
         let fqdn = Fqdn {
             namespace: Some("System".to_string()),
             class: Some("NonExistent".to_string()),
             method: None,
             field: None,
+            property: None,
+            event: None,
         };
 
         assert!(!class_symbols.match_fqdn(&fqdn));
@@ -297,7 +322,8 @@ mod tests {
         // Only match "String" not "StringBuilder"
         let search = Search::create_search("System.String".to_string()).unwrap();
 
-        let class_symbols = ClassSymbols::new(&graph, roots, &search).unwrap();
+        let class_symbols =
+            ClassSymbols::new(&graph, roots, &search, &QueryOptions::default()).unwrap();
 
         // Should only have String, not StringBuilder
         assert_eq!(class_symbols.classes.len(), 1);
@@ -0,0 +1,338 @@
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+
+use serde_json::Value;
+use stack_graphs::{
+    arena::Handle,
+    graph::{File, Node, StackGraph},
+};
+use tracing::debug;
+
+use crate::c_sharp_graph::{
+    query::{get_fqdn, get_location, get_syntax_type, SyntaxType},
+    results::Location,
+};
+
+const MODIFIERS: &[&str] = &[
+    "public",
+    "private",
+    "protected",
+    "internal",
+    "static",
+    "virtual",
+    "override",
+    "abstract",
+    "sealed",
+    "async",
+    "extern",
+    "unsafe",
+    "new",
+    "readonly",
+    "partial",
+    "const",
+    "volatile",
+    "required",
+    "file",
+];
+
+/// A namespace, type or member declared in a file.
+#[derive(Debug)]
+struct Declaration {
+    node: Handle<Node>,
+    syntax_type: SyntaxType,
+    location: Location,
+}
+
+/// The declarations of a file, used to tell the namespace, type and member that an incident is
+/// in, along with the signature and accessibility of the innermost one.
+pub(crate) struct DeclarationContext<'a> {
+    graph: &'a StackGraph,
+    declarations: Vec<Declaration>,
+    /// The location of the compilation unit, the generated class and method of top level
+    /// statements span the whole file and have no signature or accessibility in the source.
+    comp_unit: Option<Location>,
+    imports: Vec<Location>,
+    source: Option<String>,
+    line_starts: Vec<usize>,
+}
+
+impl<'a> DeclarationContext<'a> {
+    pub(crate) fn new(graph: &'a StackGraph, file: Handle<File>) -> DeclarationContext<'a> {
+        let mut declarations = vec![];
+        let mut comp_unit = None;
+        let mut imports = vec![];
+        for node in graph.nodes_for_file(file) {
+            if !graph[node].is_definition() {
+                continue;
+            }
+            let Some(syntax_type) = get_syntax_type(graph, node) else {
+                continue;
+            };
+            let Some(location) = get_location(graph, node) else {
+                continue;
+            };
+            match syntax_type {
+                SyntaxType::CompUnit => comp_unit = Some(location),
+                SyntaxType::Import => imports.push(location),
+                SyntaxType::NamespaceDeclaration
+                | SyntaxType::ClassDef
+                | SyntaxType::MethodName
+                | SyntaxType::PropertyName
+                | SyntaxType::EventName => declarations.push(Declaration {
+                    node,
+                    syntax_type,
+                    location,
+                }),
+                _ => {}
+            }
+        }
+        // The signature is read from the file, the graph only has the names.
+        let source = match std::fs::read_to_string(graph[file].name()) {
+            Ok(source) => Some(source),
+            Err(e) => {
+                debug!(
+                    "unable to read {} for signatures: {}",
+                    graph[file].name(),
+                    e
+                );
+                None
+            }
+        };
+        let mut line_starts = vec![0];
+        if let Some(source) = &source {
+            line_starts.extend(source.match_indices('\n').map(|(i, _)| i + 1));
+        }
+        DeclarationContext {
+            graph,
+            declarations,
+            comp_unit,
+            imports,
+            source,
+            line_starts,
+        }
+    }
+
+    /// The `enclosing_*` variables for an incident at the location, a declaration is not its
+    /// own enclosing declaration.
+    pub(crate) fn variables(&self, location: &Location) -> BTreeMap<String, Value> {
+        let mut var: BTreeMap<String, Value> = BTreeMap::new();
+        // Using directives come before the top level statements.
+        let in_import = self.imports.iter().any(|i| i.contains(location));
+        // The innermost declaration starts last, a member goes before the generated class of
+        // top level statements that has the same span.
+        let Some(enclosing) = self
+            .declarations
+            .iter()
+            .filter(|d| d.location != *location && d.location.contains(location))
+            .filter(|d| !in_import || self.comp_unit.as_ref() != Some(&d.location))
+            .max_by_key(|d| {
+                (
+                    d.location.start_position.clone(),
+                    Reverse(d.location.end_position.clone()),
+                    rank(&d.syntax_type),
+                )
+            })
+        else {
+            return var;
+        };
+        let Some(fqdn) = get_fqdn(enclosing.node, self.graph) else {
+            return var;
+        };
+        if let Some(namespace) = fqdn.namespace {
+            var.insert("enclosing_namespace".to_string(), Value::from(namespace));
+        }
+        if let Some(class) = fqdn.class {
+            var.insert("enclosing_type".to_string(), Value::from(class));
+        }
+        if let Some(member) = fqdn.method.or(fqdn.property).or(fqdn.event) {
+            var.insert("enclosing_member".to_string(), Value::from(member));
+        }
+        if enclosing.syntax_type == SyntaxType::NamespaceDeclaration
+            || self.comp_unit.as_ref() == Some(&enclosing.location)
+        {
+            return var;
+        }
+
+        let header = self.header(&enclosing.location);
+        if enclosing.syntax_type != SyntaxType::ClassDef && !header.is_empty() {
+            var.insert(
+                "enclosing_signature".to_string(),
+                Value::from(header.clone()),
+            );
+        }
+        let accessibility = accessibility(&header).unwrap_or_else(|| {
+            // Types declared in a namespace default to internal, everything else to private.
+            let nested = self.declarations.iter().any(|d| {
+                d.syntax_type != SyntaxType::NamespaceDeclaration
+                    && d.location != enclosing.location
+                    && d.location.contains(&enclosing.location)
+            });
+            if enclosing.syntax_type == SyntaxType::ClassDef && !nested {
+                "internal"
+            } else {
+                "private"
+            }
+        });
+        var.insert(
+            "enclosing_accessibility".to_string(),
+            Value::from(accessibility),
+        );
+        var
+    }
+
+    /// The declaration text up to its body.
+    fn header(&self, location: &Location) -> String {
+        let Some(source) = &self.source else {
+            return String::new();
+        };
+        let offset = |line: usize, character: usize| {
+            self.line_starts
+                .get(line)
+                .map(|start| (start + character).min(source.len()))
+        };
+        let start = offset(
+            location.start_position.line,
+            location.start_position.character,
+        );
+        let end = offset(location.end_position.line, location.end_position.character);
+        match (start, end) {
+            (Some(start), Some(end)) if start <= end => {
+                source.get(start..end).map(header).unwrap_or_default()
+            }
+            _ => String::new(),
+        }
+    }
+}
+
+/// Members are the innermost of declarations with the same span.
+fn rank(syntax_type: &SyntaxType) -> u8 {
+    match syntax_type {
+        SyntaxType::NamespaceDeclaration => 0,
+        SyntaxType::ClassDef => 1,
+        _ => 2,
+    }
+}
+
+/// The text of a declaration without its attributes and body, with the whitespace collapsed.
+fn header(declaration: &str) -> String {
+    let mut rest = declaration.trim_start();
+    // Skip the attribute lists.
+    while rest.starts_with('[') {
+        let Some(end) = closing(rest, '[', ']') else {
+            return String::new();
+        };
+        rest = rest[end + 1..].trim_start();
+    }
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut end = rest.len();
+    let mut chars = rest.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        if in_string {
+            match c {
+                '\\' => {
+                    chars.next();
+                }
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth -= 1,
+            '{' | ';' if depth == 0 => {
+                end = i;
+                break;
+            }
+            '=' if depth == 0 && chars.peek().is_some_and(|(_, n)| *n == '>') => {
+                end = i;
+                break;
+            }
+            _ => {}
+        }
+    }
+    rest[..end].split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// The byte offset of the bracket that closes the one `text` starts with.
+fn closing(text: &str, open: char, close: char) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in text.char_indices() {
+        if c == open {
+            depth += 1;
+        } else if c == close {
+            depth -= 1;
+            if depth == 0 {
+                return Some(i);
+            }
+        }
+    }
+    None
+}
+
+/// The accessibility from the modifiers a declaration header starts with.
+fn accessibility(header: &str) -> Option<&'static str> {
+    let modifiers: Vec<&str> = header
+        .split_whitespace()
+        .take_while(|w| MODIFIERS.contains(w))
+        .collect();
+    let has = |m: &str| modifiers.contains(&m);
+    if has("public") {
+        Some("public")
+    } else if has("protected") && has("internal") {
+        Some("protected internal")
+    } else if has("private") && has("protected") {
+        Some("private protected")
+    } else if has("protected") {
+        Some("protected")
+    } else if has("internal") {
+        Some("internal")
+    } else if has("private") {
+        Some("private")
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_header() {
+        assert_eq!(
+            header("[HttpPost]\n        [ValidateAntiForgeryToken]\n        public ActionResult Login(LoginModel model, string returnUrl)\n        {\n            return View();\n        }"),
+            "public ActionResult Login(LoginModel model, string returnUrl)"
+        );
+        assert_eq!(
+            header("public string Name { get; set; }"),
+            "public string Name"
+        );
+        assert_eq!(
+            header("int Double(int x, string s = \"{\") => x * 2;"),
+            "int Double(int x, string s = \"{\")"
+        );
+        assert_eq!(
+            header("public class Dinner<T> : Base where T : new()\n{\n}"),
+            "public class Dinner<T> : Base where T : new()"
+        );
+    }
+
+    #[test]
+    fn test_accessibility() {
+        assert_eq!(accessibility("public static void Main()"), Some("public"));
+        assert_eq!(
+            accessibility("internal protected override void Run()"),
+            Some("protected internal")
+        );
+        assert_eq!(
+            accessibility("private protected int Count"),
+            Some("private protected")
+        );
+        assert_eq!(accessibility("static void Local()"), None);
+        // Only the leading modifiers count.
+        assert_eq!(accessibility("void Public(int internal)"), None);
+    }
+}
@@ -28,13 +28,6 @@ pub struct NodeInfo {
     syntax_type: SyntaxType,
 }
 
-#[derive(Debug, PartialEq, Eq)]
-pub struct EdgeInfo {
-    source: NodeInfo,
-    sink: NodeInfo,
-    precedence: i32,
-}
-
 impl FileAnalyzer for DepXMLFileAnalyzer {
     #[allow(clippy::needless_lifetimes)]
     fn build_stack_graph_into<'a>(
@@ -306,7 +299,7 @@ impl DepXMLFileAnalyzer {
                 };
                 vec![node]
             }
-            // type, field and property
+            // type
             "T" => {
                 if name.is_empty() {
                     return vec![];
@@ -343,21 +336,29 @@ impl DepXMLFileAnalyzer {
                 nodes.push(namesapce_node.clone());
                 nodes
             }
-            "F" | "P" => {
+            // field, property and event
+            "F" | "P" | "E" => {
                 if name.is_empty() {
                     return vec![];
                 }
-                let mut parts = name.split('.');
+                let syntax_type = match member_type {
+                    "P" => SyntaxType::PropertyName,
+                    "E" => SyntaxType::EventName,
+                    _ => SyntaxType::FieldName,
+                };
+                // Indexers are properties with parameters, for now we can ignore the parameters.
+                let new_name = name.split('(').next().unwrap_or(name);
+                let mut parts = new_name.split('.');
                 let mut nodes: Vec<NodeInfo> = vec![];
                 let part = parts.next_back();
                 if part.is_none() {
                     return vec![];
                 }
-                let field_name = NodeInfo {
+                let member_name = NodeInfo {
                     symbol: part.unwrap().to_string(),
-                    syntax_type: SyntaxType::FieldName,
+                    syntax_type,
                 };
-                nodes.push(field_name.clone());
+                nodes.push(member_name.clone());
                 let part = parts.next_back();
                 if part.is_none() {
                     return vec![];
@@ -576,16 +577,43 @@ mod tests {
     }
 
     #[test]
-    fn test_handle_member_property_same_as_field() {
+    fn test_handle_member_property() {
         let analyzer = create_analyzer();
         let nodes_f = analyzer.handle_member("F", "System.Console.Out");
         let nodes_p = analyzer.handle_member("P", "System.Console.Out");
 
-        // Field and Property should be handled identically
+        // Property has the same shape as a field but its own syntax type
         assert_eq!(nodes_f.len(), nodes_p.len());
 
         assert_eq!(nodes_f[0].symbol, nodes_p[0].symbol);
-        assert_eq!(nodes_f[0].syntax_type, nodes_p[0].syntax_type);
+        assert_eq!(nodes_p[0].syntax_type, SyntaxType::PropertyName);
+        assert_eq!(nodes_f[1..], nodes_p[1..]);
+    }
+
+    #[test]
+    fn test_handle_member_indexer_property() {
+        let analyzer = create_analyzer();
+        let nodes = analyzer.handle_member("P", "System.Collections.ArrayList.Item(System.Int32)");
+
+        assert_eq!(nodes.len(), 3);
+        assert_eq!(nodes[0].symbol, "Item");
+        assert_eq!(nodes[0].syntax_type, SyntaxType::PropertyName);
+        assert_eq!(nodes[1].symbol, "ArrayList");
+        assert_eq!(nodes[2].symbol, "System.Collections");
+    }
+
+    #[test]
+    fn test_handle_member_event() {
+        let analyzer = create_analyzer();
+        let nodes = analyzer.handle_member("E", "System.Web.HttpApplication.BeginRequest");
+
+        assert_eq!(nodes.len(), 3);
+        assert_eq!(nodes[0].symbol, "BeginRequest");
+        assert_eq!(nodes[0].syntax_type, SyntaxType::EventName);
+        assert_eq!(nodes[1].symbol, "HttpApplication");
+        assert_eq!(nodes[1].syntax_type, SyntaxType::ClassDef);
+        assert_eq!(nodes[2].symbol, "System.Web");
+        assert_eq!(nodes[2].syntax_type, SyntaxType::NamespaceDeclaration);
     }
 
     #[test]
@@ -0,0 +1,126 @@
+use anyhow::{Error, Ok};
+use stack_graphs::{
+    arena::Handle,
+    graph::{Node, StackGraph},
+};
+use tracing::debug;
+
+use crate::c_sharp_graph::{
+    member_query::MemberSymbols,
+    query::{Fqdn, GetMatcher, QueryOptions, Search, SymbolMatcher, SyntaxType},
+};
+
+pub(crate) struct EventSymbolsGetter {}
+
+impl GetMatcher for EventSymbolsGetter {
+    type Matcher = EventSymbols;
+
+    fn get_matcher(
+        stack_graphs: &StackGraph,
+        definition_root_nodes: Vec<Handle<Node>>,
+        search: &Search,
+        options: &QueryOptions,
+    ) -> Result<Self::Matcher, Error>
+    where
+        Self: std::marker::Sized,
+    {
+        debug!("getting EventSymbols matcher");
+        EventSymbols::new(stack_graphs, definition_root_nodes, search, options)
+    }
+
+    fn reports_properties_and_events() -> bool {
+        true
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct EventSymbols {
+    events: MemberSymbols,
+}
+
+// Create exposed methods for NamesapceSymbols
+impl EventSymbols {
+    pub(crate) fn new(
+        graph: &StackGraph,
+        nodes: Vec<Handle<Node>>,
+        search: &Search,
+        options: &QueryOptions,
+    ) -> anyhow::Result<EventSymbols, Error> {
+        let events = MemberSymbols::new(graph, nodes, search, &[SyntaxType::EventName], options)?;
+        Ok(EventSymbols { events })
+    }
+}
+
+impl SymbolMatcher for EventSymbols {
+    fn match_symbol(&self, symbol: String) -> bool {
+        self.events
+            .symbol_in_namespace(symbol, |fqdn| fqdn.event.as_ref())
+    }
+    fn match_fqdn(&self, fqdn: &Fqdn) -> bool {
+        self.events.contains(fqdn)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::c_sharp_graph::member_query::tests::build_mock_graph_with_members;
+    use crate::c_sharp_graph::query::Search;
+
+    fn build_mock_graph_with_events() -> (StackGraph, Vec<Handle<Node>>) {
+        build_mock_graph_with_members(
+            "System.Web",
+            "HttpApplication",
+            &[("BeginRequest", "event_name"), ("Context", "property_name")],
+        )
+    }
+
+    #[test]
+    fn test_event_symbols_new_only_events() {
+        let (graph, roots) = build_mock_graph_with_events();
+        let search = Search::create_search("*".to_string()).unwrap();
+
+        let event_symbols =
+            EventSymbols::new(&graph, roots, &search, &QueryOptions::default()).unwrap();
+        assert_eq!(event_symbols.events.len(), 1);
+    }
+
+    #[test]
+    fn test_event_symbols_match_symbol() {
+        let (graph, roots) = build_mock_graph_with_events();
+        let search = Search::create_search("*".to_string()).unwrap();
+        let event_symbols =
+            EventSymbols::new(&graph, roots, &search, &QueryOptions::default()).unwrap();
+
+        assert!(event_symbols.match_symbol("HttpApplication.BeginRequest".to_string()));
+        assert!(!event_symbols.match_symbol("HttpApplication.Context".to_string()));
+    }
+
+    #[test]
+    fn test_event_symbols_match_fqdn() {
+        let (graph, roots) = build_mock_graph_with_events();
+        let search =
+            Search::create_search("System.Web.HttpApplication.BeginRequest".to_string()).unwrap();
+        let event_symbols =
+            EventSymbols::new(&graph, roots, &search, &QueryOptions::default()).unwrap();
+
+        let fqdn = Fqdn {
+            namespace: Some("System.Web".to_string()),
+            class: Some("HttpApplication".to_string()),
+            method: None,
+            field: None,
+            property: None,
+            event: Some("BeginRequest".to_string()),
+        };
+        assert!(event_symbols.match_fqdn(&fqdn));
+    }
+
+    #[test]
+    fn test_event_symbols_not_found() {
+        let (graph, roots) = build_mock_graph_with_events();
+        let search = Search::create_search("*.Context".to_string()).unwrap();
+
+        let result = EventSymbols::new(&graph, roots, &search, &QueryOptions::default());
+        assert!(result.is_err());
+    }
+}
@@ -16,6 +16,9 @@ use tree_sitter_stack_graphs::FILE_PATH_VAR;
 
 use crate::c_sharp_graph::loader::SourceType;
 use crate::c_sharp_graph::loader::SOURCE_TYPE_NODE;
+use crate::c_sharp_graph::razor_analyzer::{RazorFileAnalyzer, RAZOR_FILE_EXTENSIONS};
+use crate::c_sharp_graph::view::view_file_key;
+use crate::c_sharp_graph::web_forms_analyzer::{WebFormsFileAnalyzer, WEB_FORMS_FILE_EXTENSIONS};
 
 pub const STACK_GRAPHS_TSG_PATH: &str = "src/stack-graphs.tsg";
 /// The stack graphs tsg source for this language.
@@ -30,6 +33,40 @@ pub const STACK_GRAPHS_BUILTINS_SOURCE: &str = include_str!("builtins.cs");
 
 const BUILTINS_FILENAME: &str = "<builtins>";
 
+fn c_sharp_language() -> Result<StackGraphLanguage, Error> {
+    let sgl = StackGraphLanguage::from_source(
+        tree_sitter_c_sharp::LANGUAGE.into(),
+        STACK_GRAPHS_TSG_PATH.into(),
+        STACK_GRAPHS_TSG_SOURCE,
+    )
+    .map_err(|err| LoadError::SglParse {
+        inner: err,
+        tsg_path: STACK_GRAPHS_TSG_PATH.into(),
+        tsg: Cow::from(STACK_GRAPHS_TSG_SOURCE),
+    })?;
+    Ok(sgl)
+}
+
+/// The analyzers of the Razor and Web Forms views, registered by extension with
+/// [`view_file_key`]. The views are built with the C# TSG, the analyzers keep their own copy of
+/// the language as the special files can not borrow from the configuration.
+fn view_file_analyzers(sgl: Arc<StackGraphLanguage>) -> FileAnalyzers {
+    let mut analyzers = FileAnalyzers::new();
+    for extension in RAZOR_FILE_EXTENSIONS {
+        analyzers.add(
+            view_file_key(extension),
+            RazorFileAnalyzer { sgl: sgl.clone() },
+        );
+    }
+    for extension in WEB_FORMS_FILE_EXTENSIONS {
+        analyzers.add(
+            view_file_key(extension),
+            WebFormsFileAnalyzer { sgl: sgl.clone() },
+        );
+    }
+    analyzers
+}
+
 pub struct SourceNodeLanguageConfiguration {
     pub language_config: LanguageConfiguration,
     pub source_type_node_info: Arc<SourceType>,
@@ -40,16 +77,7 @@ impl SourceNodeLanguageConfiguration {
     pub fn new(
         cancellation_flag: &dyn CancellationFlag,
     ) -> Result<SourceNodeLanguageConfiguration, Error> {
-        let sgl = StackGraphLanguage::from_source(
-            tree_sitter_c_sharp::LANGUAGE.into(),
-            STACK_GRAPHS_TSG_PATH.into(),
-            STACK_GRAPHS_TSG_SOURCE,
-        )
-        .map_err(|err| LoadError::SglParse {
-            inner: err,
-            tsg_path: STACK_GRAPHS_TSG_PATH.into(),
-            tsg: Cow::from(STACK_GRAPHS_TSG_SOURCE),
-        })?;
+        let sgl = c_sharp_language()?;
         let mut builtins = StackGraph::new();
         let (source_type_node_info, dependnecy_type_node_info) =
             SourceType::load_symbols_into_graph(&mut builtins);
@@ -125,7 +153,7 @@ impl SourceNodeLanguageConfiguration {
             file_types: vec![String::from("cs")],
             sgl,
             builtins,
-            special_files: FileAnalyzers::new(),
+            special_files: view_file_analyzers(Arc::new(c_sharp_language()?)),
             no_similar_paths_in_file: false,
         };
         //let loader = Loader::from_language_configurations(vec![lc], None)?;
@@ -0,0 +1,300 @@
+use std::collections::BTreeMap;
+
+use anyhow::{Error, Ok};
+use regex::Regex;
+use serde_json::Value;
+use stack_graphs::{
+    arena::Handle,
+    graph::{File, Node, StackGraph},
+};
+use tracing::{debug, info};
+
+use crate::c_sharp_graph::{
+    declaration_context::DeclarationContext,
+    loader::SourceType,
+    query::{
+        get_file_uri, get_location, get_syntax_type, is_source_file, Query, QueryType, SyntaxType,
+    },
+    results::{Position, ResultNode},
+};
+
+/// Restricts a literal search to the literals passed directly as an argument (or indexer
+/// argument) to something that matches the referenced pattern.
+pub struct ArgumentOf<'graph> {
+    pub target: QueryType<'graph>,
+    pub pattern: String,
+}
+
+/// Finds string literals whose contents match the query regex.
+pub struct LiteralQuery<'graph> {
+    pub graph: &'graph StackGraph,
+    pub source_type: &'graph SourceType,
+    pub argument_of: Option<ArgumentOf<'graph>>,
+}
+
+impl Query for LiteralQuery<'_> {
+    fn query(self, query: String) -> anyhow::Result<Vec<ResultNode>, Error> {
+        let regex = Regex::new(&query)?;
+
+        // The call sites are keyed by file and the start of the invoked expression, this is what
+        // the TSG records on the literal arguments.
+        let call_sites: Option<BTreeMap<(String, Position), String>> = match self.argument_of {
+            None => None,
+            Some(argument_of) => {
+                // This will return a NotFoundError when nothing references the pattern.
+                let references = argument_of.target.query(argument_of.pattern)?;
+                Some(
+                    references
+                        .iter()
+                        .map(|r| {
+                            (
+                                (r.file_uri.clone(), r.code_location.start_position.clone()),
+                                r.get_fqdn_symbol(),
+                            )
+                        })
+                        .collect(),
+                )
+            }
+        };
+
+        let mut results: Vec<ResultNode> = vec![];
+        let mut files: Vec<Handle<File>> = self.graph.iter_files().collect();
+        files.sort();
+        for file in files {
+            let mut file_nodes: Vec<Handle<Node>> = self.graph.nodes_for_file(file).collect();
+            file_nodes.sort();
+            let Some(comp_unit) = file_nodes
+                .iter()
+                .find(|n| get_syntax_type(self.graph, **n) == Some(SyntaxType::CompUnit))
+            else {
+                continue;
+            };
+            if !is_source_file(self.graph, self.source_type, file, *comp_unit) {
+                continue;
+            }
+            let Some(file_uri) = get_file_uri(self.graph, file) else {
+                continue;
+            };
+            let mut context: Option<DeclarationContext> = None;
+            for node_handle in file_nodes {
+                if get_syntax_type(self.graph, node_handle) != Some(SyntaxType::StringLiteral) {
+                    continue;
+                }
+                let Some(symbol) = self.graph[node_handle].symbol() else {
+                    continue;
+                };
+                let symbol = self.graph[symbol].to_string();
+                let value = literal_value(&symbol);
+                if !regex.is_match(&value) {
+                    continue;
+                }
+                let argument_of = match &call_sites {
+                    None => None,
+                    Some(call_sites) => {
+                        let Some(call_site) = argument_position(self.graph, node_handle) else {
+                            continue;
+                        };
+                        match call_sites.get(&(file_uri.clone(), call_site)) {
+                            None => continue,
+                            Some(fqdn) => Some(fqdn.clone()),
+                        }
+                    }
+                };
+                let Some(code_location) = get_location(self.graph, node_handle) else {
+                    continue;
+                };
+
+                let mut var: BTreeMap<String, Value> = BTreeMap::new();
+                var.insert("file".to_string(), Value::from(file_uri.clone()));
+                var.insert("symbol".to_string(), Value::from(symbol));
+                var.insert("value".to_string(), Value::from(value));
+                var.insert(
+                    "syntax_type".to_string(),
+                    Value::from(SyntaxType::StringLiteral.to_string()),
+                );
+                if let Some(argument_of) = argument_of {
+                    var.insert("argument_of".to_string(), Value::from(argument_of));
+                }
+                let context =
+                    context.get_or_insert_with(|| DeclarationContext::new(self.graph, file));
+                var.extend(context.variables(&code_location));
+                results.push(ResultNode {
+                    file_uri: file_uri.clone(),
+                    line_number: code_location.start_position.line,
+                    variables: var,
+                    code_location,
+                });
+            }
+        }
+        results.sort();
+        info!("found {} string literals for '{}'", results.len(), query);
+        Ok(results)
+    }
+}
+
+/// The position of the invoked expression when the literal is an argument.
+fn argument_position(graph: &StackGraph, node: Handle<Node>) -> Option<Position> {
+    let debug_info = graph.node_debug_info(node)?;
+    let mut line = None;
+    let mut character = None;
+    for entry in debug_info.iter() {
+        match &graph[entry.key] {
+            "argument_of_line" => line = graph[entry.value].parse::<usize>().ok(),
+            "argument_of_column" => character = graph[entry.value].parse::<usize>().ok(),
+            _ => {}
+        }
+    }
+    debug!("literal argument of: {:?}:{:?}", line, character);
+    Some(Position {
+        line: line?,
+        character: character?,
+    })
+}
+
+/// Get the contents of a C# string literal from its source text, handling regular, verbatim
+/// (`@"..."`), raw (`"""..."""`) and interpolated (`$"..."`) literals as well as the `u8`
+/// suffix. The interpolations are kept as written.
+fn literal_value(text: &str) -> String {
+    let text = text.strip_suffix("u8").unwrap_or(text);
+    let (prefix, text) = text.split_at(text.find('"').unwrap_or(0));
+    if prefix.contains('@') {
+        let inner = text
+            .strip_prefix('"')
+            .and_then(|t| t.strip_suffix('"'))
+            .unwrap_or(text);
+        return inner.replace("\"\"", "\"");
+    }
+    let quotes = text.chars().take_while(|c| *c == '"').count();
+    if quotes >= 3 {
+        let inner = &text[quotes..text.len().saturating_sub(quotes).max(quotes)];
+        return inner.trim_matches('\n').to_string();
+    }
+    let inner = text
+        .strip_prefix('"')
+        .and_then(|t| t.strip_suffix('"'))
+        .unwrap_or(text);
+    let mut value = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            value.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => value.push('\n'),
+            Some('r') => value.push('\r'),
+            Some('t') => value.push('\t'),
+            Some('0') => value.push('\0'),
+            Some(other) => value.push(other),
+            None => value.push('\\'),
+        }
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_literal_value_regular() {
+        assert_eq!(literal_value(r#""DefaultConnection""#), "DefaultConnection");
+        assert_eq!(
+            literal_value(r#""a \"quoted\" value""#),
+            "a \"quoted\" value"
+        );
+        assert_eq!(literal_value(r#""C:\\temp""#), "C:\\temp");
+        assert_eq!(literal_value(r#""bytes"u8"#), "bytes");
+    }
+
+    #[test]
+    fn test_literal_value_verbatim() {
+        assert_eq!(literal_value(r#"@"C:\temp""#), "C:\\temp");
+        assert_eq!(literal_value(r#"@"say ""hi""""#), "say \"hi\"");
+    }
+
+    #[test]
+    fn test_literal_value_raw() {
+        assert_eq!(literal_value(r#""""raw "value"""""#), "raw \"value\"");
+        assert_eq!(
+            literal_value("\"\"\"\n  multi line\n  \"\"\""),
+            "  multi line\n  "
+        );
+    }
+
+    #[test]
+    fn test_literal_value_interpolated() {
+        assert_eq!(
+            literal_value(r#"$"https://{host}/api""#),
+            "https://{host}/api"
+        );
+        assert_eq!(literal_value(r#"$@"C:\{dir}""#), "C:\\{dir}");
+        assert_eq!(literal_value(r#"@$"C:\{dir}""#), "C:\\{dir}");
+        assert_eq!(literal_value(r#"$"{a}\t{b}""#), "{a}\t{b}");
+        assert_eq!(
+            literal_value("$\"\"\"{\"id\": {id}}\"\"\""),
+            "{\"id\": {id}}"
+        );
+    }
+
+    #[test]
+    fn test_interpolated_string_query() {
+        use tree_sitter_stack_graphs::NoCancellation;
+
+        use crate::c_sharp_graph::{
+            language_config::SourceNodeLanguageConfiguration, loader::load_graph_for_file,
+        };
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Client.cs");
+        std::fs::write(
+            &path,
+            r#"namespace Demo
+{
+    public class Client
+    {
+        public string Url(string host, int id)
+        {
+            var plain = "https://example.com/health";
+            return $"https://{host}/api/users/{id}";
+        }
+    }
+}
+"#,
+        )
+        .unwrap();
+        let lc = SourceNodeLanguageConfiguration::new(&NoCancellation).unwrap();
+        let mut graph = StackGraph::new();
+        let _ = graph.add_from_graph(&lc.language_config.builtins);
+        load_graph_for_file(
+            path,
+            &mut graph,
+            &lc.language_config,
+            &lc.source_type_node_info,
+            &NoCancellation,
+        )
+        .unwrap();
+
+        let query = |pattern: &str| {
+            LiteralQuery {
+                graph: &graph,
+                source_type: &lc.source_type_node_info,
+                argument_of: None,
+            }
+            .query(pattern.to_string())
+            .unwrap()
+        };
+        let results = query("/api/users/");
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].line_number, 7);
+        assert_eq!(
+            results[0].variables["value"],
+            Value::from("https://{host}/api/users/{id}")
+        );
+        assert_eq!(
+            results[0].variables["syntax_type"],
+            Value::from("string_literal")
+        );
+        assert_eq!(query("^https://").len(), 2);
+    }
+}
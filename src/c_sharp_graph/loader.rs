@@ -1,7 +1,12 @@
 use std::fmt::Debug;
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap, HashSet},
     path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc, Arc,
+    },
+    thread,
 };
 
 use anyhow::{anyhow, Error, Result};
@@ -11,16 +16,20 @@ use stack_graphs::{
     arena::Handle,
     graph::{File, NodeID, StackGraph, Symbol},
     partial::{PartialPath, PartialPaths},
-    storage::SQLiteWriter,
+    storage::{SQLiteReader, SQLiteWriter},
 };
 use tracing::{debug, error, info, trace};
 use tree_sitter_stack_graphs::{
     loader::{FileReader, LanguageConfiguration},
-    NoCancellation, Variables, FILE_PATH_VAR, ROOT_PATH_VAR,
+    CancellationFlag, FileAnalyzer, Variables, FILE_PATH_VAR, ROOT_PATH_VAR,
 };
 use walkdir::WalkDir;
 
+use crate::c_sharp_graph::view::view_file_key;
+
 pub const SOURCE_TYPE_NODE: &str = "SOURCE_TYPE_NODE";
+pub const TOP_LEVEL_CLASS: &str = "TOP_LEVEL_CLASS";
+pub const TOP_LEVEL_METHOD: &str = "TOP_LEVEL_METHOD";
 
 #[derive(PartialEq, Eq, Hash)]
 pub enum SourceType {
@@ -125,6 +134,7 @@ pub fn add_dir_to_graph(
     source_type: &SourceType,
     language_config: &LanguageConfiguration,
     original_graph: StackGraph,
+    cancellation_flag: &dyn CancellationFlag,
 ) -> Result<AsyncInitializeGraph, Error> {
     let mut stack_graph = original_graph;
     let mut files_loaded = 0;
@@ -158,6 +168,7 @@ pub fn add_dir_to_graph(
             &mut stack_graph,
             language_config,
             source_type,
+            cancellation_flag,
         ) {
             Ok(res) => match res {
                 Some((f, tag)) => {
@@ -186,7 +197,9 @@ pub fn load_graph_for_file(
     stack_graph: &mut StackGraph,
     language_config: &LanguageConfiguration,
     source_type: &SourceType,
+    cancellation_flag: &dyn CancellationFlag,
 ) -> Result<Option<(Handle<File>, String)>, Error> {
+    cancellation_flag.check("loading file")?;
     let mut file_reader = FileReader::new();
     debug!("loading file: {:?}", entry);
     let entry_parent = entry.parent().expect("parent path should be available");
@@ -206,12 +219,10 @@ pub fn load_graph_for_file(
         )
         .expect("failed to add root path variable");
 
-    let file_name = entry.file_name();
-    if file_name.is_none() {
+    if entry.file_name().is_none() {
         return Ok(None);
     }
-    let file_name = file_name.unwrap().to_string_lossy().to_string();
-    let analyzer_bulder = language_config.special_files.get(&file_name);
+    let analyzer_bulder = file_analyzer(&entry, language_config);
     let matches_file = language_config.matches_file(&entry, &mut file_reader)?;
 
     if analyzer_bulder.is_none() && !matches_file {
@@ -235,18 +246,34 @@ pub fn load_graph_for_file(
         }
     };
 
-    if analyzer_bulder.is_some() {
-        info!("trying to build with xml analyzer");
-        let analyzer_bulder = analyzer_bulder.unwrap();
-        analyzer_bulder.build_stack_graph_into(
+    if let Some(analyzer_bulder) = analyzer_bulder {
+        info!(file=?entry, "building with special file analyzer");
+        let analyzer_globals = HashMap::from([
+            (
+                FILE_PATH_VAR.to_string(),
+                entry.to_string_lossy().to_string(),
+            ),
+            (
+                ROOT_PATH_VAR.to_string(),
+                entry_parent.to_string_lossy().to_string(),
+            ),
+            (
+                SOURCE_TYPE_NODE.to_string(),
+                source_type_node_id.local_id().to_string(),
+            ),
+        ]);
+        if let Err(e) = analyzer_bulder.build_stack_graph_into(
             stack_graph,
             file,
             &entry,
             source,
-            &mut Box::new(::std::iter::empty()),
-            &HashMap::new(),
-            &NoCancellation,
-        )?;
+            &mut ::std::iter::empty(),
+            &analyzer_globals,
+            cancellation_flag,
+        ) {
+            error!("unable to build graph for {:?}: {:?}", entry, e);
+            return Err(anyhow!("unable to build graph"));
+        }
         Ok(Some((file, tag)))
     } else {
         let mut builder = language_config
@@ -257,7 +284,7 @@ pub fn load_graph_for_file(
             .add(SOURCE_TYPE_NODE.into(), graph_node.into())
             .expect("adding source type node");
 
-        let build_result = builder.build(&globals, &NoCancellation);
+        let build_result = builder.build(&globals, cancellation_flag);
         if let Err(e) = build_result {
             error!("unable to build graph for {:?}: {:?}", entry, e);
             return Err(anyhow!("unable to build graph"));
@@ -266,25 +293,163 @@ pub fn load_graph_for_file(
     }
 }
 
-pub fn init_stack_graph(
+/// What `update_stack_graph` changed in the database.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct IndexUpdate {
+    /// Files whose tag matches the file on disk.
+    pub unchanged: usize,
+    /// Files that were new or changed and have been indexed again.
+    pub indexed: usize,
+    /// Files that are no longer on disk and have been cleaned from the database.
+    pub removed: usize,
+}
+
+/// Build the graph of the files in `source_location` and store it in the database. The files
+/// are parsed and built on `workers` threads, each into a graph of its own, which is merged into
+/// the result and stored in the order the files were found, so the graph does not depend on the
+/// number of workers. `on_file` is called with the number of files built and the total as each
+/// file completes. The files stored before `cancellation_flag` is cancelled stay in the database.
+pub fn init_stack_graph<F>(
     source_location: &Path,
     db_path: &Path,
     source_type: &SourceType,
     language_config: &LanguageConfiguration,
-) -> Result<InitializedGraph, Error> {
+    workers: usize,
+    cancellation_flag: &(dyn CancellationFlag + Sync),
+    on_file: F,
+) -> Result<InitializedGraph, Error>
+where
+    F: Fn(usize, usize),
+{
     let mut db: SQLiteWriter = SQLiteWriter::open(db_path)?;
 
     let mut files_loaded = 0;
+    let entries = walk_files(source_location)?;
 
     let mut stack_graph = StackGraph::new();
     let _ = stack_graph.add_from_graph(&language_config.builtins);
+    build_files(
+        &entries,
+        source_type,
+        language_config,
+        workers,
+        cancellation_flag,
+        on_file,
+        |entry_path, file_graph, tag| {
+            store_file_graph(&mut db, &file_graph, tag)?;
+            let files = match stack_graph.add_from_graph(&file_graph) {
+                Ok(files) => files,
+                Err(_) => return Err(anyhow!("file already in the graph: {:?}", entry_path)),
+            };
+            files_loaded += 1;
+            debug!("loaded file handles: {:?} - file: {:?}", files, entry_path);
+            Ok(())
+        },
+    )?;
+
+    Ok(InitializedGraph {
+        files_loaded,
+        stack_graph,
+    })
+}
+
+/// Bring the files of `source_location` in an existing database up to date with the files on
+/// disk, comparing the stored tags with the SHA1 of the sources. New and changed files are built
+/// again the same way `init_stack_graph` builds them, and stored files that are gone are
+/// cleaned. `on_file` is called with the number of files built and the number to build.
+pub fn update_stack_graph<F>(
+    source_location: &Path,
+    db_path: &Path,
+    source_type: &SourceType,
+    language_config: &LanguageConfiguration,
+    workers: usize,
+    cancellation_flag: &(dyn CancellationFlag + Sync),
+    on_file: F,
+) -> Result<IndexUpdate, Error>
+where
+    F: Fn(usize, usize),
+{
+    let stored_tags: HashMap<PathBuf, String> = {
+        let reader = SQLiteReader::open(db_path)?;
+        let mut files = reader.list_file_or_directory(source_location)?;
+        let stored = files
+            .try_iter()?
+            .map(|entry| entry.map(|entry| (entry.path, entry.tag)))
+            .collect::<Result<_, _>>()?;
+        stored
+    };
+    // Only the files a graph is built for have a tag to compare.
+    let entries: Vec<PathBuf> = walk_files(source_location)?
+        .into_iter()
+        .filter(|entry| is_source_file(entry, language_config))
+        .collect();
+
+    let mut update = IndexUpdate::default();
+    let mut stale_entries = vec![];
+    for entry_path in &entries {
+        let unchanged = stored_tags.get(entry_path).is_some_and(|tag| {
+            std::fs::read_to_string(entry_path).is_ok_and(|source| sha1(&source) == *tag)
+        });
+        if unchanged {
+            update.unchanged += 1;
+        } else {
+            stale_entries.push(entry_path.clone());
+        }
+    }
+
+    let mut db = SQLiteWriter::open(db_path)?;
+    let on_disk: HashSet<&PathBuf> = entries.iter().collect();
+    for stored_path in stored_tags.keys().filter(|p| !on_disk.contains(p)) {
+        debug!("cleaning deleted file: {:?}", stored_path);
+        db.clean_file(stored_path)?;
+        update.removed += 1;
+    }
+
+    build_files(
+        &stale_entries,
+        source_type,
+        language_config,
+        workers,
+        cancellation_flag,
+        on_file,
+        |entry_path, file_graph, tag| {
+            store_file_graph(&mut db, &file_graph, tag)?;
+            update.indexed += 1;
+            debug!("indexed changed file: {:?}", entry_path);
+            Ok(())
+        },
+    )?;
+    Ok(update)
+}
+
+/// The analyzer of a file that is not built with the TSG, special files are registered by their
+/// name and views by their extension.
+fn file_analyzer(
+    entry: &Path,
+    language_config: &LanguageConfiguration,
+) -> Option<Arc<dyn FileAnalyzer + Send + Sync>> {
+    let file_name = entry.file_name()?.to_string_lossy();
+    language_config.special_files.get(&file_name).or_else(|| {
+        entry.extension().and_then(|ext| {
+            language_config
+                .special_files
+                .get(&view_file_key(&ext.to_string_lossy()))
+        })
+    })
+}
+
+/// Whether `load_graph_for_file` builds a graph for the file, the other files of the project
+/// are never stored.
+fn is_source_file(entry: &Path, language_config: &LanguageConfiguration) -> bool {
+    file_analyzer(entry, language_config).is_some()
+        || language_config
+            .matches_file(entry, &mut FileReader::new())
+            .unwrap_or(false)
+}
+
+fn walk_files(source_location: &Path) -> Result<Vec<PathBuf>, Error> {
+    let mut entries = vec![];
     for path in WalkDir::new(source_location).into_iter() {
-        trace!(
-            "stack_graph files: {}, nodes: {}, symbols: {}",
-            stack_graph.iter_files().count(),
-            stack_graph.iter_nodes().count(),
-            stack_graph.iter_symbols().count()
-        );
         let entry = match path {
             Ok(entry) => {
                 if entry.file_type().is_dir() {
@@ -294,42 +459,122 @@ pub fn init_stack_graph(
             }
             Err(err) => return Err(Error::new(err)),
         };
-        let entry_path = entry.to_owned().into_path();
-        match load_graph_for_file(
-            entry_path.clone(),
-            &mut stack_graph,
-            language_config,
-            source_type,
-        ) {
-            Ok(res) => match res {
-                Some((f, tag)) => {
-                    files_loaded += 1;
-                    let mut partials = PartialPaths::new();
-                    let paths: Vec<PartialPath> = Vec::new();
-
-                    match db.store_result_for_file(&stack_graph, f, &tag, &mut partials, &paths) {
-                        Ok(_) => (),
-                        Err(err) => {
-                            error!("error: {}", err);
-                            return Err(anyhow!(err));
-                        }
+        entries.push(entry.into_path());
+    }
+    Ok(entries)
+}
+
+/// Build the graphs of `entries` on `workers` threads and hand the graph of each file that is
+/// loaded to `on_built`, in the order of `entries`. A failure stops the workers.
+fn build_files<F, B>(
+    entries: &[PathBuf],
+    source_type: &SourceType,
+    language_config: &LanguageConfiguration,
+    workers: usize,
+    cancellation_flag: &(dyn CancellationFlag + Sync),
+    on_file: F,
+    mut on_built: B,
+) -> Result<(), Error>
+where
+    F: Fn(usize, usize),
+    B: FnMut(&Path, StackGraph, &str) -> Result<(), Error>,
+{
+    let total_files = entries.len();
+    let next_entry = AtomicUsize::new(0);
+    let stopped = AtomicBool::new(false);
+    let (sender, receiver) = mpsc::channel();
+    thread::scope(|scope| {
+        for _ in 0..workers.clamp(1, total_files.max(1)) {
+            let (next_entry, stopped, sender) = (&next_entry, &stopped, sender.clone());
+            scope.spawn(move || {
+                while !stopped.load(Ordering::Relaxed) {
+                    let i = next_entry.fetch_add(1, Ordering::Relaxed);
+                    let Some(entry_path) = entries.get(i) else {
+                        break;
+                    };
+                    let built = build_file_graph(
+                        entry_path.clone(),
+                        language_config,
+                        source_type,
+                        cancellation_flag,
+                    );
+                    if sender.send((i, built)).is_err() {
+                        break;
                     }
-                    debug!("loaded file handle: {:?} - file: {:?}", f, entry_path)
                 }
-                None => trace!("skipped file: {:?}", entry_path),
-            },
-            Err(e) => {
-                return Err(anyhow!("unable to load file: {:?} - {}", entry_path, e));
-            }
+            });
         }
-    }
+        drop(sender);
 
-    Ok(InitializedGraph {
-        files_loaded,
-        stack_graph,
+        // A file is handed over once the files found before it are.
+        let mut built_files = BTreeMap::new();
+        let mut next_to_hand_over = 0;
+        for (processed, (i, built)) in receiver.iter().enumerate() {
+            on_file(processed + 1, total_files);
+            built_files.insert(i, built);
+            while let Some(built) = built_files.remove(&next_to_hand_over) {
+                let entry_path = &entries[next_to_hand_over];
+                next_to_hand_over += 1;
+                let handed_over = match built {
+                    Ok(Some((file_graph, tag))) => on_built(entry_path, file_graph, &tag),
+                    Ok(None) => {
+                        trace!("skipped file: {:?}", entry_path);
+                        Ok(())
+                    }
+                    Err(e) => Err(anyhow!("unable to load file: {:?} - {}", entry_path, e)),
+                };
+                if let Err(e) = handed_over {
+                    stopped.store(true, Ordering::Relaxed);
+                    return Err(e);
+                }
+            }
+        }
+        Ok(())
     })
 }
 
+/// Build the graph of a single file into a graph of its own, the source type symbols are added
+/// to it first so that the source type node resolves the same way it does in the merged graph.
+fn build_file_graph(
+    entry: PathBuf,
+    language_config: &LanguageConfiguration,
+    source_type: &SourceType,
+    cancellation_flag: &dyn CancellationFlag,
+) -> Result<Option<(StackGraph, String)>, Error> {
+    let mut file_graph = StackGraph::new();
+    let (source, dependency) = SourceType::load_symbols_into_graph(&mut file_graph);
+    let file_source_type = match source_type {
+        SourceType::Source { .. } => source,
+        SourceType::Dependency { .. } => dependency,
+    };
+    let loaded = load_graph_for_file(
+        entry,
+        &mut file_graph,
+        language_config,
+        &file_source_type,
+        cancellation_flag,
+    )?;
+    Ok(loaded.map(|(_, tag)| (file_graph, tag)))
+}
+
+/// Store the graph of a single file, as built by `build_file_graph`, to the database.
+fn store_file_graph(
+    db: &mut SQLiteWriter,
+    file_graph: &StackGraph,
+    tag: &str,
+) -> Result<(), Error> {
+    let Some(file) = file_graph.iter_files().next() else {
+        return Err(anyhow!("no file in the graph of the file"));
+    };
+    let mut partials = PartialPaths::new();
+    let paths: Vec<PartialPath> = Vec::new();
+    if let Err(err) = db.store_result_for_file(file_graph, file, tag, &mut partials, &paths) {
+        error!("error: {}", err);
+        return Err(anyhow!(err));
+    }
+    Ok(())
+}
+
 /// Loads a file into the graph and stores it to the database.
 /// This is useful for incremental updates when a file changes.
 /// Accepts a mutable SQLiteWriter reference to avoid opening a new connection per file.
@@ -339,9 +584,16 @@ pub fn load_and_store_file(
     language_config: &LanguageConfiguration,
     source_type: &SourceType,
     db: &mut SQLiteWriter,
+    cancellation_flag: &dyn CancellationFlag,
 ) -> Result<Option<Handle<File>>, Error> {
     // First load the file into the graph
-    match load_graph_for_file(file_path.clone(), stack_graph, language_config, source_type) {
+    match load_graph_for_file(
+        file_path.clone(),
+        stack_graph,
+        language_config,
+        source_type,
+        cancellation_flag,
+    ) {
         Ok(Some((file_handle, tag))) => {
             // Store it to the database using the provided writer
             let mut partials = PartialPaths::new();
@@ -368,3 +620,165 @@ fn sha1(source: &str) -> String {
     hasher.update(source);
     base64::prelude::BASE64_STANDARD_NO_PAD.encode(hasher.finalize())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use tree_sitter_stack_graphs::NoCancellation;
+
+    use super::*;
+    use crate::c_sharp_graph::{
+        language_config::SourceNodeLanguageConfiguration,
+        query::{Query, QueryOptions, QueryType},
+    };
+
+    #[test]
+    fn test_init_stack_graph_in_parallel() {
+        let location = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("testdata/nerd-dinner");
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("graph.db");
+        let lc = SourceNodeLanguageConfiguration::new(&NoCancellation).unwrap();
+
+        let progress = Cell::new((0, 0));
+        let parallel = init_stack_graph(
+            &location,
+            &db_path,
+            &lc.source_type_node_info,
+            &lc.language_config,
+            4,
+            &NoCancellation,
+            |processed, total| progress.set((processed, total)),
+        )
+        .unwrap();
+        let (processed, total) = progress.get();
+        assert_eq!(processed, total);
+
+        let mut sequential = StackGraph::new();
+        let _ = sequential.add_from_graph(&lc.language_config.builtins);
+        for entry in WalkDir::new(&location) {
+            let entry = entry.unwrap();
+            if entry.file_type().is_file() {
+                load_graph_for_file(
+                    entry.into_path(),
+                    &mut sequential,
+                    &lc.language_config,
+                    &lc.source_type_node_info,
+                    &NoCancellation,
+                )
+                .unwrap();
+            }
+        }
+
+        let names = |graph: &StackGraph| -> Vec<String> {
+            graph
+                .iter_files()
+                .map(|f| graph[f].name().to_string())
+                .collect()
+        };
+        assert_eq!(names(&parallel.stack_graph), names(&sequential));
+        assert_eq!(
+            parallel.stack_graph.iter_nodes().count(),
+            sequential.iter_nodes().count()
+        );
+        let query = |graph: &StackGraph| {
+            QueryType::All {
+                graph,
+                source_type: &lc.source_type_node_info,
+                options: QueryOptions::default(),
+            }
+            .query("NerdDinner.*".to_string())
+            .unwrap()
+        };
+        let results = query(&parallel.stack_graph);
+        assert!(!results.is_empty());
+        assert_eq!(results, query(&sequential));
+    }
+
+    #[test]
+    fn test_update_stack_graph() {
+        let dir = tempfile::tempdir().unwrap();
+        let location = dir.path().join("project");
+        let db_path = dir.path().join("project.db");
+        std::fs::create_dir_all(&location).unwrap();
+        let class = |name: &str| format!("namespace Demo {{ public class {} {{ }} }}", name);
+        std::fs::write(location.join("Kept.cs"), class("Kept")).unwrap();
+        std::fs::write(location.join("Changed.cs"), class("Changed")).unwrap();
+        std::fs::write(location.join("Deleted.cs"), class("Deleted")).unwrap();
+        // Files that are not built are not compared with the database.
+        std::fs::write(location.join("README.md"), "# Demo").unwrap();
+        std::fs::write(location.join("Demo.dll"), [0xff, 0xfe, 0x00]).unwrap();
+        let lc = SourceNodeLanguageConfiguration::new(&NoCancellation).unwrap();
+        let to_build = Cell::new(0);
+        let update = || {
+            to_build.set(0);
+            update_stack_graph(
+                &location,
+                &db_path,
+                &lc.source_type_node_info,
+                &lc.language_config,
+                2,
+                &NoCancellation,
+                |_, total| to_build.set(total),
+            )
+            .unwrap()
+        };
+
+        init_stack_graph(
+            &location,
+            &db_path,
+            &lc.source_type_node_info,
+            &lc.language_config,
+            2,
+            &NoCancellation,
+            |_, _| {},
+        )
+        .unwrap();
+        assert_eq!(
+            update(),
+            IndexUpdate {
+                unchanged: 3,
+                indexed: 0,
+                removed: 0
+            }
+        );
+        assert_eq!(to_build.get(), 0);
+
+        std::fs::write(location.join("Changed.cs"), class("Renamed")).unwrap();
+        std::fs::write(location.join("Added.cs"), class("Added")).unwrap();
+        std::fs::remove_file(location.join("Deleted.cs")).unwrap();
+        assert_eq!(
+            update(),
+            IndexUpdate {
+                unchanged: 1,
+                indexed: 2,
+                removed: 1
+            }
+        );
+        assert_eq!(to_build.get(), 2);
+        assert_eq!(
+            update(),
+            IndexUpdate {
+                unchanged: 3,
+                indexed: 0,
+                removed: 0
+            }
+        );
+
+        let mut reader = SQLiteReader::open(&db_path).unwrap();
+        reader
+            .load_graphs_for_file_or_directory(&location, &stack_graphs::NoCancellation)
+            .unwrap();
+        let (graph, _, _) = reader.get();
+        let mut names: Vec<String> = graph
+            .iter_files()
+            .filter_map(|f| Path::new(graph[f].name()).file_name())
+            .map(|n| n.to_string_lossy().to_string())
+            .collect();
+        names.sort();
+        assert_eq!(names, vec!["Added.cs", "Changed.cs", "Kept.cs"]);
+        let symbols: Vec<String> = graph.iter_symbols().map(|s| graph[s].to_string()).collect();
+        assert!(symbols.contains(&"Renamed".to_string()));
+        assert!(!symbols.contains(&"Deleted".to_string()));
+    }
+}
@@ -0,0 +1,228 @@
+use std::{collections::BTreeMap, vec};
+
+use anyhow::{anyhow, Error, Ok};
+use stack_graphs::{
+    arena::Handle,
+    graph::{Node, StackGraph},
+};
+use tracing::trace;
+
+use crate::c_sharp_graph::{
+    namespace_query::NotFoundError,
+    query::{get_fqdn, Fqdn, QueryOptions, Search, SyntaxType},
+};
+
+/// The members of classes, such as fields, properties and events, that are one of the given
+/// syntax types and match the search.
+#[derive(Debug)]
+pub(crate) struct MemberSymbols {
+    members: BTreeMap<Fqdn, Handle<Node>>,
+}
+
+impl MemberSymbols {
+    pub(crate) fn new(
+        graph: &StackGraph,
+        nodes: Vec<Handle<Node>>,
+        search: &Search,
+        syntax_types: &[SyntaxType],
+        options: &QueryOptions,
+    ) -> anyhow::Result<MemberSymbols, Error> {
+        let mut members: BTreeMap<Fqdn, Handle<Node>> = BTreeMap::new();
+
+        for node_handle in nodes {
+            //Get all the edges
+            Self::traverse_node(
+                graph,
+                node_handle,
+                search,
+                syntax_types,
+                options,
+                &mut members,
+            )?;
+        }
+        if members.is_empty() {
+            return Err(anyhow!(NotFoundError {}));
+        }
+        trace!("member nodes found: {:?}", members);
+
+        Ok(MemberSymbols { members })
+    }
+
+    #[cfg(test)]
+    pub(crate) fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    pub(crate) fn contains(&self, fqdn: &Fqdn) -> bool {
+        self.members.contains_key(fqdn)
+    }
+
+    // Symbol here must be of <thing>.<member_name>, `member_name` picks the member out of the
+    // FQDN.
+    // <thing> may be a class or a variable.
+    // if a variable, we may have to enhance this method
+    // to get the actual "class" of the variable.
+    // TODO: Consider scoped things for this(??)
+    // TODO: Consider a edge from the var to the class symbol
+    pub(crate) fn symbol_in_namespace<F>(&self, symbol: String, member_name: F) -> bool
+    where
+        F: Fn(&Fqdn) -> Option<&String>,
+    {
+        let parts: Vec<&str> = symbol.split(".").collect();
+        if parts.len() != 2 {
+            return false;
+        }
+        let member_part = parts.last().expect("unable to get member part for symbol");
+        let class_part = parts.first().expect("unable to get class part for symbol");
+        self.members.keys().any(|fqdn| {
+            member_name(fqdn).is_some_and(|m| m == member_part)
+                && fqdn.class.as_deref().unwrap_or_default() == *class_part
+        })
+    }
+
+    fn traverse_node(
+        graph: &StackGraph,
+        node: Handle<Node>,
+        search: &Search,
+        syntax_types: &[SyntaxType],
+        options: &QueryOptions,
+        members: &mut BTreeMap<Fqdn, Handle<Node>>,
+    ) -> anyhow::Result<(), Error> {
+        options.check_cancelled("traverse_node")?;
+        // Collect and sort all edges first to ensure deterministic iteration
+        let mut edges: Vec<_> = graph.outgoing_edges(node).collect();
+        edges.sort_by_key(|e| e.sink);
+
+        let mut child_edges: Vec<Handle<Node>> = vec![];
+        for edge in edges {
+            if edge.precedence == 10 {
+                continue;
+            }
+            child_edges.push(edge.sink);
+            let child_node = &graph[edge.sink];
+            let symbol = match child_node.symbol() {
+                None => continue,
+                Some(symbol) => &graph[symbol],
+            };
+            if !search.match_symbol(symbol) {
+                continue;
+            }
+            match graph.source_info(edge.sink) {
+                None => continue,
+                Some(source_info) => match source_info.syntax_type.into_option() {
+                    None => continue,
+                    Some(syntax_type) => {
+                        if syntax_types.contains(&SyntaxType::get(&graph[syntax_type])) {
+                            if let Some(fqdn_name) = get_fqdn(edge.sink, graph) {
+                                if search.match_namespace(&fqdn_name.get_full_symbol()) {
+                                    members.insert(fqdn_name, edge.sink);
+                                }
+                            }
+                        } else {
+                            trace!(
+                                "got node: {:?}, symbol: {} not matching syntax_type: {}",
+                                edge.sink,
+                                symbol,
+                                &graph[syntax_type]
+                            );
+                        }
+                    }
+                },
+            }
+        }
+        // Recursively traverse child edges (already in sorted order)
+        for child_edge in child_edges {
+            Self::traverse_node(graph, child_edge, search, syntax_types, options, members)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Helper to build a mock graph of a class with the given members, as name and syntax type.
+    pub(crate) fn build_mock_graph_with_members(
+        namespace: &str,
+        class: &str,
+        members: &[(&str, &str)],
+    ) -> (StackGraph, Vec<Handle<Node>>) {
+        let mut graph = StackGraph::new();
+        let file = graph.add_file("test.cs").unwrap();
+
+        // Create root node
+        let root_id = graph.new_node_id(file);
+        let root_symbol = graph.add_symbol("root");
+        let root = graph
+            .add_pop_symbol_node(root_id, root_symbol, true)
+            .unwrap();
+
+        // Create namespace
+        let ns_id = graph.new_node_id(file);
+        let ns_symbol = graph.add_symbol(namespace);
+        let ns_node = graph.add_pop_symbol_node(ns_id, ns_symbol, true).unwrap();
+        let ns_syntax = graph.add_string("namespace_declaration");
+        graph.source_info_mut(ns_node).syntax_type = ns_syntax.into();
+
+        // Create class
+        let class_id = graph.new_node_id(file);
+        let class_symbol = graph.add_symbol(class);
+        let class_node = graph
+            .add_pop_symbol_node(class_id, class_symbol, true)
+            .unwrap();
+        let class_syntax = graph.add_string("class_def");
+        graph.source_info_mut(class_node).syntax_type = class_syntax.into();
+
+        // Build edge structure
+        graph.add_edge(root, ns_node, 0);
+        graph.add_edge(ns_node, class_node, 0);
+        graph.add_edge(class_node, ns_node, 10); // FQDN edge
+
+        for (name, syntax_type) in members {
+            let member_id = graph.new_node_id(file);
+            let member_symbol = graph.add_symbol(name);
+            let member_node = graph
+                .add_pop_symbol_node(member_id, member_symbol, true)
+                .unwrap();
+            let member_syntax = graph.add_string(syntax_type);
+            graph.source_info_mut(member_node).syntax_type = member_syntax.into();
+
+            graph.add_edge(class_node, member_node, 0);
+            graph.add_edge(member_node, class_node, 10); // FQDN edge
+        }
+
+        (graph, vec![root])
+    }
+
+    #[test]
+    fn test_member_symbols_only_given_syntax_types() {
+        let (graph, roots) = build_mock_graph_with_members(
+            "System.Web",
+            "HttpApplication",
+            &[
+                ("BeginRequest", "event_name"),
+                ("Context", "property_name"),
+                ("Modules", "field_name"),
+            ],
+        );
+        let search = Search::create_search("*".to_string()).unwrap();
+
+        let members = MemberSymbols::new(
+            &graph,
+            roots,
+            &search,
+            &[SyntaxType::PropertyName, SyntaxType::EventName],
+            &QueryOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(members.len(), 2);
+        assert!(
+            members.symbol_in_namespace("HttpApplication.Context".to_string(), |f| f
+                .property
+                .as_ref())
+        );
+        assert!(!members
+            .symbol_in_namespace("HttpApplication.Modules".to_string(), |f| f.field.as_ref()));
+    }
+}
@@ -9,7 +9,7 @@ use tracing::{debug, info, trace};
 
 use crate::c_sharp_graph::{
     namespace_query::NotFoundError,
-    query::{get_fqdn, Fqdn, GetMatcher, Search, SymbolMatcher, SyntaxType},
+    query::{get_fqdn, Fqdn, GetMatcher, QueryOptions, Search, SymbolMatcher, SyntaxType},
 };
 
 pub(crate) struct MethodSymbolsGetter {}
@@ -21,12 +21,13 @@ impl GetMatcher for MethodSymbolsGetter {
         stack_graphs: &StackGraph,
         definition_root_nodes: Vec<Handle<Node>>,
         search: &Search,
+        options: &QueryOptions,
     ) -> Result<Self::Matcher, Error>
     where
         Self: std::marker::Sized,
     {
         debug!("getting MethodSymbols matcher");
-        MethodSymbols::new(stack_graphs, definition_root_nodes, search)
+        MethodSymbols::new(stack_graphs, definition_root_nodes, search, options)
     }
 }
 
@@ -41,12 +42,13 @@ impl MethodSymbols {
         graph: &StackGraph,
         nodes: Vec<Handle<Node>>,
         search: &Search,
+        options: &QueryOptions,
     ) -> anyhow::Result<MethodSymbols, Error> {
         let mut methods: BTreeMap<Fqdn, Handle<Node>> = BTreeMap::new();
 
         for node_handle in nodes {
             //Get all the edges
-            Self::traverse_node(graph, node_handle, search, &mut methods)
+            Self::traverse_node(graph, node_handle, search, options, &mut methods)?;
         }
 
         if methods.is_empty() {
@@ -74,8 +76,10 @@ impl MethodSymbols {
         graph: &StackGraph,
         node: Handle<Node>,
         search: &Search,
+        options: &QueryOptions,
         methods: &mut BTreeMap<Fqdn, Handle<Node>>,
-    ) {
+    ) -> anyhow::Result<(), Error> {
+        options.check_cancelled("traverse_node")?;
         // Collect and sort all edges first to ensure deterministic iteration
         let mut edges: Vec<_> = graph.outgoing_edges(node).collect();
         edges.sort_by_key(|e| e.sink);
@@ -112,8 +116,9 @@ impl MethodSymbols {
         }
         // Recursively traverse child edges (already in sorted order)
         for child_edge in child_edges {
-            Self::traverse_node(graph, child_edge, search, methods);
+            Self::traverse_node(graph, child_edge, search, options, methods)?;
         }
+        Ok(())
     }
 
     // Symbol here must be of <thing>.<method_name>.
@@ -213,7 +218,7 @@ mod tests {
         let (graph, roots) = build_mock_graph_with_methods();
         let search = Search::create_search("*".to_string()).unwrap();
 
-        let result = MethodSymbols::new(&graph, roots, &search);
+        let result = MethodSymbols::new(&graph, roots, &search, &QueryOptions::default());
         assert!(result.is_ok());
 
         let method_symbols = result.unwrap();
@@ -224,7 +229,8 @@ mod tests {
     fn test_method_symbols_match_symbol_valid_format() {
         let (graph, roots) = build_mock_graph_with_methods();
         let search = Search::create_search("*".to_string()).unwrap();
-        let method_symbols = MethodSymbols::new(&graph, roots, &search).unwrap();
+        let method_symbols =
+            MethodSymbols::new(&graph, roots, &search, &QueryOptions::default()).unwrap();
 
         // Valid format: Class.Method
         assert!(method_symbols.match_symbol("String.Format".to_string()));
@@ -235,7 +241,8 @@ mod tests {
     fn test_method_symbols_match_symbol_invalid_format() {
         let (graph, roots) = build_mock_graph_with_methods();
         let search = Search::create_search("*".to_string()).unwrap();
-        let method_symbols = MethodSymbols::new(&graph, roots, &search).unwrap();
+        let method_symbols =
+            MethodSymbols::new(&graph, roots, &search, &QueryOptions::default()).unwrap();
 
         // Invalid formats
         assert!(!method_symbols.match_symbol("Format".to_string())); // No class
@@ -248,13 +255,16 @@ mod tests {
     fn test_method_symbols_match_fqdn() {
         let (graph, roots) = build_mock_graph_with_methods();
         let search = Search::create_search("*".to_string()).unwrap();
-        let method_symbols = MethodSymbols::new(&graph, roots, &search).unwrap();
+        let method_symbols =
+            MethodSymbols::new(&graph, roots, &search, &QueryOptions::default()).unwrap();
 
         let fqdn = Fqdn {
             namespace: Some("System".to_string()),
             class: Some("String".to_string()),
             method: Some("Format".to_string()),
             field: None,
+            property: None,
+            event: None,
         };
 
         assert!(method_symbols.match_fqdn(&fqdn));
@@ -264,13 +274,16 @@ mod tests {
     fn test_method_symbols_no_match_fqdn() {
         let (graph, roots) = build_mock_graph_with_methods();
         let search = Search::create_search("*".to_string()).unwrap();
-        let method_symbols = MethodSymbols::new(&graph, roots, &search).unwrap();
+        let method_symbols =
+            MethodSymbols::new(&graph, roots, &search, &QueryOptions::default()).unwrap();
 
         let fqdn = Fqdn {
             namespace: Some("System".to_string()),
             class: Some("String".to_string()),
             method: Some("NonExistent".to_string()),
             field: None,
+            property: None,
+            event: None,
         };
 
         assert!(!method_symbols.match_fqdn(&fqdn));
@@ -282,7 +295,8 @@ mod tests {
         // Only match "Format" not "Concat"
         let search = Search::create_search("*.Format".to_string()).unwrap();
 
-        let method_symbols = MethodSymbols::new(&graph, roots, &search).unwrap();
+        let method_symbols =
+            MethodSymbols::new(&graph, roots, &search, &QueryOptions::default()).unwrap();
 
         // Should only have Format
         assert_eq!(method_symbols.methods.len(), 1);
@@ -1,10 +1,20 @@
+pub mod call_graph;
 mod class_query;
+mod declaration_context;
 pub mod dependency_xml_analyzer;
+mod event_query;
 mod field_query;
 pub mod language_config;
+pub mod literal_query;
 pub mod loader;
+mod member_query;
 mod method_query;
 mod namespace_query;
 pub use namespace_query::NotFoundError;
+mod property_query;
 pub mod query;
+pub mod razor_analyzer;
 pub mod results;
+pub mod symbol_index;
+mod view;
+pub mod web_forms_analyzer;
@@ -10,9 +10,11 @@ use tracing::{info, trace};
 
 use crate::c_sharp_graph::{
     class_query::ClassSymbols,
+    event_query::EventSymbols,
     field_query::FieldSymbols,
     method_query::MethodSymbols,
-    query::{get_fqdn, Fqdn, GetMatcher, Search, SymbolMatcher, SyntaxType},
+    property_query::PropertySymbols,
+    query::{get_fqdn, Fqdn, GetMatcher, QueryOptions, Search, SymbolMatcher, SyntaxType},
 };
 
 #[derive(Debug, Clone)]
@@ -35,11 +37,12 @@ impl GetMatcher for NamespaceSymbolsGetter {
         stack_graphs: &StackGraph,
         definition_root_nodes: Vec<Handle<Node>>,
         search: &Search,
+        options: &QueryOptions,
     ) -> Result<Self::Matcher, Error>
     where
         Self: std::marker::Sized,
     {
-        NamespaceSymbols::new(stack_graphs, definition_root_nodes, search)
+        NamespaceSymbols::new(stack_graphs, definition_root_nodes, search, options)
     }
 }
 
@@ -48,6 +51,8 @@ pub(crate) struct NamespaceSymbols {
     classes: Option<ClassSymbols>,
     fields: Option<FieldSymbols>,
     methods: Option<MethodSymbols>,
+    properties: Option<PropertySymbols>,
+    events: Option<EventSymbols>,
     namespace: Vec<Fqdn>,
 }
 
@@ -57,21 +62,29 @@ impl NamespaceSymbols {
         graph: &StackGraph,
         nodes: Vec<Handle<Node>>,
         search: &Search,
+        options: &QueryOptions,
     ) -> anyhow::Result<NamespaceSymbols, Error> {
         // TODO: Handle borrow in new function
-        let class_symbol = ClassSymbols::new(graph, nodes.clone(), search);
-        let field_symbol = FieldSymbols::new(graph, nodes.clone(), search);
-        let method_symbols = MethodSymbols::new(graph, nodes.clone(), search);
+        let class_symbol = ClassSymbols::new(graph, nodes.clone(), search, options);
+        let field_symbol = FieldSymbols::new(graph, nodes.clone(), search, options);
+        let method_symbols = MethodSymbols::new(graph, nodes.clone(), search, options);
+        let property_symbols = PropertySymbols::new(graph, nodes.clone(), search, options);
+        let event_symbols = EventSymbols::new(graph, nodes.clone(), search, options);
+
+        // A cancelled traversal is not the same as nothing found.
+        options.check_cancelled("namespace_symbols")?;
 
         let mut results: Vec<Fqdn> = vec![];
         for node in nodes {
-            Self::traverse_node(graph, node, search, &mut results);
+            Self::traverse_node(graph, node, search, options, &mut results)?;
         }
 
         if results.is_empty()
             && class_symbol.is_err()
             && field_symbol.is_err()
             && method_symbols.is_err()
+            && property_symbols.is_err()
+            && event_symbols.is_err()
         {
             info!("no searchable nodes found");
             return Err(anyhow!(NotFoundError {}));
@@ -79,15 +92,19 @@ impl NamespaceSymbols {
         let class_symbol = class_symbol.ok();
         let method_symbols = method_symbols.ok();
         let field_symbol = field_symbol.ok();
+        let property_symbols = property_symbols.ok();
+        let event_symbols = event_symbols.ok();
         info!(
-            "searchable nodes found: \nclasses: {:?}\nmethods: {:?}\nfields: {:?}\nnamespaces: {:?}",
-            class_symbol, method_symbols, field_symbol, results
+            "searchable nodes found: \nclasses: {:?}\nmethods: {:?}\nfields: {:?}\nproperties: {:?}\nevents: {:?}\nnamespaces: {:?}",
+            class_symbol, method_symbols, field_symbol, property_symbols, event_symbols, results
         );
 
         Ok(NamespaceSymbols {
             classes: class_symbol,
             fields: field_symbol,
             methods: method_symbols,
+            properties: property_symbols,
+            events: event_symbols,
             namespace: results,
         })
     }
@@ -118,6 +135,16 @@ impl SymbolMatcher for NamespaceSymbols {
                 return true;
             }
         }
+        if let Some(properties) = &self.properties {
+            if properties.match_symbol(symbol.clone()) {
+                return true;
+            }
+        }
+        if let Some(events) = &self.events {
+            if events.match_symbol(symbol.clone()) {
+                return true;
+            }
+        }
         false
     }
 
@@ -137,6 +164,16 @@ impl SymbolMatcher for NamespaceSymbols {
                 return true;
             }
         }
+        if let Some(properties) = &self.properties {
+            if properties.match_fqdn(fqdn) {
+                return true;
+            }
+        }
+        if let Some(events) = &self.events {
+            if events.match_fqdn(fqdn) {
+                return true;
+            }
+        }
         false
     }
 }
@@ -147,8 +184,10 @@ impl NamespaceSymbols {
         db: &StackGraph,
         node: Handle<Node>,
         search: &Search,
+        options: &QueryOptions,
         results: &mut Vec<Fqdn>,
-    ) {
+    ) -> anyhow::Result<(), Error> {
+        options.check_cancelled("traverse_node")?;
         // We need to handle the first node.
         match db.source_info(node) {
             None => (),
@@ -177,8 +216,9 @@ impl NamespaceSymbols {
         // Sort child_edges to ensure deterministic traversal order
         child_edges.sort();
         for child_edge in child_edges {
-            Self::traverse_node(db, child_edge, search, results)
+            Self::traverse_node(db, child_edge, search, options, results)?;
         }
+        Ok(())
     }
 }
 
@@ -270,19 +310,37 @@ mod tests {
         let (graph, roots) = build_mock_namespace_graph();
         let search = Search::create_search("*".to_string()).unwrap();
 
-        let result = NamespaceSymbols::new(&graph, roots, &search);
+        let result = NamespaceSymbols::new(&graph, roots, &search, &QueryOptions::default());
         assert!(result.is_ok());
 
         let ns_symbols = result.unwrap();
         assert_eq!(ns_symbols.namespace.len(), 3)
     }
 
+    #[test]
+    fn test_namespace_symbols_cancelled() {
+        let (graph, roots) = build_mock_namespace_graph();
+        let search = Search::create_search("*".to_string()).unwrap();
+        let cancellation = crate::provider::cancellation::Cancellation::default();
+        cancellation.cancel();
+        let options = QueryOptions {
+            cancellation: Some(&cancellation),
+            ..Default::default()
+        };
+
+        // A cancelled traversal is an error of its own, not a namespace that was not found.
+        let err = NamespaceSymbols::new(&graph, roots.clone(), &search, &options).unwrap_err();
+        assert!(err.is::<tree_sitter_stack_graphs::CancellationError>());
+        let err = ClassSymbols::new(&graph, roots, &search, &options).unwrap_err();
+        assert!(err.is::<tree_sitter_stack_graphs::CancellationError>());
+    }
+
     #[test]
     fn test_namespace_intra_regex() {
         let (graph, roots) = build_mock_namespace_graph();
         let search = Search::create_search("*.Configuration.*".to_string()).unwrap();
 
-        let result = NamespaceSymbols::new(&graph, roots, &search);
+        let result = NamespaceSymbols::new(&graph, roots, &search, &QueryOptions::default());
         assert!(result.is_ok());
 
         let ns_symbols = result.unwrap();
@@ -293,7 +351,8 @@ mod tests {
     fn test_namespace_symbols_match_symbol_namespace() {
         let (graph, roots) = build_mock_namespace_graph();
         let search = Search::create_search("*".to_string()).unwrap();
-        let ns_symbols = NamespaceSymbols::new(&graph, roots, &search).unwrap();
+        let ns_symbols =
+            NamespaceSymbols::new(&graph, roots, &search, &QueryOptions::default()).unwrap();
 
         // Should match the namespace itself
         assert!(ns_symbols.match_symbol("System.Configuration".to_string()));
@@ -305,7 +364,8 @@ mod tests {
         let search =
             Search::create_search("System.Configuration.ConfigurationManager.*".to_string())
                 .unwrap();
-        let ns_symbols = NamespaceSymbols::new(&graph, roots, &search).unwrap();
+        let ns_symbols =
+            NamespaceSymbols::new(&graph, roots, &search, &QueryOptions::default()).unwrap();
 
         // Should match class within namespace
         assert!(ns_symbols.match_symbol("ConfigurationManager".to_string()));
@@ -315,7 +375,8 @@ mod tests {
     fn test_namespace_symbols_match_symbol_method() {
         let (graph, roots) = build_mock_namespace_graph();
         let search = Search::create_search("*".to_string()).unwrap();
-        let ns_symbols = NamespaceSymbols::new(&graph, roots, &search).unwrap();
+        let ns_symbols =
+            NamespaceSymbols::new(&graph, roots, &search, &QueryOptions::default()).unwrap();
 
         // Should match method (Class.Method format)
         assert!(ns_symbols.match_symbol("ConfigurationManager.GetSection".to_string()));
@@ -325,7 +386,8 @@ mod tests {
     fn test_namespace_symbols_match_symbol_field() {
         let (graph, roots) = build_mock_namespace_graph();
         let search = Search::create_search("*".to_string()).unwrap();
-        let ns_symbols = NamespaceSymbols::new(&graph, roots, &search).unwrap();
+        let ns_symbols =
+            NamespaceSymbols::new(&graph, roots, &search, &QueryOptions::default()).unwrap();
 
         // Should match field (Class.Field format)
         assert!(ns_symbols.match_symbol("ConfigurationManager.AppSettings".to_string()));
@@ -335,7 +397,8 @@ mod tests {
     fn test_namespace_symbols_regex_search_match() {
         let (graph, roots) = build_mock_namespace_graph();
         let search = Search::create_search("System.Configuration.*".to_string()).unwrap();
-        let ns_symbols = NamespaceSymbols::new(&graph, roots, &search).unwrap();
+        let ns_symbols =
+            NamespaceSymbols::new(&graph, roots, &search, &QueryOptions::default()).unwrap();
 
         assert!(ns_symbols.match_symbol("System.Configuration.Web".to_string()));
         assert!(ns_symbols.match_symbol("System.Configuration.File".to_string()));
@@ -346,7 +409,8 @@ mod tests {
     fn test_namespace_exact_match() {
         let (graph, roots) = build_mock_namespace_graph();
         let search = Search::create_search("System.Configuration".to_string()).unwrap();
-        let ns_symbols = NamespaceSymbols::new(&graph, roots, &search).unwrap();
+        let ns_symbols =
+            NamespaceSymbols::new(&graph, roots, &search, &QueryOptions::default()).unwrap();
 
         // Should match field (Class.Field format)
         assert!(!ns_symbols.match_symbol("System.Configuration.Web".to_string()));
@@ -358,7 +422,8 @@ mod tests {
     fn test_namespace_symbols_no_match_symbol() {
         let (graph, roots) = build_mock_namespace_graph();
         let search = Search::create_search("*".to_string()).unwrap();
-        let ns_symbols = NamespaceSymbols::new(&graph, roots, &search).unwrap();
+        let ns_symbols =
+            NamespaceSymbols::new(&graph, roots, &search, &QueryOptions::default()).unwrap();
 
         assert!(!ns_symbols.match_symbol("NonExistent".to_string()));
         assert!(!ns_symbols.match_symbol("Other.Namespace".to_string()));
@@ -368,13 +433,16 @@ mod tests {
     fn test_namespace_symbols_match_fqdn_class() {
         let (graph, roots) = build_mock_namespace_graph();
         let search = Search::create_search("*".to_string()).unwrap();
-        let ns_symbols = NamespaceSymbols::new(&graph, roots, &search).unwrap();
+        let ns_symbols =
+            NamespaceSymbols::new(&graph, roots, &search, &QueryOptions::default()).unwrap();
 
         let fqdn = Fqdn {
             namespace: Some("System.Configuration".to_string()),
             class: Some("ConfigurationManager".to_string()),
             method: None,
             field: None,
+            property: None,
+            event: None,
         };
 
         assert!(ns_symbols.match_fqdn(&fqdn));
@@ -384,13 +452,16 @@ mod tests {
     fn test_namespace_symbols_match_fqdn_method() {
         let (graph, roots) = build_mock_namespace_graph();
         let search = Search::create_search("*".to_string()).unwrap();
-        let ns_symbols = NamespaceSymbols::new(&graph, roots, &search).unwrap();
+        let ns_symbols =
+            NamespaceSymbols::new(&graph, roots, &search, &QueryOptions::default()).unwrap();
 
         let fqdn = Fqdn {
             namespace: Some("System.Configuration".to_string()),
             class: Some("ConfigurationManager".to_string()),
             method: Some("GetSection".to_string()),
             field: None,
+            property: None,
+            event: None,
         };
 
         assert!(ns_symbols.match_fqdn(&fqdn));
@@ -400,13 +471,16 @@ mod tests {
     fn test_namespace_symbols_match_fqdn_field() {
         let (graph, roots) = build_mock_namespace_graph();
         let search = Search::create_search("*".to_string()).unwrap();
-        let ns_symbols = NamespaceSymbols::new(&graph, roots, &search).unwrap();
+        let ns_symbols =
+            NamespaceSymbols::new(&graph, roots, &search, &QueryOptions::default()).unwrap();
 
         let fqdn = Fqdn {
             namespace: Some("System.Configuration".to_string()),
             class: Some("ConfigurationManager".to_string()),
             field: Some("AppSettings".to_string()),
             method: None,
+            property: None,
+            event: None,
         };
 
         assert!(ns_symbols.match_fqdn(&fqdn));
@@ -416,13 +490,16 @@ mod tests {
     fn test_namespace_symbols_no_match_fqdn() {
         let (graph, roots) = build_mock_namespace_graph();
         let search = Search::create_search("*".to_string()).unwrap();
-        let ns_symbols = NamespaceSymbols::new(&graph, roots, &search).unwrap();
+        let ns_symbols =
+            NamespaceSymbols::new(&graph, roots, &search, &QueryOptions::default()).unwrap();
 
         let fqdn = Fqdn {
             namespace: Some("System.Configuration".to_string()),
             class: Some("NonExistent".to_string()),
             method: None,
             field: None,
+            property: None,
+            event: None,
         };
 
         assert!(!ns_symbols.match_fqdn(&fqdn));
@@ -441,7 +518,7 @@ mod tests {
             .unwrap();
 
         let search = Search::create_search("*".to_string()).unwrap();
-        let result = NamespaceSymbols::new(&graph, vec![root], &search);
+        let result = NamespaceSymbols::new(&graph, vec![root], &search, &QueryOptions::default());
 
         // Should return error when no namespace is found
         assert!(result.is_err());
@@ -451,7 +528,8 @@ mod tests {
     fn test_non_ending_regex() {
         let (graph, roots) = build_mock_namespace_graph();
         let search = Search::create_search("System.*.Web".to_string()).unwrap();
-        let ns_symbols = NamespaceSymbols::new(&graph, roots, &search).unwrap();
+        let ns_symbols =
+            NamespaceSymbols::new(&graph, roots, &search, &QueryOptions::default()).unwrap();
         // Should return error when no namespace is found
         assert!(ns_symbols.match_symbol("System.Configuration.Web".to_string()));
     }
@@ -0,0 +1,134 @@
+use anyhow::{Error, Ok};
+use stack_graphs::{
+    arena::Handle,
+    graph::{Node, StackGraph},
+};
+use tracing::debug;
+
+use crate::c_sharp_graph::{
+    member_query::MemberSymbols,
+    query::{Fqdn, GetMatcher, QueryOptions, Search, SymbolMatcher, SyntaxType},
+};
+
+pub(crate) struct PropertySymbolsGetter {}
+
+impl GetMatcher for PropertySymbolsGetter {
+    type Matcher = PropertySymbols;
+
+    fn get_matcher(
+        stack_graphs: &StackGraph,
+        definition_root_nodes: Vec<Handle<Node>>,
+        search: &Search,
+        options: &QueryOptions,
+    ) -> Result<Self::Matcher, Error>
+    where
+        Self: std::marker::Sized,
+    {
+        debug!("getting PropertySymbols matcher");
+        PropertySymbols::new(stack_graphs, definition_root_nodes, search, options)
+    }
+
+    fn reports_properties_and_events() -> bool {
+        true
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct PropertySymbols {
+    properties: MemberSymbols,
+}
+
+// Create exposed methods for NamesapceSymbols
+impl PropertySymbols {
+    pub(crate) fn new(
+        graph: &StackGraph,
+        nodes: Vec<Handle<Node>>,
+        search: &Search,
+        options: &QueryOptions,
+    ) -> anyhow::Result<PropertySymbols, Error> {
+        let properties =
+            MemberSymbols::new(graph, nodes, search, &[SyntaxType::PropertyName], options)?;
+        Ok(PropertySymbols { properties })
+    }
+}
+
+impl SymbolMatcher for PropertySymbols {
+    fn match_symbol(&self, symbol: String) -> bool {
+        self.properties
+            .symbol_in_namespace(symbol, |fqdn| fqdn.property.as_ref())
+    }
+    fn match_fqdn(&self, fqdn: &Fqdn) -> bool {
+        self.properties.contains(fqdn)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::c_sharp_graph::member_query::tests::build_mock_graph_with_members;
+    use crate::c_sharp_graph::query::Search;
+
+    fn build_mock_graph_with_properties() -> (StackGraph, Vec<Handle<Node>>) {
+        build_mock_graph_with_members(
+            "System.Web",
+            "HttpContext",
+            &[("Current", "property_name"), ("Items", "field_name")],
+        )
+    }
+
+    #[test]
+    fn test_property_symbols_new_only_properties() {
+        let (graph, roots) = build_mock_graph_with_properties();
+        let search = Search::create_search("*".to_string()).unwrap();
+
+        let property_symbols =
+            PropertySymbols::new(&graph, roots, &search, &QueryOptions::default()).unwrap();
+        assert_eq!(property_symbols.properties.len(), 1);
+    }
+
+    #[test]
+    fn test_property_symbols_match_symbol() {
+        let (graph, roots) = build_mock_graph_with_properties();
+        let search = Search::create_search("*".to_string()).unwrap();
+        let property_symbols =
+            PropertySymbols::new(&graph, roots, &search, &QueryOptions::default()).unwrap();
+
+        assert!(property_symbols.match_symbol("HttpContext.Current".to_string()));
+        assert!(!property_symbols.match_symbol("HttpContext.Items".to_string()));
+        assert!(!property_symbols.match_symbol("Current".to_string()));
+    }
+
+    #[test]
+    fn test_property_symbols_match_fqdn() {
+        let (graph, roots) = build_mock_graph_with_properties();
+        let search = Search::create_search("System.Web.HttpContext.Current".to_string()).unwrap();
+        let property_symbols =
+            PropertySymbols::new(&graph, roots, &search, &QueryOptions::default()).unwrap();
+
+        let fqdn = Fqdn {
+            namespace: Some("System.Web".to_string()),
+            class: Some("HttpContext".to_string()),
+            method: None,
+            field: None,
+            property: Some("Current".to_string()),
+            event: None,
+        };
+        assert!(property_symbols.match_fqdn(&fqdn));
+
+        let field_fqdn = Fqdn {
+            property: None,
+            field: Some("Current".to_string()),
+            ..fqdn
+        };
+        assert!(!property_symbols.match_fqdn(&field_fqdn));
+    }
+
+    #[test]
+    fn test_property_symbols_not_found() {
+        let (graph, roots) = build_mock_graph_with_properties();
+        let search = Search::create_search("*.Items".to_string()).unwrap();
+
+        let result = PropertySymbols::new(&graph, roots, &search, &QueryOptions::default());
+        assert!(result.is_err());
+    }
+}
@@ -1,7 +1,12 @@
 use std::{
-    collections::{BTreeMap, BTreeSet},
+    cell::OnceCell,
+    collections::{BTreeMap, BTreeSet, HashMap},
     path::Path,
-    vec,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread, vec,
 };
 
 use anyhow::{Error, Ok};
@@ -12,22 +17,27 @@ use stack_graphs::{
     graph::{Edge, File, Node, StackGraph},
 };
 use tracing::{debug, error, info, trace};
+use tree_sitter_stack_graphs::CancellationFlag;
 use url::Url;
 
 use crate::c_sharp_graph::{
     class_query::ClassSymbolsGetter,
+    declaration_context::DeclarationContext,
+    event_query::EventSymbolsGetter,
     field_query::FieldSymbolsGetter,
     loader::SourceType,
     method_query::MethodSymbolsGetter,
     namespace_query::NamespaceSymbolsGetter,
+    property_query::PropertySymbolsGetter,
     results::{Location, Position, ResultNode},
+    symbol_index::SymbolIndex,
 };
 
 pub trait Query {
     fn query(self, query: String) -> anyhow::Result<Vec<ResultNode>, Error>;
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum SyntaxType {
     Import,
     CompUnit,
@@ -35,8 +45,14 @@ pub enum SyntaxType {
     ClassDef,
     MethodName,
     FieldName,
+    PropertyName,
+    EventName,
     LocalVar,
+    Lambda,
+    EventSubscription,
     Argument,
+    StringLiteral,
+    TypeReference,
     Name,
 }
 
@@ -49,8 +65,14 @@ impl SyntaxType {
             "class_def" => Self::ClassDef,
             "method_name" => Self::MethodName,
             "field_name" => Self::FieldName,
+            "property_name" => Self::PropertyName,
+            "event_name" => Self::EventName,
             "local_var" => Self::LocalVar,
+            "lambda" => Self::Lambda,
+            "event_subscription" => Self::EventSubscription,
             "argument" => Self::Argument,
+            "string_literal" => Self::StringLiteral,
+            "type_reference" => Self::TypeReference,
             "name" => Self::Name,
             // Name is the least used thing, and I want to have a default for this.
             &_ => Self::Name,
@@ -64,8 +86,14 @@ impl SyntaxType {
             Self::ClassDef => "class_def",
             Self::MethodName => "method_name",
             Self::FieldName => "field_name",
+            Self::PropertyName => "property_name",
+            Self::EventName => "event_name",
             Self::LocalVar => "local_var",
+            Self::Lambda => "lambda",
+            Self::EventSubscription => "event_subscription",
             Self::Argument => "argument",
+            Self::StringLiteral => "string_literal",
+            Self::TypeReference => "type_reference",
             Self::Name => "name",
         }
     }
@@ -77,6 +105,8 @@ pub(crate) struct Fqdn {
     pub(crate) class: Option<String>,
     pub(crate) method: Option<String>,
     pub(crate) field: Option<String>,
+    pub(crate) property: Option<String>,
+    pub(crate) event: Option<String>,
 }
 
 pub(crate) fn get_fqdn(node: Handle<Node>, graph: &StackGraph) -> Option<Fqdn> {
@@ -85,6 +115,8 @@ pub(crate) fn get_fqdn(node: Handle<Node>, graph: &StackGraph) -> Option<Fqdn> {
         class: None,
         method: None,
         field: None,
+        property: None,
+        event: None,
     };
     // traverse upwards based on the FQDN edge
     // Once there is no FQDN edge, return
@@ -126,6 +158,14 @@ pub(crate) fn get_fqdn(node: Handle<Node>, graph: &StackGraph) -> Option<Fqdn> {
                 fqdn.field = Some(symbol);
                 Some(fqdn)
             }
+            SyntaxType::PropertyName => {
+                fqdn.property = Some(symbol);
+                Some(fqdn)
+            }
+            SyntaxType::EventName => {
+                fqdn.event = Some(symbol);
+                Some(fqdn)
+            }
             _ => None,
         },
         Some(e) => match get_fqdn(e.sink, graph) {
@@ -159,6 +199,20 @@ pub(crate) fn get_fqdn(node: Handle<Node>, graph: &StackGraph) -> Option<Fqdn> {
                     );
                     Some(f)
                 }
+                SyntaxType::PropertyName => {
+                    f.property = f.property.map_or_else(
+                        || Some(symbol.clone()),
+                        |property| Some(format!("{}.{}", property, symbol.clone())),
+                    );
+                    Some(f)
+                }
+                SyntaxType::EventName => {
+                    f.event = f.event.map_or_else(
+                        || Some(symbol.clone()),
+                        |event| Some(format!("{}.{}", event, symbol.clone())),
+                    );
+                    Some(f)
+                }
                 _ => None,
             },
         },
@@ -166,101 +220,472 @@ pub(crate) fn get_fqdn(node: Handle<Node>, graph: &StackGraph) -> Option<Fqdn> {
 }
 
 impl Fqdn {
+    /// The FQDN with a property or event moved to the field, the way they were reported before
+    /// they were told apart.
+    pub(crate) fn with_members_as_field(mut self) -> Fqdn {
+        self.field = self
+            .field
+            .take()
+            .or(self.property.take())
+            .or(self.event.take());
+        self
+    }
+
     pub(crate) fn get_full_symbol(&self) -> String {
-        let mut s = String::new();
-        if let Some(n) = self.namespace.as_ref() {
-            s.push_str(n)
-        }
-        if let Some(c) = self.class.as_ref() {
-            s.push_str(&format!(".{}", c));
+        // Types in the global namespace, such as the `Program` of top level statements, have no
+        // namespace part.
+        [
+            &self.namespace,
+            &self.class,
+            &self.method,
+            &self.field,
+            &self.property,
+            &self.event,
+        ]
+        .into_iter()
+        .flatten()
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .join(".")
+    }
+}
+
+// This determines if the file is source code or not, but using the source_type symbol
+// graph node. When searching dependencies, every file is considered.
+pub(crate) fn is_source_file(
+    graph: &StackGraph,
+    source_type: &SourceType,
+    file: Handle<File>,
+    comp_unit_node_handle: Handle<Node>,
+) -> bool {
+    let symobl_handle = match source_type {
+        SourceType::Source { symbol_handle } => symbol_handle,
+        _ => return true,
+    };
+    graph.nodes_for_file(file).any(|node_handle| {
+        let node = &graph[node_handle];
+        if let Some(sh) = node.symbol() {
+            // This compares the source_type symbol handle to the nodes symbol
+            // as symbols are de-duplicated, this will check that the symbol for the
+            // given node is the one that we set for the source_type in the graph.
+            if sh.as_usize() == symobl_handle.as_usize() {
+                if source_type.get_string() != graph[sh] {
+                    error!("SOMETHING IS VERY WRONG!!!!");
+                }
+                // We need to make sure that the compulation unit for the file is
+                // actually has an edge from teh source_type node.
+                let edges: Vec<Edge> = graph.outgoing_edges(node_handle).collect();
+                for edge in edges {
+                    if edge.sink == comp_unit_node_handle {
+                        return true;
+                    }
+                }
+            }
         }
-        if let Some(m) = self.method.as_ref() {
-            s.push_str(&format!(".{}", m));
+        false
+    })
+}
+
+pub(crate) fn get_file_uri(graph: &StackGraph, file: Handle<File>) -> Option<String> {
+    let f = &graph[file];
+    let mut file_str = f.name().to_string();
+    let file_path = Path::new(f.name());
+    if !file_path.is_absolute() {
+        file_str = format!("/{}", file_str).clone();
+    }
+    match Url::from_file_path(&file_str) {
+        Result::Ok(url) => Some(url.as_str().to_string()),
+        Err(_) => {
+            error!("unable to get the file url for file: {}", &file_str);
+            None
         }
-        if let Some(f) = self.field.as_ref() {
-            s.push_str(&format!(".{}", f));
+    }
+}
+
+pub(crate) fn get_syntax_type(graph: &StackGraph, node: Handle<Node>) -> Option<SyntaxType> {
+    let syntax_type = graph.source_info(node)?.syntax_type.into_option()?;
+    Some(SyntaxType::get(&graph[syntax_type]))
+}
+
+pub(crate) fn get_location(graph: &StackGraph, node: Handle<Node>) -> Option<Location> {
+    let source_info = graph.source_info(node)?;
+    Some(Location {
+        start_position: Position {
+            line: source_info.span.start.line,
+            character: source_info.span.start.column.utf8_offset,
+        },
+        end_position: Position {
+            line: source_info.span.end.line,
+            character: source_info.span.end.column.utf8_offset,
+        },
+    })
+}
+
+/// What a query can use besides the graph.
+#[derive(Clone, Copy, Default)]
+pub struct QueryOptions<'a> {
+    /// The searches computed by prepare.
+    pub(crate) prepared: Option<&'a PreparedSearches>,
+    /// Checked for every node searched, a cancelled query returns an error.
+    pub(crate) cancellation: Option<&'a dyn CancellationFlag>,
+    /// Used instead of scanning the whole graph for the starting nodes and the definitions of
+    /// references.
+    pub(crate) index: Option<&'a SymbolIndex>,
+}
+
+impl<'a> QueryOptions<'a> {
+    pub fn indexed(index: &'a SymbolIndex) -> QueryOptions<'a> {
+        QueryOptions {
+            index: Some(index),
+            ..Default::default()
         }
+    }
 
-        s
+    pub(crate) fn check_cancelled(&self, at: &'static str) -> anyhow::Result<(), Error> {
+        match self.cancellation {
+            Some(cancellation) => Ok(cancellation.check(at)?),
+            None => Ok(()),
+        }
     }
 }
+
 pub enum QueryType<'graph> {
     All {
         graph: &'graph StackGraph,
         source_type: &'graph SourceType,
+        options: QueryOptions<'graph>,
     },
     Method {
         graph: &'graph StackGraph,
         source_type: &'graph SourceType,
+        options: QueryOptions<'graph>,
     },
     Field {
         graph: &'graph StackGraph,
         source_type: &'graph SourceType,
+        options: QueryOptions<'graph>,
     },
     Class {
         graph: &'graph StackGraph,
         source_type: &'graph SourceType,
+        options: QueryOptions<'graph>,
+    },
+    Property {
+        graph: &'graph StackGraph,
+        source_type: &'graph SourceType,
+        options: QueryOptions<'graph>,
+    },
+    Event {
+        graph: &'graph StackGraph,
+        source_type: &'graph SourceType,
+        options: QueryOptions<'graph>,
     },
 }
 
 impl Query for QueryType<'_> {
     fn query(self, query: String) -> anyhow::Result<Vec<ResultNode>, Error> {
         match self {
-            QueryType::All { graph, source_type } => {
+            QueryType::All {
+                graph,
+                source_type,
+                options,
+            } => {
                 let q = Querier {
                     graph,
                     source_type,
+                    options,
                     _matcher_getter: NamespaceSymbolsGetter {},
                 };
                 q.query(query)
             }
-            QueryType::Method { graph, source_type } => {
+            QueryType::Method {
+                graph,
+                source_type,
+                options,
+            } => {
                 let q = Querier {
                     graph,
                     source_type,
+                    options,
                     _matcher_getter: MethodSymbolsGetter {},
                 };
                 q.query(query)
             }
-            QueryType::Field { graph, source_type } => {
+            QueryType::Field {
+                graph,
+                source_type,
+                options,
+            } => {
                 let q = Querier {
                     graph,
                     source_type,
+                    options,
                     _matcher_getter: FieldSymbolsGetter {},
                 };
                 q.query(query)
             }
-            QueryType::Class { graph, source_type } => {
+            QueryType::Class {
+                graph,
+                source_type,
+                options,
+            } => {
                 let q = Querier {
                     graph,
                     source_type,
+                    options,
                     _matcher_getter: ClassSymbolsGetter {},
                 };
                 q.query(query)
             }
+            QueryType::Property {
+                graph,
+                source_type,
+                options,
+            } => {
+                let q = Querier {
+                    graph,
+                    source_type,
+                    options,
+                    _matcher_getter: PropertySymbolsGetter {},
+                };
+                q.query(query)
+            }
+            QueryType::Event {
+                graph,
+                source_type,
+                options,
+            } => {
+                let q = Querier {
+                    graph,
+                    source_type,
+                    options,
+                    _matcher_getter: EventSymbolsGetter {},
+                };
+                q.query(query)
+            }
         }
     }
 }
 
+/// The methods, local functions and lambdas of a file, the scopes of its local variables and
+/// parameters.
+pub(crate) struct LocalScopes {
+    /// Sorted by start, the outer scope first when two start at the same position.
+    scopes: Vec<Location>,
+}
+
+impl LocalScopes {
+    pub(crate) fn new(graph: &StackGraph, file: Handle<File>) -> LocalScopes {
+        let mut scopes: Vec<Location> = graph
+            .nodes_for_file(file)
+            .filter(|n| {
+                matches!(
+                    get_syntax_type(graph, *n),
+                    Some(SyntaxType::MethodName | SyntaxType::Lambda)
+                )
+            })
+            .filter_map(|n| get_location(graph, n))
+            .collect();
+        scopes.sort_by(|a, b| {
+            a.start_position
+                .cmp(&b.start_position)
+                .then_with(|| b.end_position.cmp(&a.end_position))
+        });
+        scopes.dedup();
+        LocalScopes { scopes }
+    }
+
+    /// The innermost scope that contains `location`. Scopes nest, so it is the last one starting
+    /// before the location that also contains it.
+    pub(crate) fn innermost(&self, location: &Location) -> Option<&Location> {
+        let starting_before = self
+            .scopes
+            .partition_point(|scope| scope.start_position <= location.start_position);
+        self.scopes[..starting_before]
+            .iter()
+            .rev()
+            .find(|scope| scope.contains(location))
+    }
+}
+
+/// The operators of the `+=` and `-=` assignments of a file, by the location of their left hand
+/// side.
+fn event_subscriptions(graph: &StackGraph, file: Handle<File>) -> BTreeMap<Location, String> {
+    graph
+        .nodes_for_file(file)
+        .filter(|n| get_syntax_type(graph, *n) == Some(SyntaxType::EventSubscription))
+        .filter_map(|n| {
+            let operator = graph[graph[n].symbol()?].to_string();
+            Some((get_location(graph, n)?, operator))
+        })
+        .collect()
+}
+
 pub(crate) struct Querier<'graph, T: GetMatcher> {
     pub(crate) graph: &'graph StackGraph,
     pub(crate) source_type: &'graph SourceType,
+    pub(crate) options: QueryOptions<'graph>,
     _matcher_getter: T,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub(crate) struct StartingNodes {
     definition_root_nodes: Vec<Handle<Node>>,
     referenced_files: BTreeSet<Handle<File>>,
     file_to_compunit_handle: BTreeMap<Handle<File>, Handle<Node>>,
 }
 
+impl StartingNodes {
+    /// Record the node when it is a compilation unit, or an import or namespace declaration
+    /// that is part of the search.
+    fn add(&mut self, graph: &StackGraph, node_handle: Handle<Node>, search: &Search) {
+        let node: &Node = &graph[node_handle];
+        let Some(file_handle) = node.file() else {
+            return;
+        };
+        let Some(symbol_handle) = node.symbol() else {
+            // If the node doesn't have a symbol to look at, then we should continue and it
+            // only used to tie together other nodes.
+            trace!("node no symbol: {}", node.display(graph));
+            return;
+        };
+        let symbol = &graph[symbol_handle];
+        let Some(source_info) = graph.source_info(node_handle) else {
+            trace!("node no source_info: {}", node.display(graph));
+            return;
+        };
+        let Some(handle) = source_info.syntax_type.into_option() else {
+            return;
+        };
+        let syntax_type = SyntaxType::get(&graph[handle]);
+        trace!(
+            "node for syntax_type: {} -- syntax_type: {:?}",
+            node.display(graph),
+            syntax_type
+        );
+        match syntax_type {
+            SyntaxType::CompUnit => {
+                self.file_to_compunit_handle
+                    .insert(file_handle, node_handle);
+            }
+            SyntaxType::Import if search.partial_namespace(symbol) => {
+                self.referenced_files.insert(file_handle);
+            }
+            SyntaxType::NamespaceDeclaration if search.partial_namespace(symbol) => {
+                self.definition_root_nodes.push(node_handle);
+                self.referenced_files.insert(file_handle);
+            }
+            _ => {}
+        }
+    }
+
+    /// The starting nodes of the search, looked up from the namespaces in the index.
+    fn from_index(graph: &StackGraph, index: &SymbolIndex, search: &Search) -> StartingNodes {
+        let mut starting_nodes = StartingNodes::default();
+        for (namespace, files) in index.importing_files() {
+            if search.partial_namespace(namespace) {
+                starting_nodes.referenced_files.extend(files);
+            }
+        }
+        for (namespace, nodes) in index.namespace_declarations() {
+            if search.partial_namespace(namespace) {
+                starting_nodes.definition_root_nodes.extend(nodes);
+                starting_nodes
+                    .referenced_files
+                    .extend(nodes.iter().filter_map(|n| graph[*n].file()));
+            }
+        }
+        for file in &starting_nodes.referenced_files {
+            if let Some(comp_unit) = index.compilation_unit(*file) {
+                starting_nodes
+                    .file_to_compunit_handle
+                    .insert(*file, comp_unit);
+            }
+        }
+        starting_nodes.definition_root_nodes.sort();
+        starting_nodes
+    }
+}
+
+/// The search and starting nodes of a pattern, computed ahead of the queries that use it.
+#[derive(Debug)]
+pub struct PreparedSearch {
+    search: Search,
+    starting_nodes: StartingNodes,
+}
+
+/// Prepared searches by the pattern they were created from. The searches are shared, so that
+/// the map can be copied cheaply when more patterns are prepared.
+pub type PreparedSearches = HashMap<String, Arc<PreparedSearch>>;
+
+/// Compute the search and starting nodes of every pattern from the symbol index of the graph,
+/// with the patterns split across `workers` threads. `on_pattern` is called with the number of
+/// patterns processed and the total after each one. Patterns that are not valid searches are
+/// left out, the query reports the error when it is evaluated.
+pub(crate) fn prepare_searches<F>(
+    graph: &StackGraph,
+    index: &SymbolIndex,
+    patterns: Vec<String>,
+    workers: usize,
+    on_pattern: F,
+) -> PreparedSearches
+where
+    F: Fn(usize, usize) + Sync,
+{
+    let total_patterns = patterns.len();
+    let processed = AtomicUsize::new(0);
+    let chunk_size = total_patterns.div_ceil(workers.max(1)).max(1);
+    let mut prepared = PreparedSearches::new();
+    thread::scope(|scope| {
+        let handles: Vec<_> = patterns
+            .chunks(chunk_size)
+            .map(|chunk| {
+                let (processed, on_pattern) = (&processed, &on_pattern);
+                scope.spawn(move || {
+                    let mut found = vec![];
+                    for pattern in chunk {
+                        match Search::create_search(pattern.clone()) {
+                            Result::Ok(search) => {
+                                let starting_nodes =
+                                    StartingNodes::from_index(graph, index, &search);
+                                found.push((
+                                    pattern.clone(),
+                                    Arc::new(PreparedSearch {
+                                        search,
+                                        starting_nodes,
+                                    }),
+                                ));
+                            }
+                            Err(e) => debug!("unable to prepare search for {}: {}", pattern, e),
+                        }
+                        on_pattern(
+                            processed.fetch_add(1, Ordering::Relaxed) + 1,
+                            total_patterns,
+                        );
+                    }
+                    found
+                })
+            })
+            .collect();
+        for handle in handles {
+            match handle.join() {
+                Result::Ok(found) => prepared.extend(found),
+                Err(_) => error!("worker preparing searches panicked"),
+            }
+        }
+    });
+    prepared
+}
+
 impl<T: GetMatcher> Querier<'_, T> {
     pub(crate) fn get_search(&self, query: String) -> anyhow::Result<Search, Error> {
         Search::create_search(query)
     }
 
-    pub(crate) fn get_starting_nodes(&self, search: &Search) -> StartingNodes {
+    pub(crate) fn get_starting_nodes(
+        &self,
+        search: &Search,
+    ) -> anyhow::Result<StartingNodes, Error> {
         // get all the compilation units that use some portion of the search (using System or
         // using System.Configuration) This will require us to then determine if there qualified
         // names ConfigurationManager.AppSettings for examples;
@@ -269,70 +694,18 @@ impl<T: GetMatcher> Querier<'_, T> {
         // declaration. then we need to capture all the nodes that are definitions attached to
         // this (for instance namespace System.Configuration; Class ConfigurationManager; method
         // AppSettings)
-        let mut definition_root_nodes: Vec<Handle<Node>> = vec![];
-        let mut referenced_files: BTreeSet<Handle<File>> = BTreeSet::new();
-        let mut file_to_compunit_handle: BTreeMap<Handle<File>, Handle<Node>> = BTreeMap::new();
-
+        if let Some(index) = self.options.index {
+            return Ok(StartingNodes::from_index(self.graph, index, search));
+        }
+        let mut starting_nodes = StartingNodes::default();
         for node_handle in self.graph.iter_nodes() {
-            let node: &Node = &self.graph[node_handle];
-            let file_handle = match node.file() {
-                Some(h) => h,
-                None => {
-                    continue;
-                }
-            };
-            let symbol_option = node.symbol();
-            if symbol_option.is_none() {
-                // If the node doesn't have a symbol to look at, then we should continue and it
-                // only used to tie together other nodes.
-                //
-                trace!("node no symbol: {}", node.display(self.graph));
-                continue;
-            }
-            let symbol = &self.graph[node.symbol().unwrap()];
-            let source_info = self.graph.source_info(node_handle);
-            if source_info.is_none() {
-                trace!("node no source_info: {}", node.display(self.graph));
-                continue;
-            }
-            match source_info.unwrap().syntax_type.into_option() {
-                None => continue,
-                Some(handle) => {
-                    let syntax_type = SyntaxType::get(&self.graph[handle]);
-                    trace!(
-                        "node for syntax_type: {} -- syntax_type: {:?}",
-                        node.display(self.graph),
-                        syntax_type
-                    );
-                    match syntax_type {
-                        SyntaxType::CompUnit => {
-                            file_to_compunit_handle.insert(file_handle, node_handle);
-                        }
-                        SyntaxType::Import => {
-                            if search.partial_namespace(symbol) {
-                                referenced_files.insert(file_handle);
-                            }
-                        }
-                        SyntaxType::NamespaceDeclaration => {
-                            if search.partial_namespace(symbol) {
-                                definition_root_nodes.push(node_handle);
-                                referenced_files.insert(file_handle);
-                            }
-                        }
-                        _ => continue,
-                    }
-                }
-            }
+            self.options.check_cancelled("get_starting_nodes")?;
+            starting_nodes.add(self.graph, node_handle, search);
         }
 
         // Sort definition_root_nodes to ensure deterministic query results
-        definition_root_nodes.sort();
-
-        StartingNodes {
-            definition_root_nodes,
-            referenced_files,
-            file_to_compunit_handle,
-        }
+        starting_nodes.definition_root_nodes.sort();
+        Ok(starting_nodes)
     }
 
     pub(crate) fn search_nodes(
@@ -342,7 +715,7 @@ impl<T: GetMatcher> Querier<'_, T> {
         searchable_nodes: &BTreeSet<Handle<Node>>,
         results: &mut Vec<ResultNode>,
         file_uri: String,
-    ) {
+    ) -> anyhow::Result<(), Error> {
         let mut searchable_nodes = searchable_nodes.clone();
         searchable_nodes.extend(self.graph.nodes_for_file(file));
         debug!(
@@ -351,12 +724,18 @@ impl<T: GetMatcher> Querier<'_, T> {
             searchable_nodes.len(),
             results.len(),
         );
+        let first_result = results.len();
+        // Only built once a reference resolves to a local variable.
+        let scopes = OnceCell::new();
+        // Only built once a reference resolves to an event.
+        let subscriptions = OnceCell::new();
         let mut used_nodes: BTreeSet<Handle<Node>> = BTreeSet::new();
         // Collect and sort nodes to ensure deterministic iteration
         let mut file_nodes: Vec<Handle<Node>> = self.graph.nodes_for_file(file).collect();
         file_nodes.sort();
         let file_nodes_count = file_nodes.len();
         for node_handle in file_nodes {
+            self.options.check_cancelled("search_nodes")?;
             if used_nodes.contains(&node_handle) {
                 continue;
             }
@@ -379,8 +758,13 @@ impl<T: GetMatcher> Querier<'_, T> {
 
             let mut full_symbol: Option<Fqdn> = None;
             if node.is_reference() {
-                full_symbol = self.get_type_with_symbol(node_handle, symbol, &searchable_nodes);
-                if full_symbol.is_none() {
+                full_symbol = match get_syntax_type(self.graph, node_handle) {
+                    Some(SyntaxType::TypeReference) => {
+                        self.get_type_reference_fqdn(file, symbol, &searchable_nodes)
+                    }
+                    _ => self.get_type_with_symbol(node_handle, symbol, &searchable_nodes, &scopes),
+                };
+                let Some(fqdn) = full_symbol.as_ref() else {
                     trace!(
                         file = %file_uri,
                         "unable to get full symbol: {}",
@@ -388,10 +772,9 @@ impl<T: GetMatcher> Querier<'_, T> {
                     );
                     used_nodes.insert(node_handle);
                     continue;
-                }
-                let full_symbol_unwrap = full_symbol.clone().unwrap();
-                trace!("found FQDN: {:?}", &full_symbol);
-                if !symbol_matcher.match_fqdn(&full_symbol_unwrap) {
+                };
+                trace!("found FQDN: {:?}", fqdn);
+                if !symbol_matcher.match_fqdn(fqdn) {
                     used_nodes.insert(node_handle);
                     continue;
                 }
@@ -437,9 +820,17 @@ impl<T: GetMatcher> Querier<'_, T> {
             // Add symbol for debugging
             var.insert("symbol".to_string(), Value::from(symbol.to_string()));
 
+            if !T::reports_properties_and_events() {
+                full_symbol = full_symbol.map(Fqdn::with_members_as_field);
+                if syntax_type_str == SyntaxType::PropertyName.to_string()
+                    || syntax_type_str == SyntaxType::EventName.to_string()
+                {
+                    syntax_type_str = SyntaxType::FieldName.to_string().to_owned();
+                }
+            }
+
             // Add FQDN for debugging and infer syntax_type for references
-            if node.is_reference() && full_symbol.is_some() {
-                let fqdn = full_symbol.unwrap();
+            if let Some(fqdn) = full_symbol.filter(|_| node.is_reference()) {
                 if let Some(ns) = &fqdn.namespace {
                     var.insert("fqdn_namespace".to_string(), Value::from(ns.clone()));
                 }
@@ -460,8 +851,40 @@ impl<T: GetMatcher> Querier<'_, T> {
                         syntax_type_str = "field_reference".to_string();
                     }
                 }
+                if let Some(property) = &fqdn.property {
+                    var.insert("fqdn_property".to_string(), Value::from(property.clone()));
+                    if syntax_type_str == "unknown" {
+                        syntax_type_str = "property_reference".to_string();
+                    }
+                }
+                if let Some(event) = &fqdn.event {
+                    var.insert("fqdn_event".to_string(), Value::from(event.clone()));
+                    // The TSG records the operator of `+=` and `-=` at the span of their left
+                    // hand side, it is only an event subscription once that resolves to an event.
+                    let subscription_operator = subscriptions
+                        .get_or_init(|| event_subscriptions(self.graph, file))
+                        .get(&code_location);
+                    if let Some(operator) = subscription_operator {
+                        var.insert(
+                            "subscription_operator".to_string(),
+                            Value::from(operator.clone()),
+                        );
+                        syntax_type_str = "event_subscription".to_string();
+                    } else if syntax_type_str == "unknown" {
+                        syntax_type_str = "event_reference".to_string();
+                    }
+                }
             }
 
+            // Imports are definitions in the graph, but for rules they are a usage of the
+            // namespace.
+            let kind = if node.is_reference() || syntax_type_str == SyntaxType::Import.to_string() {
+                "reference"
+            } else {
+                "declaration"
+            };
+            var.insert("kind".to_string(), Value::from(kind));
+
             // Set syntax_type after potential inference from FQDN
             var.insert("syntax_type".to_string(), Value::from(syntax_type_str));
 
@@ -474,6 +897,13 @@ impl<T: GetMatcher> Querier<'_, T> {
             });
             used_nodes.insert(node_handle);
         }
+        if results.len() > first_result {
+            let context = DeclarationContext::new(self.graph, file);
+            for result in results[first_result..].iter_mut() {
+                let enclosing = context.variables(&result.code_location);
+                result.variables.extend(enclosing);
+            }
+        }
         if used_nodes.len() != file_nodes_count {
             error!(
                 file = %file_uri,
@@ -488,6 +918,7 @@ impl<T: GetMatcher> Querier<'_, T> {
             searchable_nodes.len(),
             results.len(),
         );
+        Ok(())
     }
 
     // Select the best FQDN from candidates based on imports
@@ -542,6 +973,9 @@ impl<T: GetMatcher> Querier<'_, T> {
 
     // Helper function to get all imported namespaces for a file
     fn get_file_imports(&self, file: Handle<File>) -> BTreeSet<String> {
+        if let Some(index) = self.options.index {
+            return index.file_imports(file);
+        }
         let mut imports = BTreeSet::new();
 
         for node_handle in self.graph.nodes_for_file(file) {
@@ -570,12 +1004,48 @@ impl<T: GetMatcher> Querier<'_, T> {
         imports
     }
 
+    /// The class a type reference names, the class is looked up by name and picked by the imports
+    /// of the file.
+    fn get_type_reference_fqdn(
+        &self,
+        file: Handle<File>,
+        symbol: &str,
+        searchable_nodes: &BTreeSet<Handle<Node>>,
+    ) -> Option<Fqdn> {
+        let mut classes: Vec<Handle<Node>> = match self.options.index {
+            Some(index) => index
+                .definitions(symbol)
+                .iter()
+                .filter(|(_, syntax_type)| *syntax_type == SyntaxType::ClassDef)
+                .map(|(definition, _)| *definition)
+                .filter(|definition| searchable_nodes.contains(definition))
+                .collect(),
+            None => searchable_nodes
+                .iter()
+                .copied()
+                .filter(|n| {
+                    let node = &self.graph[*n];
+                    node.is_definition()
+                        && node.symbol().is_some_and(|s| self.graph[s] == *symbol)
+                        && get_syntax_type(self.graph, *n) == Some(SyntaxType::ClassDef)
+                })
+                .collect(),
+        };
+        classes.sort();
+        let candidates = classes
+            .into_iter()
+            .filter_map(|class| get_fqdn(class, self.graph))
+            .collect();
+        self.select_best_fqdn(candidates, self.get_file_imports(file))
+    }
+
     // Note: This function will only work, on the memeber_access_expresssion
     fn get_type_with_symbol(
         &self,
         node: Handle<Node>,
         symbol: &str,
         searchable_nodes: &BTreeSet<Handle<Node>>,
+        scopes: &OnceCell<LocalScopes>,
     ) -> Option<Fqdn> {
         let parts: Vec<&str> = symbol.split(".").collect();
         if parts.len() != 2 {
@@ -603,33 +1073,42 @@ impl<T: GetMatcher> Querier<'_, T> {
             imports
         );
 
-        // Find the symbol that matches the accessor
-        // Collect and sort to ensure deterministic selection
-        let mut matching_symbols: Vec<_> = self
-            .graph
-            .iter_symbols()
-            .filter(|s| accessor == self.graph[*s])
-            .collect();
-        matching_symbols.sort();
-        let get_symbol_handle_for_accessor = matching_symbols.first().copied()?;
         // Find the node that is the defintion of the symbol.
-        let mut nodes_for_defines_symbol: Vec<&Handle<Node>> = searchable_nodes
-            .iter()
-            .filter(|f| {
-                let n = &self.graph[**f];
-                if !n.is_definition() {
-                    return false;
-                }
-                let s = n.symbol();
-                if s.is_none() {
-                    return false;
-                }
-
-                s.unwrap() == get_symbol_handle_for_accessor
-            })
-            .collect();
+        let mut nodes_for_defines_symbol: Vec<Handle<Node>> = match self.options.index {
+            Some(index) => index
+                .definitions(&accessor)
+                .iter()
+                .map(|(definition, _)| *definition)
+                .filter(|definition| searchable_nodes.contains(definition))
+                .collect(),
+            None => {
+                // Find the symbol that matches the accessor
+                // Collect and sort to ensure deterministic selection
+                let mut matching_symbols: Vec<_> = self
+                    .graph
+                    .iter_symbols()
+                    .filter(|s| accessor == self.graph[*s])
+                    .collect();
+                matching_symbols.sort();
+                let get_symbol_handle_for_accessor = matching_symbols.first().copied()?;
+                searchable_nodes
+                    .iter()
+                    .copied()
+                    .filter(|f| {
+                        let n = &self.graph[*f];
+                        n.is_definition() && n.symbol() == Some(get_symbol_handle_for_accessor)
+                    })
+                    .collect()
+            }
+        };
         // Sort to ensure deterministic selection when multiple definitions match
         nodes_for_defines_symbol.sort();
+        if let Some(reference) = get_location(self.graph, node) {
+            nodes_for_defines_symbol.retain(|d| {
+                get_syntax_type(self.graph, *d) != Some(SyntaxType::LocalVar)
+                    || self.is_local_visible(file, scopes, *d, &reference)
+            });
+        }
 
         trace!(
             "looking for correct definition for {}-{}, found {} candidates",
@@ -641,7 +1120,7 @@ impl<T: GetMatcher> Querier<'_, T> {
         // Collect all candidate FQDNs with their namespaces
         let mut candidates: Vec<Fqdn> = vec![];
 
-        for definition_node in nodes_for_defines_symbol {
+        for definition_node in &nodes_for_defines_symbol {
             let source_info = &self.graph.source_info(*definition_node);
             if source_info.is_none() {
                 continue;
@@ -676,9 +1155,10 @@ impl<T: GetMatcher> Querier<'_, T> {
                     let found_edge = matching_edges.first();
                     found_edge.and_then(|e| get_fqdn(e.sink, self.graph))
                 }
-                SyntaxType::FieldName | SyntaxType::MethodName => {
-                    get_fqdn(*definition_node, self.graph)
-                }
+                SyntaxType::FieldName
+                | SyntaxType::PropertyName
+                | SyntaxType::EventName
+                | SyntaxType::MethodName => get_fqdn(*definition_node, self.graph),
                 SyntaxType::LocalVar => {
                     access_node.file().and_then(|f| {
                         let fqdns = self.get_local_var_type_fqdn(
@@ -710,6 +1190,27 @@ impl<T: GetMatcher> Querier<'_, T> {
         self.select_best_fqdn(candidates, imports)
     }
 
+    /// Whether a local variable or parameter is visible at the reference, the innermost method,
+    /// local function or lambda around the declaration has to contain the reference.
+    fn is_local_visible(
+        &self,
+        file: Handle<File>,
+        scopes: &OnceCell<LocalScopes>,
+        definition: Handle<Node>,
+        reference: &Location,
+    ) -> bool {
+        if !self.graph[definition].is_in_file(file) {
+            return false;
+        }
+        let Some(declared) = get_location(self.graph, definition) else {
+            return true;
+        };
+        scopes
+            .get_or_init(|| LocalScopes::new(self.graph, file))
+            .innermost(&declared)
+            .is_none_or(|scope| scope.contains(reference))
+    }
+
     fn get_local_var_type_fqdn(
         &self,
         definition_node: Handle<Node>,
@@ -791,14 +1292,24 @@ impl<T: GetMatcher> Querier<'_, T> {
 
 impl<T: GetMatcher> Query for Querier<'_, T> {
     fn query(self, query: String) -> anyhow::Result<Vec<ResultNode>, Error> {
-        let search: Search = self.get_search(query)?;
+        let computed: (Search, StartingNodes);
+        let (search, starting_nodes) = match self.options.prepared.and_then(|p| p.get(&query)) {
+            Some(prepared) => {
+                debug!("using prepared search for: {}", query);
+                (&prepared.search, &prepared.starting_nodes)
+            }
+            None => {
+                let search: Search = self.get_search(query)?;
+                let starting_nodes = self.get_starting_nodes(&search)?;
+                computed = (search, starting_nodes);
+                (&computed.0, &computed.1)
+            }
+        };
 
         debug!("search: {:?}", search);
 
         let mut results: Vec<ResultNode> = vec![];
 
-        let starting_nodes = self.get_starting_nodes(&search);
-
         // Get all the definition_node_handles to be searched.
         let mut searchable_nodes: BTreeSet<Handle<Node>> = BTreeSet::new();
         for definition_root_node in &starting_nodes.definition_root_nodes {
@@ -817,13 +1328,12 @@ impl<T: GetMatcher> Query for Querier<'_, T> {
         }
 
         // Now that we have the all the nodes we need to build the reference symbols to match the *
-        let symbol_matcher =
-            T::get_matcher(self.graph, starting_nodes.definition_root_nodes, &search)?;
-
-        let (is_source, symbol_handle) = match self.source_type {
-            SourceType::Source { symbol_handle } => (true, Some(symbol_handle)),
-            _ => (false, None),
-        };
+        let symbol_matcher = T::get_matcher(
+            self.graph,
+            starting_nodes.definition_root_nodes.clone(),
+            search,
+            &self.options,
+        )?;
 
         info!(
             "searching for match: {:?} across {} files",
@@ -841,55 +1351,19 @@ impl<T: GetMatcher> Query for Querier<'_, T> {
                     continue;
                 }
             };
-            // This determines if the file is source code or not, but using the source_type symbol
-            // graph node.
-            if is_source
-                && !self.graph.nodes_for_file(*file).any(|node_handle| {
-                    let node = &self.graph[node_handle];
-
-                    let symobl_handle = symbol_handle.unwrap();
-                    if let Some(sh) = node.symbol() {
-                        // This compares the source_type symbol handle to the nodes symbol
-                        // as symbols are de-duplicated, this will check that the symbol for the
-                        // given node is the one that we set for the source_type in the graph.
-                        if sh.as_usize() == symobl_handle.as_usize() {
-                            if self.source_type.get_string() != self.graph[sh] {
-                                error!("SOMETHING IS VERY WRONG!!!!");
-                            }
-                            // We need to make sure that the compulation unit for the file is
-                            // actually has an edge from teh source_type node.
-                            let edges: Vec<Edge> = self.graph.outgoing_edges(node_handle).collect();
-                            for edge in edges {
-                                if edge.sink == *comp_unit_node_handle {
-                                    return true;
-                                }
-                            }
-                        }
-                    }
-                    false
-                })
-            {
+            if !is_source_file(self.graph, self.source_type, *file, *comp_unit_node_handle) {
                 continue;
             }
-            let f = &self.graph[*file];
-            let mut file_str = f.name().to_string();
-            let file_path = Path::new(f.name());
-            if !file_path.is_absolute() {
-                file_str = format!("/{}", file_str).clone();
-            }
-            let file_url = Url::from_file_path(&file_str);
-            if file_url.is_err() {
-                error!("unable to get the file url for file: {}", &file_str);
+            let Some(file_uri) = get_file_uri(self.graph, *file) else {
                 continue;
-            }
-            let file_uri = file_url.unwrap().as_str().to_string();
+            };
             self.search_nodes(
                 *file,
                 &symbol_matcher,
                 &searchable_nodes,
                 &mut results,
                 file_uri,
-            );
+            )?;
         }
         // Sort results to ensure deterministic output
         results.sort();
@@ -951,9 +1425,19 @@ pub(crate) trait GetMatcher {
         stack_graphs: &StackGraph,
         definition_root_nodes: Vec<Handle<Node>>,
         search: &Search,
+        options: &QueryOptions,
     ) -> Result<Self::Matcher, Error>
     where
         Self: std::marker::Sized;
+
+    /// Whether the results report properties and events as such. The locations that predate
+    /// them report them as fields, so that the variables rules use do not change.
+    fn reports_properties_and_events() -> bool
+    where
+        Self: std::marker::Sized,
+    {
+        false
+    }
 }
 
 pub(crate) trait SymbolMatcher {
@@ -1138,7 +1622,7 @@ impl SearchPart {
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
 
     // Tests for Search::create_search()
@@ -1464,6 +1948,7 @@ mod tests {
         let querier = Querier {
             graph: &graph,
             source_type: &source_type,
+            options: QueryOptions::default(),
             _matcher_getter: NamespaceSymbolsGetter {},
         };
 
@@ -1472,6 +1957,8 @@ mod tests {
             class: Some("ConfigurationManager".to_string()),
             method: Some("AppSettings".to_string()),
             field: None,
+            property: None,
+            event: None,
         }];
         let imports = BTreeSet::new();
 
@@ -1487,6 +1974,7 @@ mod tests {
         let querier = Querier {
             graph: &graph,
             source_type: &source_type,
+            options: QueryOptions::default(),
             _matcher_getter: NamespaceSymbolsGetter {},
         };
 
@@ -1495,6 +1983,8 @@ mod tests {
             class: Some("ConfigurationManager".to_string()),
             method: Some("AppSettings".to_string()),
             field: None,
+            property: None,
+            event: None,
         }];
         let mut imports = BTreeSet::new();
         imports.insert("System.Configuration.Test".to_string());
@@ -1512,6 +2002,7 @@ mod tests {
         let querier = Querier {
             graph: &graph,
             source_type: &source_type,
+            options: QueryOptions::default(),
             _matcher_getter: NamespaceSymbolsGetter {},
         };
 
@@ -1521,12 +2012,16 @@ mod tests {
                 class: Some("ConfigurationManager".to_string()),
                 method: Some("AppSettings".to_string()),
                 field: None,
+                property: None,
+                event: None,
             },
             Fqdn {
                 namespace: Some("System.Configuration".to_string()),
                 class: Some("ConfigurationManager".to_string()),
                 method: Some("AppSettings".to_string()),
                 field: None,
+                property: None,
+                event: None,
             },
         ];
         let mut imports = BTreeSet::new();
@@ -1550,6 +2045,7 @@ mod tests {
         let querier = Querier {
             graph: &graph,
             source_type: &source_type,
+            options: QueryOptions::default(),
             _matcher_getter: NamespaceSymbolsGetter {},
         };
 
@@ -1559,12 +2055,16 @@ mod tests {
                 class: Some("ConfigurationManager".to_string()),
                 method: Some("AppSettings".to_string()),
                 field: None,
+                property: None,
+                event: None,
             },
             Fqdn {
                 namespace: Some("System.Configuration.Internal".to_string()),
                 class: Some("ConfigurationManager".to_string()),
                 method: Some("AppSettings".to_string()),
                 field: None,
+                property: None,
+                event: None,
             },
         ];
         let mut imports = BTreeSet::new();
@@ -1583,6 +2083,7 @@ mod tests {
         let querier = Querier {
             graph: &graph,
             source_type: &source_type,
+            options: QueryOptions::default(),
             _matcher_getter: NamespaceSymbolsGetter {},
         };
 
@@ -1592,12 +2093,16 @@ mod tests {
                 class: Some("ConfigurationManager".to_string()),
                 method: None,
                 field: None,
+                property: None,
+                event: None,
             },
             Fqdn {
                 namespace: Some("ZZZ.Configuration".to_string()),
                 class: Some("ConfigurationManager".to_string()),
                 method: None,
                 field: None,
+                property: None,
+                event: None,
             },
         ];
         let mut imports = BTreeSet::new();
@@ -1617,6 +2122,7 @@ mod tests {
         let querier = Querier {
             graph: &graph,
             source_type: &source_type,
+            options: QueryOptions::default(),
             _matcher_getter: NamespaceSymbolsGetter {},
         };
 
@@ -1636,6 +2142,7 @@ mod tests {
         let querier = Querier {
             graph: &graph,
             source_type: &source_type,
+            options: QueryOptions::default(),
             _matcher_getter: NamespaceSymbolsGetter {},
         };
 
@@ -1645,12 +2152,16 @@ mod tests {
                 class: Some("ConfigurationManager".to_string()),
                 method: None,
                 field: None,
+                property: None,
+                event: None,
             },
             Fqdn {
                 namespace: Some("System.Configuration".to_string()),
                 class: Some("ConfigurationManager".to_string()),
                 method: None,
                 field: None,
+                property: None,
+                event: None,
             },
         ];
         let mut imports = BTreeSet::new();
@@ -1664,4 +2175,287 @@ mod tests {
             Some("System.Configuration".to_string())
         );
     }
+
+    #[test]
+    fn test_cancelled_query() {
+        let mut graph = StackGraph::new();
+        let source_type = SourceType::Source {
+            symbol_handle: graph.add_symbol("source"),
+        };
+        let cancellation = crate::provider::cancellation::Cancellation::default();
+        let querier = Querier {
+            graph: &graph,
+            source_type: &source_type,
+            options: QueryOptions {
+                prepared: None,
+                cancellation: Some(&cancellation),
+                index: None,
+            },
+            _matcher_getter: NamespaceSymbolsGetter {},
+        };
+        let search = Search::create_search("System.*".to_string()).unwrap();
+        assert!(querier.get_starting_nodes(&search).is_ok());
+
+        cancellation.cancel();
+        assert!(querier.get_starting_nodes(&search).is_err());
+        assert!(querier.query("System.*".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_indexed_query_matches_scan() {
+        use crate::c_sharp_graph::{
+            language_config::SourceNodeLanguageConfiguration, loader::load_graph_for_file,
+        };
+        use tree_sitter_stack_graphs::NoCancellation;
+
+        let location =
+            std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("testdata/nerd-dinner");
+        let lc = SourceNodeLanguageConfiguration::new(&NoCancellation).unwrap();
+        let mut graph = StackGraph::new();
+        let _ = graph.add_from_graph(&lc.language_config.builtins);
+        for entry in walkdir::WalkDir::new(&location).sort_by_file_name() {
+            let entry = entry.unwrap();
+            if entry.file_type().is_file() {
+                let _ = load_graph_for_file(
+                    entry.into_path(),
+                    &mut graph,
+                    &lc.language_config,
+                    &lc.source_type_node_info,
+                    &NoCancellation,
+                );
+            }
+        }
+        let index = SymbolIndex::new(&graph);
+        let patterns = [
+            "NerdDinner.*",
+            "NerdDinner.Models.*",
+            "NerdDinner.Helpers.*",
+        ];
+        let prepared = prepare_searches(
+            &graph,
+            &index,
+            patterns.iter().map(|p| p.to_string()).collect(),
+            2,
+            |_, _| {},
+        );
+        assert_eq!(prepared.len(), patterns.len());
+        for pattern in patterns {
+            let scanned = QueryType::All {
+                graph: &graph,
+                source_type: &lc.source_type_node_info,
+                options: QueryOptions::default(),
+            }
+            .query(pattern.to_string())
+            .unwrap();
+            let indexed = QueryType::All {
+                graph: &graph,
+                source_type: &lc.source_type_node_info,
+                options: QueryOptions::indexed(&index),
+            }
+            .query(pattern.to_string())
+            .unwrap();
+            let from_prepared = QueryType::All {
+                graph: &graph,
+                source_type: &lc.source_type_node_info,
+                options: QueryOptions {
+                    prepared: Some(&prepared),
+                    ..Default::default()
+                },
+            }
+            .query(pattern.to_string())
+            .unwrap();
+            assert!(!scanned.is_empty());
+            assert_eq!(scanned, indexed, "pattern: {}", pattern);
+            assert_eq!(scanned, from_prepared, "pattern: {}", pattern);
+        }
+    }
+
+    /// The graph of a single source file written to a temporary directory.
+    pub(crate) fn graph_for_source(
+        source: &str,
+    ) -> (
+        StackGraph,
+        crate::c_sharp_graph::language_config::SourceNodeLanguageConfiguration,
+    ) {
+        use crate::c_sharp_graph::{
+            language_config::SourceNodeLanguageConfiguration, loader::load_graph_for_file,
+        };
+        use tree_sitter_stack_graphs::NoCancellation;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Source.cs");
+        std::fs::write(&path, source).unwrap();
+        let lc = SourceNodeLanguageConfiguration::new(&NoCancellation).unwrap();
+        let mut graph = StackGraph::new();
+        let _ = graph.add_from_graph(&lc.language_config.builtins);
+        load_graph_for_file(
+            path,
+            &mut graph,
+            &lc.language_config,
+            &lc.source_type_node_info,
+            &NoCancellation,
+        )
+        .unwrap();
+        (graph, lc)
+    }
+
+    #[test]
+    fn test_property_variables_only_for_property_location() {
+        let (graph, lc) = graph_for_source(
+            r#"using Demo;
+
+namespace Demo
+{
+    public class Settings
+    {
+        public static string Name { get; set; }
+    }
+}
+
+namespace Demo.Readers
+{
+    public class Reader
+    {
+        public string Read()
+        {
+            return Settings.Name;
+        }
+    }
+}
+"#,
+        );
+        let reference = |results: Vec<ResultNode>| {
+            results
+                .into_iter()
+                .find(|r| r.variables["kind"] == "reference")
+                .expect("reference to Settings.Name")
+                .variables
+        };
+
+        let field = reference(
+            QueryType::Field {
+                graph: &graph,
+                source_type: &lc.source_type_node_info,
+                options: QueryOptions::default(),
+            }
+            .query("Demo.Settings.Name".to_string())
+            .unwrap(),
+        );
+        assert_eq!(field["fqdn_field"], "Name");
+        assert_eq!(field["syntax_type"], "field_reference");
+        assert!(!field.contains_key("fqdn_property"));
+
+        let property = reference(
+            QueryType::Property {
+                graph: &graph,
+                source_type: &lc.source_type_node_info,
+                options: QueryOptions::default(),
+            }
+            .query("Demo.Settings.Name".to_string())
+            .unwrap(),
+        );
+        assert_eq!(property["fqdn_property"], "Name");
+        assert_eq!(property["syntax_type"], "property_reference");
+        assert!(!property.contains_key("fqdn_field"));
+    }
+
+    #[test]
+    fn test_event_subscriptions_and_struct_and_interface_events() {
+        let (graph, lc) = graph_for_source(
+            r#"using System;
+using Demo;
+
+namespace Demo
+{
+    public class Publisher
+    {
+        public static event EventHandler Changed;
+    }
+
+    public interface INotifier
+    {
+        event EventHandler Raised;
+    }
+
+    public struct Counter
+    {
+        public event EventHandler Overflowed;
+    }
+}
+
+namespace Demo.Subscribers
+{
+    public class Subscriber
+    {
+        public void Subscribe(INotifier notifier, Counter counter)
+        {
+            Publisher.Changed += OnChanged;
+            Publisher.Changed -= OnChanged;
+            notifier.Raised += OnChanged;
+            counter.Overflowed += OnChanged;
+        }
+
+        private void OnChanged(object sender, EventArgs e)
+        {
+        }
+    }
+}
+"#,
+        );
+        let query = |pattern: &str| {
+            QueryType::Event {
+                graph: &graph,
+                source_type: &lc.source_type_node_info,
+                options: QueryOptions::default(),
+            }
+            .query(pattern.to_string())
+            .unwrap()
+        };
+
+        let mut operators: Vec<&str> = vec![];
+        let results = query("Demo.Publisher.Changed");
+        for result in &results {
+            if result.variables["kind"] == "reference" {
+                assert_eq!(result.variables["syntax_type"], "event_subscription");
+                operators.push(result.variables["subscription_operator"].as_str().unwrap());
+            }
+        }
+        operators.sort();
+        assert_eq!(operators, vec!["+=", "-="]);
+
+        for (pattern, class) in [
+            ("Demo.INotifier.Raised", "INotifier"),
+            ("Demo.Counter.Overflowed", "Counter"),
+        ] {
+            let results = query(pattern);
+            assert_eq!(results.len(), 1, "pattern: {}", pattern);
+            assert_eq!(results[0].variables["fqdn_class"], class);
+            assert_eq!(results[0].variables["syntax_type"], "event_subscription");
+        }
+    }
+
+    #[test]
+    fn test_local_scopes_innermost() {
+        let span = |start: (usize, usize), end: (usize, usize)| Location {
+            start_position: Position {
+                line: start.0,
+                character: start.1,
+            },
+            end_position: Position {
+                line: end.0,
+                character: end.1,
+            },
+        };
+        let method = span((2, 4), (20, 5));
+        let lambda = span((4, 20), (8, 9));
+        let sibling = span((10, 8), (14, 9));
+        let scopes = LocalScopes {
+            scopes: vec![method.clone(), lambda.clone(), sibling.clone()],
+        };
+
+        assert_eq!(scopes.innermost(&span((5, 12), (5, 16))), Some(&lambda));
+        assert_eq!(scopes.innermost(&span((16, 12), (16, 16))), Some(&method));
+        assert_eq!(scopes.innermost(&span((11, 12), (11, 16))), Some(&sibling));
+        assert_eq!(scopes.innermost(&span((22, 0), (22, 4))), None);
+    }
 }
@@ -0,0 +1,921 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use stack_graphs::arena::Handle;
+use stack_graphs::graph::{File, StackGraph};
+use tracing::info;
+use tree_sitter_stack_graphs::{BuildError, CancellationFlag, FileAnalyzer, StackGraphLanguage};
+
+use crate::c_sharp_graph::view::{
+    build_view_graph, is_identifier_byte, is_identifier_start, type_namespaces, unqualified_type,
+    web_config_namespaces, ViewDocument, ViewWriter,
+};
+
+/// The view file extensions handled by the [`RazorFileAnalyzer`].
+pub const RAZOR_FILE_EXTENSIONS: [&str; 2] = ["cshtml", "vbhtml"];
+
+/// The namespace of `WebViewPage`, the base class of the views.
+const MVC_NAMESPACE: &str = "System.Web.Mvc";
+
+/// The `Web.config` section with the namespaces imported by the Razor views.
+const RAZOR_CONFIG_SECTION: &str = "system.web.webPages.razor";
+
+/// Directives that do not reference any code that we can analyze.
+const IGNORED_DIRECTIVES: [&str; 11] = [
+    "page",
+    "layout",
+    "namespace",
+    "addTagHelper",
+    "removeTagHelper",
+    "tagHelperPrefix",
+    "attribute",
+    "implements",
+    "preservewhitespace",
+    "typeparam",
+    "rendermode",
+];
+
+/// VB block keywords and the keyword that closes them.
+const VB_BLOCKS: [(&str, &str); 13] = [
+    ("code", "end code"),
+    ("functions", "end functions"),
+    ("helper", "end helper"),
+    ("section", "end section"),
+    ("if", "end if"),
+    ("for", "next"),
+    ("while", "end while"),
+    ("do", "loop"),
+    ("select", "end select"),
+    ("using", "end using"),
+    ("try", "end try"),
+    ("synclock", "end synclock"),
+    ("with", "end with"),
+];
+
+/// Builds the graph for Razor views (`.cshtml` and `.vbhtml`).
+///
+/// The view is converted to C# top level statements where every code construct stays at its
+/// line and column in the view and the markup is replaced by whitespace, so the spans of the
+/// graph nodes point at the view. The C# is then built with the same TSG as the source files.
+pub struct RazorFileAnalyzer {
+    pub sgl: Arc<StackGraphLanguage>,
+}
+
+impl RazorFileAnalyzer {
+    pub fn matches(path: &Path) -> bool {
+        path.extension().is_some_and(|ext| {
+            RAZOR_FILE_EXTENSIONS
+                .iter()
+                .any(|e| ext.eq_ignore_ascii_case(e))
+        })
+    }
+}
+
+impl FileAnalyzer for RazorFileAnalyzer {
+    #[allow(clippy::needless_lifetimes)]
+    fn build_stack_graph_into<'a>(
+        &self,
+        stack_graph: &mut StackGraph,
+        file: Handle<File>,
+        path: &Path,
+        source: &str,
+        _all_paths: &mut dyn Iterator<Item = &'a Path>,
+        globals: &HashMap<String, String>,
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<(), BuildError> {
+        let vb = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("vbhtml"));
+        let namespaces = web_config_namespaces(path, RAZOR_CONFIG_SECTION);
+        let mut document = parse_view(source, vb, &namespaces);
+        info!(
+            file=?path,
+            "converted view with {} code segments",
+            document.segments.len()
+        );
+        build_view_graph(
+            &self.sgl,
+            stack_graph,
+            file,
+            path,
+            &mut document,
+            globals,
+            cancellation_flag,
+        )
+    }
+}
+
+/// Convert a view to C#, `namespaces` are the namespaces the configuration imports for the
+/// views.
+pub(crate) fn parse_view(source: &str, vb: bool, namespaces: &[String]) -> ViewDocument {
+    let mut scanner = Scanner {
+        w: ViewWriter::new(source),
+        vb,
+        model: None,
+        base_model: None,
+    };
+    scanner.document();
+    scanner.page_members(namespaces);
+    scanner.w.finish()
+}
+
+struct Scanner<'a> {
+    w: ViewWriter<'a>,
+    vb: bool,
+    /// The type of `@model`, `Model` is declared by the directive.
+    model: Option<String>,
+    /// The type argument of the `@inherits` base class.
+    base_model: Option<String>,
+}
+
+impl Scanner<'_> {
+    /// Declare what the view gets from `WebViewPage<TModel>` and import the namespaces of the
+    /// configuration, after the end of the view.
+    fn page_members(&mut self, namespaces: &[String]) {
+        let imports = std::iter::once(MVC_NAMESPACE).chain(namespaces.iter().map(String::as_str));
+        for namespace in imports {
+            self.w.append(&format!("using {};", namespace));
+        }
+        // `HtmlHelper<TModel>` derives from `HtmlHelper`, the helpers such as `ActionLink` extend
+        // it. Local types are looked up by name, type arguments would hide its methods.
+        self.w.append("HtmlHelper Html;");
+        self.w.append("UrlHelper Url;");
+        self.w.append("dynamic ViewBag;");
+        if self.model.is_none() {
+            let model = self.base_model.as_deref().unwrap_or("dynamic");
+            self.w.append(&format!("{} Model;", model));
+        }
+    }
+
+    fn document(&mut self) {
+        while !self.w.at_end() {
+            if self.w.src[self.w.pos] == b'@' {
+                self.transition();
+            } else {
+                self.w.blank();
+            }
+        }
+    }
+
+    /// Handle an `@` in markup.
+    fn transition(&mut self) {
+        let escaped_or_email = self.w.pos > 0 && self.w.src[self.w.pos - 1].is_ascii_alphanumeric();
+        match self.w.peek(1) {
+            Some(b'@') => self.w.blank_n(2),
+            _ if escaped_or_email => self.w.blank(),
+            Some(b'*') => {
+                while !self.w.at_end() && !self.w.starts_with(b"*@") {
+                    self.w.blank();
+                }
+                self.w.blank_n(2);
+            }
+            Some(b'{') if !self.vb => {
+                self.w.blank();
+                let start = self.w.start_segment();
+                self.code_block();
+                self.w.end_segment(start);
+            }
+            Some(b'(') => {
+                let start = self.w.start_segment();
+                self.w.replace(b'!');
+                self.balanced(b'(', b')');
+                self.w.suffix(";");
+                self.w.end_segment(start);
+            }
+            Some(b) if b.is_ascii_alphabetic() || b == b'_' => {
+                let word = self.w.word_at(self.w.pos + 1).to_string();
+                if self.vb {
+                    self.vb_transition(&word);
+                } else {
+                    self.cs_transition(&word);
+                }
+            }
+            _ => self.w.blank(),
+        }
+    }
+
+    fn cs_transition(&mut self, word: &str) {
+        match word {
+            "if" | "for" | "foreach" | "while" | "switch" | "lock" | "try" | "do" => {
+                self.statement()
+            }
+            "using" => {
+                let next = self.w.skip_whitespace_from(self.w.pos + 1 + word.len());
+                if self.w.src.get(next) == Some(&b'(') {
+                    self.statement();
+                } else {
+                    let start = self.w.start_segment();
+                    self.w.blank();
+                    self.w.copy_n(word.len());
+                    self.using_directive(start);
+                }
+            }
+            "model" => self.model = self.type_directive(word.len(), "Model"),
+            "inherits" => self.inherits_directive(word.len()),
+            "inject" => {
+                self.w.blank_n(1 + word.len());
+                self.rest_of_line_statement();
+            }
+            "functions" | "code" | "helper" => {
+                while !self.w.at_end()
+                    && self.w.src[self.w.pos] != b'{'
+                    && self.w.src[self.w.pos] != b'\n'
+                {
+                    self.w.blank();
+                }
+                if self.w.peek(0) == Some(b'{') {
+                    let start = self.w.start_segment();
+                    self.code_block();
+                    self.w.end_segment(start);
+                }
+            }
+            "section" => {
+                while !self.w.at_end()
+                    && self.w.src[self.w.pos] != b'{'
+                    && self.w.src[self.w.pos] != b'\n'
+                {
+                    self.w.blank();
+                }
+                if self.w.peek(0) == Some(b'{') {
+                    self.w.blank();
+                    self.markup_braces();
+                }
+            }
+            w if IGNORED_DIRECTIVES.contains(&w) => {
+                let end = self.w.line_content_end();
+                self.w.blank_n(end - self.w.pos);
+            }
+            _ => self.implicit_expression(word),
+        }
+    }
+
+    fn vb_transition(&mut self, word: &str) {
+        let lower = word.to_lowercase();
+        match lower.as_str() {
+            "imports" => {
+                // `@Imports` is long enough to hold `using`.
+                let start = self.w.start_segment();
+                for b in "using".bytes() {
+                    self.w.replace(b);
+                }
+                self.w.blank_n(word.len() + 1 - "using".len());
+                self.using_directive(start);
+            }
+            "modeltype" => self.model = self.type_directive(word.len(), "Model"),
+            "inherits" => self.inherits_directive(word.len()),
+            _ => match VB_BLOCKS.iter().find(|(open, _)| *open == lower) {
+                Some((open, close)) => self.vb_block(open, close),
+                None => self.implicit_expression(word),
+            },
+        }
+    }
+
+    /// `@using Namespace` becomes `using Namespace;`, the keyword is already written.
+    fn using_directive(&mut self, start: usize) {
+        let end = self.w.line_content_end();
+        let ends_with_semicolon = end > self.w.pos && self.w.src[end - 1] == b';';
+        self.w.copy_n(end - self.w.pos);
+        if !ends_with_semicolon {
+            self.w.suffix(";");
+        }
+        self.w.end_segment(start);
+    }
+
+    fn inherits_directive(&mut self, keyword_len: usize) {
+        let base = self.type_directive(keyword_len, "Base");
+        self.base_model = base.as_deref().and_then(|base| {
+            Some(
+                base.split_once('<')?
+                    .1
+                    .strip_suffix('>')?
+                    .trim()
+                    .to_string(),
+            )
+        });
+    }
+
+    /// `@model Ns.Type` becomes a local declaration of `Type` named after the directive, followed
+    /// by a `using` of `Ns`. Returns the type as declared.
+    fn type_directive(&mut self, keyword_len: usize, name: &str) -> Option<String> {
+        self.w.blank_n(1 + keyword_len);
+        while !self.w.at_end() && matches!(self.w.src[self.w.pos], b' ' | b'\t') {
+            self.w.blank();
+        }
+        let end = self.w.line_content_end();
+        let type_name = &self.w.src[self.w.pos..end];
+        // VB generics (`List(Of Dinner)`) can not be written as C# in the same space.
+        if type_name.is_empty() || type_name.windows(3).any(|w| w.eq_ignore_ascii_case(b"(of")) {
+            self.w.blank_n(end - self.w.pos);
+            return None;
+        }
+        let type_name = String::from_utf8_lossy(type_name).to_string();
+        let unqualified = unqualified_type(&type_name);
+        let start = self.w.start_segment();
+        for b in unqualified.bytes() {
+            if b == b' ' {
+                self.w.blank();
+            } else {
+                self.w.copy();
+            }
+        }
+        self.w.suffix(&format!(" {};", name));
+        for namespace in type_namespaces(&type_name) {
+            self.w.suffix(&format!(" using {};", namespace));
+        }
+        self.w.end_segment(start);
+        Some(unqualified.split_whitespace().collect())
+    }
+
+    fn rest_of_line_statement(&mut self) {
+        let end = self.w.line_content_end();
+        let start = self.w.start_segment();
+        self.w.copy_n(end - self.w.pos);
+        self.w.suffix(";");
+        self.w.end_segment(start);
+    }
+
+    /// `@Html.ActionLink("Edit", "Edit")` becomes `!Html.ActionLink("Edit", "Edit");`, the `!`
+    /// makes any expression a statement without moving it.
+    fn implicit_expression(&mut self, word: &str) {
+        let start = self.w.start_segment();
+        self.w.replace(b'!');
+        self.w.copy_n(word.len());
+        if word == "await" {
+            while !self.w.at_end() && self.w.src[self.w.pos] == b' ' {
+                self.w.copy();
+            }
+            let next = self.w.word_at(self.w.pos).len();
+            self.w.copy_n(next);
+        }
+        loop {
+            match (self.w.peek(0), self.w.peek(1), self.w.peek(2)) {
+                (Some(b'('), _, _) => self.balanced(b'(', b')'),
+                (Some(b'['), _, _) => self.balanced(b'[', b']'),
+                (Some(b'.'), Some(b), _) if is_identifier_start(b) => {
+                    self.w.copy();
+                    let next = self.w.word_at(self.w.pos).len();
+                    self.w.copy_n(next);
+                }
+                (Some(b'?'), Some(b'.'), Some(b)) if is_identifier_start(b) => {
+                    self.w.copy_n(2);
+                    let next = self.w.word_at(self.w.pos).len();
+                    self.w.copy_n(next);
+                }
+                (Some(b'?'), Some(b'['), _) => {
+                    self.w.copy();
+                    self.balanced(b'[', b']');
+                }
+                _ => break,
+            }
+        }
+        self.w.suffix(";");
+        self.w.end_segment(start);
+    }
+
+    /// `@if (...) { ... } else { ... }` and the other statements are copied as they are, the
+    /// markup in their blocks is replaced.
+    fn statement(&mut self) {
+        let start = self.w.start_segment();
+        self.w.blank();
+        let continuations: &[&str] = match self.w.word_at(self.w.pos) {
+            "if" => &["else"],
+            "try" => &["catch", "finally"],
+            "do" => &["while"],
+            _ => &[],
+        };
+        loop {
+            // The keywords and the condition.
+            while let Some(b) = self.w.peek(0) {
+                match b {
+                    b'(' => self.balanced(b'(', b')'),
+                    b'{' | b';' => break,
+                    b if b.is_ascii_whitespace() || is_identifier_byte(b) => self.w.copy(),
+                    _ => break,
+                }
+            }
+            match self.w.peek(0) {
+                Some(b'{') => self.code_block(),
+                Some(b';') => {
+                    self.w.copy();
+                    break;
+                }
+                _ => break,
+            }
+            let next = self.w.skip_whitespace_from(self.w.pos);
+            if next >= self.w.src.len() || !continuations.contains(&self.w.word_at(next)) {
+                break;
+            }
+            self.w.copy_n(next - self.w.pos);
+        }
+        self.w.end_segment(start);
+    }
+
+    /// Copy a C# block, replacing the markup in it, the position is at the `{`.
+    fn code_block(&mut self) {
+        self.w.copy();
+        let mut depth = 1;
+        let mut statement_start = true;
+        while let Some(b) = self.w.peek(0) {
+            match b {
+                b'"' => self.string(false),
+                b'@' | b'$' if matches!(self.w.peek(1), Some(b'"')) => {
+                    let verbatim = b == b'@';
+                    self.w.copy();
+                    self.string(verbatim);
+                }
+                b'$' if self.w.peek(1) == Some(b'@') && self.w.peek(2) == Some(b'"') => {
+                    self.w.copy_n(2);
+                    self.string(true);
+                }
+                b'\'' => self.char_literal(),
+                b'/' if self.w.peek(1) == Some(b'/') => {
+                    while !self.w.at_end() && self.w.src[self.w.pos] != b'\n' {
+                        self.w.copy();
+                    }
+                }
+                b'/' if self.w.peek(1) == Some(b'*') => {
+                    self.w.copy_n(2);
+                    while !self.w.at_end() && !self.w.starts_with(b"*/") {
+                        self.w.copy();
+                    }
+                    self.w.copy_n(2);
+                }
+                b'{' => {
+                    self.w.copy();
+                    depth += 1;
+                    statement_start = true;
+                    continue;
+                }
+                b'}' => {
+                    self.w.copy();
+                    depth -= 1;
+                    if depth == 0 {
+                        return;
+                    }
+                    statement_start = true;
+                    continue;
+                }
+                b';' => {
+                    self.w.copy();
+                    statement_start = true;
+                    continue;
+                }
+                b'<' if statement_start
+                    && self
+                        .w
+                        .peek(1)
+                        .is_some_and(|n| n.is_ascii_alphabetic() || n == b'!') =>
+                {
+                    self.markup_element();
+                    continue;
+                }
+                b'@' if statement_start && self.w.peek(1) == Some(b':') => {
+                    self.w.blank_n(2);
+                    self.markup_line();
+                    continue;
+                }
+                // Razor allows expressions and statements in code blocks to start with `@`.
+                b'@' if statement_start
+                    && self
+                        .w
+                        .peek(1)
+                        .is_some_and(|n| is_identifier_start(n) || n == b'(') =>
+                {
+                    self.transition();
+                    continue;
+                }
+                b'@' if statement_start && self.w.peek(1) == Some(b'<') => {
+                    self.w.blank();
+                    self.markup_element();
+                    continue;
+                }
+                b if b.is_ascii_whitespace() => {
+                    self.w.copy();
+                    continue;
+                }
+                _ => self.w.copy(),
+            }
+            statement_start = false;
+        }
+    }
+
+    /// Copy balanced brackets as code, the position is at the opening bracket.
+    fn balanced(&mut self, open: u8, close: u8) {
+        let mut depth = 0;
+        while let Some(b) = self.w.peek(0) {
+            match b {
+                b'"' => self.string(false),
+                b'@' if self.w.peek(1) == Some(b'"') => {
+                    self.w.copy();
+                    self.string(true);
+                }
+                b'\'' => self.char_literal(),
+                b if b == open => {
+                    depth += 1;
+                    self.w.copy();
+                }
+                b if b == close => {
+                    depth -= 1;
+                    self.w.copy();
+                    if depth == 0 {
+                        return;
+                    }
+                }
+                _ => self.w.copy(),
+            }
+        }
+    }
+
+    /// Copy a string literal, the position is at the opening quote.
+    fn string(&mut self, verbatim: bool) {
+        self.w.copy();
+        while let Some(b) = self.w.peek(0) {
+            match b {
+                b'\\' if !verbatim => self.w.copy_n(2),
+                b'"' if verbatim && self.w.peek(1) == Some(b'"') => self.w.copy_n(2),
+                b'"' => {
+                    self.w.copy();
+                    return;
+                }
+                b'\n' if !verbatim => return,
+                _ => self.w.copy(),
+            }
+        }
+    }
+
+    fn char_literal(&mut self) {
+        self.w.copy();
+        while let Some(b) = self.w.peek(0) {
+            match b {
+                b'\\' => self.w.copy_n(2),
+                b'\'' => {
+                    self.w.copy();
+                    return;
+                }
+                b'\n' => return,
+                _ => self.w.copy(),
+            }
+        }
+    }
+
+    /// Markup after `@:` runs to the end of the line.
+    fn markup_line(&mut self) {
+        while let Some(b) = self.w.peek(0) {
+            match b {
+                b'\n' => return,
+                b'@' => self.transition(),
+                _ => self.w.blank(),
+            }
+        }
+    }
+
+    /// Markup in a `@section Name { ... }`, the position is after the `{`.
+    fn markup_braces(&mut self) {
+        let mut depth = 1;
+        while let Some(b) = self.w.peek(0) {
+            match b {
+                b'@' => self.transition(),
+                b'{' => {
+                    depth += 1;
+                    self.w.blank();
+                }
+                b'}' => {
+                    depth -= 1;
+                    self.w.blank();
+                    if depth == 0 {
+                        return;
+                    }
+                }
+                _ => self.w.blank(),
+            }
+        }
+    }
+
+    /// A markup element in a code block, up to its closing tag.
+    fn markup_element(&mut self) {
+        let (name, closing, self_closing) = self.tag();
+        if closing || self_closing || name.is_empty() || is_void_element(&name) {
+            return;
+        }
+        let mut depth = 1;
+        while let Some(b) = self.w.peek(0) {
+            match b {
+                b'@' => self.transition(),
+                b'<' if self
+                    .w
+                    .peek(1)
+                    .is_some_and(|n| n.is_ascii_alphabetic() || n == b'/' || n == b'!') =>
+                {
+                    let (tag_name, tag_closing, tag_self_closing) = self.tag();
+                    if !tag_name.eq_ignore_ascii_case(&name) {
+                        continue;
+                    }
+                    if tag_closing {
+                        depth -= 1;
+                        if depth == 0 {
+                            return;
+                        }
+                    } else if !tag_self_closing {
+                        depth += 1;
+                    }
+                }
+                _ => self.w.blank(),
+            }
+        }
+    }
+
+    /// Replace a tag, the position is at the `<`. Returns the tag name, whether it is a closing
+    /// tag and whether it closes itself.
+    fn tag(&mut self) -> (String, bool, bool) {
+        if self.w.src[self.w.pos..].starts_with(b"<!--") {
+            while !self.w.at_end() && !self.w.src[self.w.pos..].starts_with(b"-->") {
+                self.w.blank();
+            }
+            self.w.blank_n(3);
+            return (String::new(), false, true);
+        }
+        self.w.blank();
+        let closing = self.w.peek(0) == Some(b'/');
+        if closing {
+            self.w.blank();
+        }
+        let name_len = self.w.src[self.w.pos..]
+            .iter()
+            .position(|b| !(b.is_ascii_alphanumeric() || matches!(b, b'-' | b':' | b'.' | b'_')))
+            .unwrap_or(self.w.src.len() - self.w.pos);
+        let name =
+            String::from_utf8_lossy(&self.w.src[self.w.pos..self.w.pos + name_len]).to_string();
+        self.w.blank_n(name_len);
+        let mut self_closing = false;
+        while let Some(b) = self.w.peek(0) {
+            match b {
+                b'@' => self.transition(),
+                b'/' if self.w.peek(1) == Some(b'>') => {
+                    self_closing = true;
+                    self.w.blank_n(2);
+                    break;
+                }
+                b'>' => {
+                    self.w.blank();
+                    break;
+                }
+                _ => self.w.blank(),
+            }
+        }
+        (name, closing, self_closing)
+    }
+
+    /// VB code blocks are not analyzed, the whole block is replaced up to its closing keyword.
+    fn vb_block(&mut self, open: &str, close: &str) {
+        let mut depth = 0;
+        while !self.w.at_end() {
+            let end = self.w.src[self.w.pos..]
+                .iter()
+                .position(|b| *b == b'\n')
+                .map_or(self.w.src.len(), |i| self.w.pos + i + 1);
+            let line = String::from_utf8_lossy(&self.w.src[self.w.pos..end])
+                .trim()
+                .trim_start_matches('@')
+                .to_lowercase();
+            let opens = line.starts_with(&format!("{} ", open)) || line == open;
+            if opens && (open != "if" || line.ends_with("then")) {
+                depth += 1;
+            } else if line.starts_with(close) {
+                depth -= 1;
+            }
+            self.w.blank_n(end - self.w.pos);
+            if depth <= 0 {
+                return;
+            }
+        }
+    }
+}
+
+fn is_void_element(name: &str) -> bool {
+    [
+        "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source",
+        "track", "wbr",
+    ]
+    .iter()
+    .any(|v| name.eq_ignore_ascii_case(v))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The C# text at the position of `needle` in the view.
+    fn code_at<'a>(view: &str, document: &'a ViewDocument, needle: &str) -> &'a str {
+        let start = view.find(needle).expect("needle in view");
+        &document.code[start..start + needle.len()]
+    }
+
+    #[test]
+    fn test_directives() {
+        let view = "@using System.Web.Mvc\n@model NerdDinner.Models.Dinner\n<h2>Edit</h2>\n";
+        let document = parse_view(view, false, &[]);
+        assert_eq!(
+            document.code,
+            " using System.Web.Mvc;\n                         Dinner Model; using NerdDinner.Models;\n             \n using System.Web.Mvc; HtmlHelper Html; UrlHelper Url; dynamic ViewBag;"
+        );
+        let document = parse_view(
+            "@inherits System.Web.Mvc.WebViewPage<Demo.Dinner>\n",
+            false,
+            &["Demo.Helpers".to_string()],
+        );
+        assert_eq!(
+            document.code,
+            "                         WebViewPage<     Dinner> Base; using System.Web.Mvc; using Demo;\n using System.Web.Mvc; using Demo.Helpers; HtmlHelper Html; UrlHelper Url; dynamic ViewBag; Dinner Model;"
+        );
+    }
+
+    #[test]
+    fn test_expressions_keep_their_position() {
+        let view = r#"<p>@Html.ActionLink("Edit", "Edit", new { id = Model.DinnerID }) | @Model.Title.</p>
+<span>@(Model.RSVPs.Count + 1)</span> me@example.com @@home"#;
+        let document = parse_view(view, false, &[]);
+        assert_eq!(
+            code_at(view, &document, r#"@Html.ActionLink("Edit""#),
+            r#"!Html.ActionLink("Edit""#
+        );
+        assert_eq!(code_at(view, &document, "@Model.Title.<"), "!Model.Title; ");
+        assert_eq!(
+            code_at(view, &document, "@(Model.RSVPs.Count + 1)<"),
+            "!(Model.RSVPs.Count + 1);"
+        );
+        assert_eq!(code_at(view, &document, "me@example.com"), "              ");
+        assert_eq!(code_at(view, &document, "@@home"), "      ");
+        assert_eq!(document.code.lines().count(), view.lines().count());
+    }
+
+    #[test]
+    fn test_code_blocks_and_statements() {
+        let view = r#"@{
+    ViewBag.Title = "Edit: " + Model.Title;
+}
+@using (Html.BeginForm()) {
+    <fieldset>
+        <legend>@Model.Title</legend>
+        @: Hosted by @Model.HostedBy
+    </fieldset>
+}
+@if (Model.IsHostedBy(User.Identity.Name)) {
+    <p>Host</p>
+} else {
+    <text>Guest</text>
+}"#;
+        let document = parse_view(view, false, &[]);
+        let lines: Vec<&str> = document.code.lines().collect();
+        assert_eq!(lines[0], " {");
+        assert_eq!(lines[1], r#"    ViewBag.Title = "Edit: " + Model.Title;"#);
+        assert_eq!(lines[3], " using (Html.BeginForm()) {");
+        assert_eq!(lines[5].trim(), "!Model.Title;");
+        assert_eq!(lines[5].find('!'), view.lines().nth(5).unwrap().find('@'));
+        assert_eq!(lines[6].trim(), "!Model.HostedBy;");
+        assert_eq!(lines[9], " if (Model.IsHostedBy(User.Identity.Name)) {");
+        assert_eq!(lines[10].trim(), "");
+        assert_eq!(lines[11], "} else {");
+        assert_eq!(lines[12].trim(), "");
+    }
+
+    #[test]
+    fn test_comments_and_sections() {
+        let view = "@* @Html.Hidden(\"x\") *@\n@section Scripts {\n    <script src=\"@Url.Content(\"~/a.js\")\"></script>\n}\n";
+        let document = parse_view(view, false, &[]);
+        let lines: Vec<&str> = document.code.lines().collect();
+        assert_eq!(lines[0].trim(), "");
+        assert_eq!(lines[1].trim(), "");
+        assert_eq!(lines[2].trim(), r#"!Url.Content("~/a.js");"#);
+        assert_eq!(lines[3].trim(), "");
+    }
+
+    #[test]
+    fn test_vbhtml() {
+        let view = "@ModelType NerdDinner.Models.Dinner\n@Imports System.Web.Mvc\n@Code\n    Dim x = 1\nEnd Code\n<p>@Html.ActionLink(\"Edit\", \"Edit\")</p>\n";
+        let document = parse_view(view, true, &[]);
+        let lines: Vec<&str> = document.code.lines().collect();
+        assert_eq!(
+            lines[0],
+            "                             Dinner Model; using NerdDinner.Models;"
+        );
+        assert_eq!(lines[1], "using    System.Web.Mvc;");
+        assert_eq!(lines[2].trim(), "");
+        assert_eq!(lines[3].trim(), "");
+        assert_eq!(lines[4].trim(), "");
+        assert_eq!(
+            lines[5].trim_end(),
+            r#"   !Html.ActionLink("Edit", "Edit");"#
+        );
+    }
+
+    #[test]
+    fn test_view_graph_query() {
+        use stack_graphs::graph::StackGraph;
+        use tree_sitter_stack_graphs::NoCancellation;
+
+        use crate::c_sharp_graph::language_config::SourceNodeLanguageConfiguration;
+        use crate::c_sharp_graph::loader::load_graph_for_file;
+        use crate::c_sharp_graph::query::{Query, QueryOptions, QueryType};
+
+        let dir = tempfile::tempdir().unwrap();
+        let views = dir.path().join("Views");
+        std::fs::create_dir_all(views.join("Dinners")).unwrap();
+        std::fs::write(
+            dir.path().join("Library.cs"),
+            r#"namespace System.Web.Mvc
+{
+    public class HtmlHelper
+    {
+        public string ActionLink(string text, string action) { return text; }
+    }
+    public class UrlHelper
+    {
+        public string Content(string path) { return path; }
+    }
+}
+namespace Demo.Models
+{
+    public class Dinner
+    {
+        public string Title { get; set; }
+    }
+}
+namespace Demo.Helpers
+{
+    public class Formatter
+    {
+        public static string Date(object value) { return ""; }
+    }
+}
+"#,
+        )
+        .unwrap();
+        std::fs::write(
+            views.join("Web.config"),
+            r#"<configuration>
+  <system.web.webPages.razor>
+    <pages pageBaseType="System.Web.Mvc.WebViewPage">
+      <namespaces>
+        <add namespace="Demo.Helpers" />
+      </namespaces>
+    </pages>
+  </system.web.webPages.razor>
+</configuration>
+"#,
+        )
+        .unwrap();
+        let view = views.join("Dinners").join("Edit.cshtml");
+        std::fs::write(
+            &view,
+            r#"@model Demo.Models.Dinner
+<h2>@Model.Title</h2>
+<p>@Html.ActionLink("Edit", "Edit")</p>
+<img src="@Url.Content("~/a.png")" />
+<span>@Formatter.Date(ViewBag.When)</span>
+"#,
+        )
+        .unwrap();
+        let lc = SourceNodeLanguageConfiguration::new(&NoCancellation).unwrap();
+        let mut graph = StackGraph::new();
+        let _ = graph.add_from_graph(&lc.language_config.builtins);
+        for file in [dir.path().join("Library.cs"), view] {
+            load_graph_for_file(
+                file,
+                &mut graph,
+                &lc.language_config,
+                &lc.source_type_node_info,
+                &NoCancellation,
+            )
+            .unwrap();
+        }
+        let query = |pattern: &str| -> Vec<(usize, String)> {
+            QueryType::All {
+                graph: &graph,
+                source_type: &lc.source_type_node_info,
+                options: QueryOptions::default(),
+            }
+            .query(pattern.to_string())
+            .unwrap()
+            .into_iter()
+            .filter(|r| r.file_uri.ends_with("Edit.cshtml"))
+            .map(|r| {
+                (
+                    r.line_number,
+                    r.variables["symbol"].as_str().unwrap().to_string(),
+                )
+            })
+            .collect()
+        };
+        assert!(query("Demo.Models.Dinner").contains(&(1, "Model.Title".to_string())));
+        assert_eq!(
+            query("System.Web.Mvc.HtmlHelper.ActionLink"),
+            vec![(2, "Html.ActionLink".to_string())]
+        );
+        assert_eq!(
+            query("System.Web.Mvc.UrlHelper.Content"),
+            vec![(3, "Url.Content".to_string())]
+        );
+        assert!(query("Demo.Helpers.Formatter.Date").contains(&(4, "Formatter.Date".to_string())));
+    }
+}
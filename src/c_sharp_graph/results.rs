@@ -1,18 +1,22 @@
 use std::{collections::BTreeMap, fmt::Display, str::FromStr};
 
 use prost_types::{Struct, Value};
-use serde::{Deserialize, Deserializer};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serde_json::json;
 
 use crate::analyzer_service::{
     IncidentContext, Location as ProtoLocation, Position as ProtoPosition,
 };
 
-#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
 pub struct ResultNode {
     #[serde(rename = "fileURI")]
     pub file_uri: String,
-    #[serde(rename = "LineNumber", deserialize_with = "string_to_usize")]
+    #[serde(
+        rename = "LineNumber",
+        deserialize_with = "string_to_usize",
+        serialize_with = "usize_to_string"
+    )]
     pub line_number: usize,
     pub variables: BTreeMap<std::string::String, serde_json::Value>,
     #[serde(rename = "codeLocation")]
@@ -55,6 +59,32 @@ impl Ord for ResultNode {
     }
 }
 
+impl ResultNode {
+    /// Build the FQDN of a referenced result from its variables, falling back to the symbol.
+    pub(crate) fn get_fqdn_symbol(&self) -> String {
+        let parts: Vec<&str> = [
+            "fqdn_namespace",
+            "fqdn_class",
+            "fqdn_method",
+            "fqdn_field",
+            "fqdn_property",
+            "fqdn_event",
+        ]
+        .iter()
+        .filter_map(|k| self.variables.get(*k).and_then(|v| v.as_str()))
+        .collect();
+        if parts.is_empty() {
+            return self
+                .variables
+                .get("symbol")
+                .and_then(|v| v.as_str())
+                .unwrap_or_default()
+                .to_string();
+        }
+        parts.join(".")
+    }
+}
+
 fn string_to_usize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
 where
     T: Deserialize<'de> + FromStr,
@@ -74,7 +104,19 @@ where
     }
 }
 
-fn serde_json_to_prost(json: serde_json::Value) -> prost_types::Value {
+/// The line number is a string in the JSON of an int64 proto field.
+fn usize_to_string<S>(value: &usize, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_str(&value.to_string())
+}
+
+fn is_zero(value: &usize) -> bool {
+    *value == 0
+}
+
+pub(crate) fn serde_json_to_prost(json: serde_json::Value) -> prost_types::Value {
     use prost_types::value::Kind::*;
     use serde_json::Value::*;
     prost_types::Value {
@@ -97,6 +139,49 @@ fn serde_json_to_prost(json: serde_json::Value) -> prost_types::Value {
     }
 }
 
+fn prost_to_serde_json(value: prost_types::Value) -> serde_json::Value {
+    use prost_types::value::Kind::*;
+    match value.kind {
+        None | Some(NullValue(_)) => serde_json::Value::Null,
+        Some(BoolValue(v)) => serde_json::Value::Bool(v),
+        // Numbers are all f64 in a proto struct, whole ones were integers to begin with.
+        Some(NumberValue(n)) if n.fract() == 0.0 && n.abs() < i64::MAX as f64 => {
+            serde_json::Value::from(n as i64)
+        }
+        Some(NumberValue(n)) => serde_json::Value::from(n),
+        Some(StringValue(s)) => serde_json::Value::String(s),
+        Some(ListValue(v)) => {
+            serde_json::Value::Array(v.values.into_iter().map(prost_to_serde_json).collect())
+        }
+        Some(StructValue(v)) => serde_json::Value::Object(
+            v.fields
+                .into_iter()
+                .map(|(k, v)| (k, prost_to_serde_json(v)))
+                .collect(),
+        ),
+    }
+}
+
+impl From<&IncidentContext> for ResultNode {
+    fn from(val: &IncidentContext) -> Self {
+        ResultNode {
+            file_uri: val.file_uri.clone(),
+            line_number: val.line_number.unwrap_or_default() as usize,
+            variables: val
+                .variables
+                .clone()
+                .map(|v| {
+                    v.fields
+                        .into_iter()
+                        .map(|(k, v)| (k, prost_to_serde_json(v)))
+                        .collect()
+                })
+                .unwrap_or_default(),
+            code_location: val.code_location.map(Into::into).unwrap_or_default(),
+        }
+    }
+}
+
 impl From<&ResultNode> for IncidentContext {
     fn from(val: &ResultNode) -> Self {
         let x = serde_json_to_prost(json!(val.variables.clone()));
@@ -124,13 +209,22 @@ impl From<&ResultNode> for IncidentContext {
     }
 }
 
-#[derive(Debug, Deserialize, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Position {
     pub line: usize,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_zero")]
     pub character: usize,
 }
 
+impl From<ProtoPosition> for Position {
+    fn from(val: ProtoPosition) -> Self {
+        Position {
+            line: val.line as usize,
+            character: val.character as usize,
+        }
+    }
+}
+
 impl From<Position> for ProtoPosition {
     fn from(val: Position) -> Self {
         ProtoPosition {
@@ -140,7 +234,7 @@ impl From<Position> for ProtoPosition {
     }
 }
 
-#[derive(Debug, Deserialize, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Location {
     #[serde(rename = "startPosition")]
     pub start_position: Position,
@@ -148,6 +242,21 @@ pub struct Location {
     pub end_position: Position,
 }
 
+impl Location {
+    pub(crate) fn contains(&self, other: &Location) -> bool {
+        self.start_position <= other.start_position && other.end_position <= self.end_position
+    }
+}
+
+impl From<ProtoLocation> for Location {
+    fn from(val: ProtoLocation) -> Self {
+        Location {
+            start_position: val.start_position.map(Into::into).unwrap_or_default(),
+            end_position: val.end_position.map(Into::into).unwrap_or_default(),
+        }
+    }
+}
+
 impl From<Location> for ProtoLocation {
     fn from(val: Location) -> Self {
         ProtoLocation {
@@ -156,3 +265,37 @@ impl From<Location> for ProtoLocation {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_incident_context_round_trip() {
+        let mut variables = BTreeMap::new();
+        variables.insert("symbol".to_string(), json!("HttpContext.Current"));
+        variables.insert("line".to_string(), json!(12));
+        variables.insert("attributes".to_string(), json!({"runat": "server"}));
+        let result = ResultNode {
+            file_uri: "file:///src/Home.cs".to_string(),
+            line_number: 12,
+            variables,
+            code_location: Location {
+                start_position: Position {
+                    line: 12,
+                    character: 8,
+                },
+                end_position: Position {
+                    line: 12,
+                    character: 27,
+                },
+            },
+        };
+        let incident: IncidentContext = (&result).into();
+        assert_eq!(ResultNode::from(&incident), result);
+        // The same JSON as the proto, with the line number as a string.
+        let json = serde_json::to_value(&result).unwrap();
+        assert_eq!(json["LineNumber"], json!("12"));
+        assert_eq!(serde_json::from_value::<ResultNode>(json).unwrap(), result);
+    }
+}
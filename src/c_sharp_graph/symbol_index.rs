@@ -0,0 +1,184 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use stack_graphs::{
+    arena::Handle,
+    graph::{File, Node, StackGraph},
+};
+
+use crate::c_sharp_graph::query::{get_syntax_type, SyntaxType};
+
+/// Lookups that the queries would otherwise scan every node or symbol of the graph for. It is
+/// built in a single pass over the nodes, so it has to be rebuilt whenever the graph changes.
+#[derive(Debug, Default)]
+pub struct SymbolIndex {
+    /// Definition nodes with their syntax type by symbol, sorted by node.
+    definitions: HashMap<String, Vec<(Handle<Node>, SyntaxType)>>,
+    /// Namespace declaration nodes by the declared namespace.
+    namespace_declarations: BTreeMap<String, Vec<Handle<Node>>>,
+    /// The files that import a namespace by the namespace.
+    importing_files: BTreeMap<String, BTreeSet<Handle<File>>>,
+    /// The namespaces imported by a file.
+    file_imports: HashMap<Handle<File>, BTreeSet<String>>,
+    compilation_units: HashMap<Handle<File>, Handle<Node>>,
+}
+
+impl SymbolIndex {
+    pub fn new(graph: &StackGraph) -> SymbolIndex {
+        let mut index = SymbolIndex::default();
+        for node_handle in graph.iter_nodes() {
+            let node = &graph[node_handle];
+            let (Some(file), Some(symbol)) = (node.file(), node.symbol()) else {
+                continue;
+            };
+            let Some(syntax_type) = get_syntax_type(graph, node_handle) else {
+                continue;
+            };
+            let symbol = graph[symbol].to_string();
+            match syntax_type {
+                SyntaxType::CompUnit => {
+                    index.compilation_units.insert(file, node_handle);
+                }
+                SyntaxType::Import => {
+                    index
+                        .importing_files
+                        .entry(symbol.clone())
+                        .or_default()
+                        .insert(file);
+                    index
+                        .file_imports
+                        .entry(file)
+                        .or_default()
+                        .insert(symbol.clone());
+                }
+                SyntaxType::NamespaceDeclaration => {
+                    index
+                        .namespace_declarations
+                        .entry(symbol.clone())
+                        .or_default()
+                        .push(node_handle);
+                }
+                _ => {}
+            }
+            if node.is_definition() {
+                index
+                    .definitions
+                    .entry(symbol)
+                    .or_default()
+                    .push((node_handle, syntax_type));
+            }
+        }
+        for definitions in index.definitions.values_mut() {
+            definitions.sort_by_key(|(node, _)| *node);
+        }
+        index
+    }
+
+    /// The definitions of `symbol` along with their syntax type, sorted by node.
+    pub(crate) fn definitions(&self, symbol: &str) -> &[(Handle<Node>, SyntaxType)] {
+        self.definitions.get(symbol).map_or(&[], |d| d.as_slice())
+    }
+
+    pub(crate) fn namespace_declarations(
+        &self,
+    ) -> impl Iterator<Item = (&String, &Vec<Handle<Node>>)> {
+        self.namespace_declarations.iter()
+    }
+
+    pub(crate) fn importing_files(
+        &self,
+    ) -> impl Iterator<Item = (&String, &BTreeSet<Handle<File>>)> {
+        self.importing_files.iter()
+    }
+
+    pub(crate) fn file_imports(&self, file: Handle<File>) -> BTreeSet<String> {
+        self.file_imports.get(&file).cloned().unwrap_or_default()
+    }
+
+    pub(crate) fn compilation_unit(&self, file: Handle<File>) -> Option<Handle<Node>> {
+        self.compilation_units.get(&file).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tree_sitter_stack_graphs::NoCancellation;
+
+    use crate::c_sharp_graph::{
+        language_config::SourceNodeLanguageConfiguration, loader::load_graph_for_file,
+    };
+
+    fn build_graph() -> (StackGraph, Handle<File>) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Settings.cs");
+        std::fs::write(
+            &path,
+            r#"using System.Configuration;
+using System.Web;
+
+namespace Demo.Web
+{
+    public class Settings
+    {
+        public string Read()
+        {
+            var name = ConfigurationManager.AppSettings["name"];
+            return name;
+        }
+    }
+}
+"#,
+        )
+        .unwrap();
+        let lc = SourceNodeLanguageConfiguration::new(&NoCancellation).unwrap();
+        let mut graph = StackGraph::new();
+        let _ = graph.add_from_graph(&lc.language_config.builtins);
+        let (file, _) = load_graph_for_file(
+            path,
+            &mut graph,
+            &lc.language_config,
+            &lc.source_type_node_info,
+            &NoCancellation,
+        )
+        .unwrap()
+        .unwrap();
+        (graph, file)
+    }
+
+    #[test]
+    fn test_index_imports_and_namespaces() {
+        let (graph, file) = build_graph();
+        let index = SymbolIndex::new(&graph);
+
+        assert_eq!(
+            index.file_imports(file),
+            BTreeSet::from(["System.Configuration".to_string(), "System.Web".to_string()])
+        );
+        let importing: Vec<&String> = index.importing_files().map(|(ns, _)| ns).collect();
+        assert_eq!(importing, vec!["System.Configuration", "System.Web"]);
+        let namespaces: Vec<&String> = index.namespace_declarations().map(|(ns, _)| ns).collect();
+        assert_eq!(namespaces, vec!["Demo.Web"]);
+        assert!(index.compilation_unit(file).is_some());
+    }
+
+    #[test]
+    fn test_index_definitions() {
+        let (graph, _) = build_graph();
+        let index = SymbolIndex::new(&graph);
+
+        assert!(index
+            .definitions("Settings")
+            .iter()
+            .any(|(_, syntax_type)| *syntax_type == SyntaxType::ClassDef));
+        assert!(index
+            .definitions("Read")
+            .iter()
+            .any(|(_, syntax_type)| *syntax_type == SyntaxType::MethodName));
+        assert!(index
+            .definitions("name")
+            .iter()
+            .all(|(node, _)| graph[*node].is_definition()));
+        assert!(index.definitions("Missing").is_empty());
+    }
+}
@@ -0,0 +1,554 @@
+use std::collections::{HashMap, VecDeque};
+use std::ops::Range;
+use std::path::Path;
+
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use stack_graphs::arena::Handle;
+use stack_graphs::graph::{File, NodeID, StackGraph};
+use tracing::debug;
+use tree_sitter_stack_graphs::{BuildError, CancellationFlag, StackGraphLanguage, Variables};
+
+use crate::c_sharp_graph::loader::{SOURCE_TYPE_NODE, TOP_LEVEL_CLASS, TOP_LEVEL_METHOD};
+use crate::c_sharp_graph::query::SyntaxType;
+
+/// The method the code of a view is rendered by.
+const VIEW_METHOD: &str = "Execute";
+
+/// The key of the [`FileAnalyzers`] entry of the views with the extension, the special files
+/// of a language are otherwise looked up by their file name.
+///
+/// [`FileAnalyzers`]: tree_sitter_stack_graphs::loader::FileAnalyzers
+pub(crate) fn view_file_key(extension: &str) -> String {
+    format!("*.{}", extension.to_ascii_lowercase())
+}
+
+/// A view converted to C#.
+pub(crate) struct ViewDocument {
+    pub(crate) code: String,
+    /// The byte ranges of `code` for each code construct of the view, nested constructs have
+    /// their own segment.
+    pub(crate) segments: Vec<Range<usize>>,
+    removed: Vec<bool>,
+}
+
+impl ViewDocument {
+    /// Replace the segments containing the errors with whitespace, the smallest segment around
+    /// an error goes first. Returns false when there was nothing left to remove.
+    pub(crate) fn remove_segments(&mut self, errors: &[Range<usize>]) -> bool {
+        let mut to_remove = vec![];
+        for error in errors {
+            let live = |i: &usize| !self.removed[*i];
+            let containing = (0..self.segments.len())
+                .filter(live)
+                .filter(|i| {
+                    let s = &self.segments[*i];
+                    s.start <= error.end && error.start <= s.end
+                })
+                .min_by_key(|i| self.segments[*i].len());
+            // A missing token can be reported after the segment that needs it.
+            let found = containing.or_else(|| {
+                (0..self.segments.len())
+                    .filter(live)
+                    .filter(|i| self.segments[*i].start <= error.start)
+                    .max_by_key(|i| self.segments[*i].start)
+            });
+            if let Some(i) = found {
+                to_remove.push(i);
+            }
+        }
+        if to_remove.is_empty() {
+            // The errors are not near any code, fall back to an empty view.
+            to_remove = (0..self.segments.len())
+                .filter(|i| !self.removed[*i])
+                .collect();
+            if to_remove.is_empty() {
+                return false;
+            }
+        }
+        let mut bytes = std::mem::take(&mut self.code).into_bytes();
+        for i in to_remove {
+            self.removed[i] = true;
+            for b in bytes[self.segments[i].clone()].iter_mut() {
+                if *b != b'\n' && *b != b'\r' {
+                    *b = b' ';
+                }
+            }
+        }
+        self.code = String::from_utf8(bytes).expect("removing segments keeps valid UTF-8");
+        true
+    }
+}
+
+/// Build the graph for a converted view with the C# TSG. Views are full of code that does not
+/// make sense on its own, the segments that do not parse are dropped and the rest of the view is
+/// built again.
+///
+/// The loader passes the local ID of the source type node of the file in the
+/// [`SOURCE_TYPE_NODE`] global.
+pub(crate) fn build_view_graph(
+    sgl: &StackGraphLanguage,
+    stack_graph: &mut StackGraph,
+    file: Handle<File>,
+    path: &Path,
+    document: &mut ViewDocument,
+    globals: &HashMap<String, String>,
+    cancellation_flag: &dyn CancellationFlag,
+) -> Result<(), BuildError> {
+    let source_type_node = globals
+        .get(SOURCE_TYPE_NODE)
+        .and_then(|id| id.parse().ok())
+        .map(|id| NodeID::new_in_file(file, id))
+        .ok_or_else(|| {
+            BuildError::ConversionError(
+                SOURCE_TYPE_NODE.to_string(),
+                path.display().to_string(),
+                globals.get(SOURCE_TYPE_NODE).cloned().unwrap_or_default(),
+            )
+        })?;
+    loop {
+        let mut builder = sgl.builder_into_stack_graph(stack_graph, file, &document.code);
+        let graph_node = builder.inject_node(source_type_node);
+        let mut variables = Variables::new();
+        for (name, value) in globals.iter().filter(|(name, _)| *name != SOURCE_TYPE_NODE) {
+            variables
+                .add(name.as_str().into(), value.as_str().into())
+                .expect("failed to add global variable");
+        }
+        variables
+            .add(SOURCE_TYPE_NODE.into(), graph_node.into())
+            .expect("adding source type node");
+        variables
+            .add(TOP_LEVEL_CLASS.into(), view_class_name(path).into())
+            .expect("adding view class name");
+        variables
+            .add(TOP_LEVEL_METHOD.into(), VIEW_METHOD.into())
+            .expect("adding view method name");
+
+        match builder.build(&variables, cancellation_flag) {
+            Ok(()) => return Ok(()),
+            Err(BuildError::ParseErrors(errors)) => {
+                let ranges: Vec<Range<usize>> = errors
+                    .errors()
+                    .iter()
+                    .map(|e| e.node().start_byte()..e.node().end_byte())
+                    .collect();
+                debug!(file=?path, "dropping code segments with parse errors: {:?}", ranges);
+                if !document.remove_segments(&ranges) {
+                    return Err(BuildError::ParseErrors(errors));
+                }
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// The namespaces the `Web.config` files of the view and its parent folders import for the
+/// views, from the `<namespaces>` of the `section` element, e.g. `system.web.webPages.razor`.
+pub(crate) fn web_config_namespaces(path: &Path, section: &str) -> Vec<String> {
+    let mut namespaces = vec![];
+    for dir in path.ancestors().skip(1) {
+        let Some(config) = ["Web.config", "web.config"]
+            .iter()
+            .map(|name| dir.join(name))
+            .find(|config| config.is_file())
+        else {
+            continue;
+        };
+        let Ok(source) = std::fs::read_to_string(&config) else {
+            continue;
+        };
+        for namespace in config_namespaces(&source, section) {
+            if !namespaces.contains(&namespace) {
+                namespaces.push(namespace);
+            }
+        }
+    }
+    namespaces
+}
+
+fn config_namespaces(source: &str, section: &str) -> Vec<String> {
+    let mut reader = Reader::from_str(source);
+    reader.config_mut().trim_text(true);
+    let mut namespaces = vec![];
+    let mut elements: Vec<Vec<u8>> = vec![];
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e)) => elements.push(e.name().as_ref().to_vec()),
+            Ok(Event::Empty(e))
+                if e.name().as_ref() == b"add"
+                    && elements.last().is_some_and(|n| n == b"namespaces")
+                    && elements.iter().any(|n| n == section.as_bytes()) =>
+            {
+                let namespace = e
+                    .attributes()
+                    .flatten()
+                    .find(|a| a.key.as_ref() == b"namespace");
+                if let Some(namespace) = namespace {
+                    namespaces.push(String::from_utf8_lossy(&namespace.value).trim().to_string());
+                }
+            }
+            Ok(Event::End(_)) => {
+                elements.pop();
+            }
+            Ok(Event::Eof) => break,
+            Err(e) => {
+                debug!("unable to read the namespaces of the configuration: {}", e);
+                break;
+            }
+            _ => {}
+        }
+    }
+    namespaces
+}
+
+/// Add a reference to the type at `range` of the view, for the types that the markup names such
+/// as the base class of a page. The type is resolved by its name and the imports of the view.
+pub(crate) fn add_type_reference(
+    stack_graph: &mut StackGraph,
+    file: Handle<File>,
+    source: &str,
+    range: Range<usize>,
+) {
+    let type_name = &source[range.clone()];
+    let type_name = type_name.split('<').next().unwrap_or(type_name).trim();
+    let name = type_name.rsplit('.').next().unwrap_or(type_name);
+    let id = stack_graph.new_node_id(file);
+    let symbol = stack_graph.add_symbol(name);
+    let Some(node) = stack_graph.add_push_symbol_node(id, symbol, true) else {
+        return;
+    };
+    let syntax_type = stack_graph.add_string(SyntaxType::TypeReference.to_string());
+    let source_info = stack_graph.source_info_mut(node);
+    source_info.syntax_type = syntax_type.into();
+    for (position, offset) in [
+        (&mut source_info.span.start, range.start),
+        (&mut source_info.span.end, range.start + type_name.len()),
+    ] {
+        let line_start = source[..offset].rfind('\n').map_or(0, |i| i + 1);
+        position.line = source[..offset].matches('\n').count();
+        position.column.utf8_offset = offset - line_start;
+        position.column.utf16_offset = source[line_start..offset].encode_utf16().count();
+        position.column.grapheme_offset = source[line_start..offset].chars().count();
+    }
+}
+
+/// The class a view is compiled into, named after the file and its folder the way ASP.NET names
+/// the generated page classes, e.g. `_Page_Home_Index_cshtml`.
+pub(crate) fn view_class_name(path: &Path) -> String {
+    let mut name = String::from("_Page");
+    let folder = path.parent().and_then(|p| p.file_name());
+    for part in folder.into_iter().chain(path.file_name()) {
+        name.push('_');
+        name.extend(part.to_string_lossy().bytes().map(|b| {
+            if is_identifier_byte(b) {
+                b as char
+            } else {
+                '_'
+            }
+        }));
+    }
+    name
+}
+
+/// Writes the C# for a view, every byte of the view is either copied as code or replaced by a
+/// space. Text that has to be added, such as a `;` after an expression, takes the place of the
+/// following markup and is only inserted when the line ends first.
+pub(crate) struct ViewWriter<'a> {
+    pub(crate) src: &'a [u8],
+    pub(crate) pos: usize,
+    out: Vec<u8>,
+    pending: VecDeque<u8>,
+    segments: Vec<Range<usize>>,
+}
+
+impl<'a> ViewWriter<'a> {
+    pub(crate) fn new(source: &'a str) -> ViewWriter<'a> {
+        ViewWriter {
+            src: source.as_bytes(),
+            pos: 0,
+            out: Vec::with_capacity(source.len()),
+            pending: VecDeque::new(),
+            segments: vec![],
+        }
+    }
+
+    /// Add code that is not in the view after its end, as its own segment. This is for what the
+    /// page gets from its base class and configuration, there is no place for it in the view.
+    pub(crate) fn append(&mut self, code: &str) {
+        self.flush();
+        let start = self.out.len();
+        self.out.push(b' ');
+        self.out.extend(code.bytes());
+        self.segments.push(start..self.out.len());
+    }
+
+    pub(crate) fn finish(mut self) -> ViewDocument {
+        self.flush();
+        // Code is only ever copied at ASCII delimiters, so this is always valid UTF-8.
+        let code = String::from_utf8(self.out).unwrap_or_else(|e| {
+            e.into_bytes()
+                .iter()
+                .map(|b| if b.is_ascii() { *b as char } else { ' ' })
+                .collect()
+        });
+        let removed = vec![false; self.segments.len()];
+        ViewDocument {
+            code,
+            segments: self.segments,
+            removed,
+        }
+    }
+
+    pub(crate) fn peek(&self, offset: usize) -> Option<u8> {
+        self.src.get(self.pos + offset).copied()
+    }
+
+    pub(crate) fn at_end(&self) -> bool {
+        self.pos >= self.src.len()
+    }
+
+    pub(crate) fn starts_with(&self, text: &[u8]) -> bool {
+        self.src[self.pos..].starts_with(text)
+    }
+
+    pub(crate) fn flush(&mut self) {
+        self.out.extend(self.pending.drain(..));
+    }
+
+    pub(crate) fn blank(&mut self) {
+        let b = self.src[self.pos];
+        self.pos += 1;
+        if b == b'\n' || b == b'\r' {
+            self.flush();
+            self.out.push(b);
+        } else {
+            let replacement = self.pending.pop_front().unwrap_or(b' ');
+            self.out.push(replacement);
+        }
+    }
+
+    pub(crate) fn blank_n(&mut self, n: usize) {
+        for _ in 0..n {
+            if self.at_end() {
+                return;
+            }
+            self.blank();
+        }
+    }
+
+    pub(crate) fn copy(&mut self) {
+        self.replace(self.src[self.pos]);
+    }
+
+    pub(crate) fn copy_n(&mut self, n: usize) {
+        for _ in 0..n {
+            if self.at_end() {
+                return;
+            }
+            self.copy();
+        }
+    }
+
+    pub(crate) fn replace(&mut self, b: u8) {
+        self.flush();
+        self.out.push(b);
+        self.pos += 1;
+    }
+
+    pub(crate) fn suffix(&mut self, text: &str) {
+        self.pending.extend(text.bytes());
+    }
+
+    pub(crate) fn start_segment(&mut self) -> usize {
+        self.flush();
+        self.out.len()
+    }
+
+    pub(crate) fn end_segment(&mut self, start: usize) {
+        let end = self.out.len() + self.pending.len();
+        self.segments.push(start..end);
+    }
+
+    pub(crate) fn word_at(&self, pos: usize) -> &'a str {
+        let end = self.src[pos..]
+            .iter()
+            .position(|b| !is_identifier_byte(*b))
+            .map_or(self.src.len(), |i| pos + i);
+        std::str::from_utf8(&self.src[pos..end]).unwrap_or_default()
+    }
+
+    pub(crate) fn skip_whitespace_from(&self, pos: usize) -> usize {
+        self.src[pos.min(self.src.len())..]
+            .iter()
+            .position(|b| !b.is_ascii_whitespace())
+            .map_or(self.src.len(), |i| pos + i)
+    }
+
+    /// The end of the current line, without trailing whitespace.
+    pub(crate) fn line_content_end(&self) -> usize {
+        let line_end = self.src[self.pos..]
+            .iter()
+            .position(|b| *b == b'\n')
+            .map_or(self.src.len(), |i| self.pos + i);
+        let mut end = line_end;
+        while end > self.pos && self.src[end - 1].is_ascii_whitespace() {
+            end -= 1;
+        }
+        end
+    }
+}
+
+/// The namespaces of the qualified names in a type, e.g. `System.Collections.Generic` and
+/// `Demo.Models` for `System.Collections.Generic.List<Demo.Models.Dinner>`.
+pub(crate) fn type_namespaces(type_name: &str) -> Vec<String> {
+    let mut namespaces: Vec<String> = vec![];
+    for (_, name) in qualified_names(type_name) {
+        if let Some((namespace, _)) = name.rsplit_once('.') {
+            let namespace = namespace.trim_start_matches("global::");
+            if !namespaces.iter().any(|n| n == namespace) {
+                namespaces.push(namespace.to_string());
+            }
+        }
+    }
+    namespaces
+}
+
+/// The type with the namespaces of its qualified names replaced by spaces, so that it keeps its
+/// length. The types of local declarations are looked up by name, the namespaces are imported
+/// with [`type_namespaces`] instead.
+pub(crate) fn unqualified_type(type_name: &str) -> String {
+    let mut unqualified = type_name.to_string().into_bytes();
+    for (start, name) in qualified_names(type_name) {
+        if let Some(dot) = name.rfind('.') {
+            for b in unqualified[start..=start + dot].iter_mut() {
+                *b = b' ';
+            }
+        }
+    }
+    String::from_utf8(unqualified).expect("only ASCII is replaced")
+}
+
+/// The start and text of the (possibly qualified) names in a type.
+fn qualified_names(type_name: &str) -> impl Iterator<Item = (usize, &str)> {
+    let is_name_byte = |b: u8| is_identifier_byte(b) || b == b'.' || b == b':';
+    let bytes = type_name.as_bytes();
+    let mut names = vec![];
+    let mut pos = 0;
+    while pos < bytes.len() {
+        if !is_name_byte(bytes[pos]) {
+            pos += 1;
+            continue;
+        }
+        let start = pos;
+        while pos < bytes.len() && is_name_byte(bytes[pos]) {
+            pos += 1;
+        }
+        names.push((start, &type_name[start..pos]));
+    }
+    names.into_iter()
+}
+
+pub(crate) fn is_identifier_start(b: u8) -> bool {
+    b.is_ascii_alphabetic() || b == b'_'
+}
+
+pub(crate) fn is_identifier_byte(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'_'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remove_segments() {
+        let mut writer = ViewWriter::new("ab cd\nef");
+        let start = writer.start_segment();
+        writer.copy_n(2);
+        writer.suffix(";");
+        writer.end_segment(start);
+        writer.blank();
+        let start = writer.start_segment();
+        writer.copy_n(2);
+        writer.end_segment(start);
+        writer.blank();
+        writer.copy_n(2);
+        let mut document = writer.finish();
+        assert_eq!(document.code, "ab;cd\nef");
+        assert!(document.remove_segments(&[Range { start: 3, end: 4 }]));
+        assert_eq!(document.code, "ab;  \nef");
+        assert!(document.remove_segments(&[Range { start: 0, end: 0 }]));
+        assert_eq!(document.code, "     \nef");
+        // Every segment is gone.
+        assert!(!document.remove_segments(&[Range { start: 0, end: 0 }]));
+    }
+
+    #[test]
+    fn test_qualified_types() {
+        let type_name = "System.Collections.Generic.List<Demo.Models.Dinner>";
+        assert_eq!(
+            type_namespaces(type_name),
+            vec!["System.Collections.Generic", "Demo.Models"]
+        );
+        assert_eq!(
+            unqualified_type(type_name),
+            "                           List<            Dinner>"
+        );
+        assert!(type_namespaces("dynamic").is_empty());
+    }
+
+    #[test]
+    fn test_web_config_namespaces() {
+        let dir = tempfile::tempdir().unwrap();
+        let views = dir.path().join("Views");
+        std::fs::create_dir_all(views.join("Home")).unwrap();
+        std::fs::write(
+            dir.path().join("Web.config"),
+            r#"<configuration>
+  <system.web>
+    <pages><namespaces><add namespace="System.Web.UI" /></namespaces></pages>
+  </system.web>
+  <system.web.webPages.razor>
+    <pages><namespaces><add namespace="System.Web.Mvc" /></namespaces></pages>
+  </system.web.webPages.razor>
+</configuration>"#,
+        )
+        .unwrap();
+        std::fs::write(
+            views.join("Web.config"),
+            r#"<configuration>
+  <system.web.webPages.razor>
+    <pages>
+      <namespaces>
+        <add namespace="System.Web.Mvc.Html" />
+        <add namespace="System.Web.Mvc" />
+      </namespaces>
+    </pages>
+  </system.web.webPages.razor>
+</configuration>"#,
+        )
+        .unwrap();
+        let view = views.join("Home").join("Index.cshtml");
+        assert_eq!(
+            web_config_namespaces(&view, "system.web.webPages.razor"),
+            vec!["System.Web.Mvc.Html", "System.Web.Mvc"]
+        );
+        assert_eq!(
+            web_config_namespaces(&view, "system.web"),
+            vec!["System.Web.UI"]
+        );
+    }
+
+    #[test]
+    fn test_view_class_name() {
+        assert_eq!(
+            view_class_name(Path::new("/app/Views/Home/Index.cshtml")),
+            "_Page_Home_Index_cshtml"
+        );
+        assert_eq!(
+            view_class_name(Path::new("/app/Site.Master")),
+            "_Page_app_Site_Master"
+        );
+    }
+}
@@ -0,0 +1,737 @@
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Range;
+use std::path::Path;
+use std::sync::Arc;
+
+use stack_graphs::arena::Handle;
+use stack_graphs::graph::{File, StackGraph};
+use tracing::info;
+use tree_sitter_stack_graphs::{BuildError, CancellationFlag, FileAnalyzer, StackGraphLanguage};
+
+use crate::c_sharp_graph::view::{
+    add_type_reference, build_view_graph, is_identifier_byte, type_namespaces, unqualified_type,
+    ViewDocument, ViewWriter,
+};
+
+/// The page, user control, master page and application files handled by the
+/// [`WebFormsFileAnalyzer`].
+pub const WEB_FORMS_FILE_EXTENSIONS: [&str; 4] = ["aspx", "ascx", "master", "asax"];
+
+/// The namespace of the controls with the built in `asp` tag prefix.
+const ASP_CONTROLS_NAMESPACE: &str = "System.Web.UI.WebControls";
+
+/// The directive attributes that name a type, the attribute name is used for the local
+/// declaration of the type.
+const TYPE_ATTRIBUTES: [&str; 3] = ["Inherits", "TypeName", "Interface"];
+
+/// Directives that set the language of the code in the file.
+const MAIN_DIRECTIVES: [&str; 4] = ["Page", "Control", "Master", "Application"];
+
+/// Builds the graph for ASP.NET Web Forms pages (`.aspx`, `.ascx`, `.master` and `.asax`).
+///
+/// Like Razor views, the page is converted to C# top level statements that keep the position
+/// of the code in the page. Directives become `using` directives and local declarations of the
+/// types they reference, server controls become local declarations of the control type and the
+/// code nuggets are copied as statements.
+pub struct WebFormsFileAnalyzer {
+    pub sgl: Arc<StackGraphLanguage>,
+}
+
+impl WebFormsFileAnalyzer {
+    pub fn matches(path: &Path) -> bool {
+        path.extension().is_some_and(|ext| {
+            WEB_FORMS_FILE_EXTENSIONS
+                .iter()
+                .any(|e| ext.eq_ignore_ascii_case(e))
+        })
+    }
+}
+
+impl FileAnalyzer for WebFormsFileAnalyzer {
+    #[allow(clippy::needless_lifetimes)]
+    fn build_stack_graph_into<'a>(
+        &self,
+        stack_graph: &mut StackGraph,
+        file: Handle<File>,
+        path: &Path,
+        source: &str,
+        _all_paths: &mut dyn Iterator<Item = &'a Path>,
+        globals: &HashMap<String, String>,
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<(), BuildError> {
+        let mut page = WebFormsPage::parse(source);
+        let source = source.strip_prefix('\u{feff}').unwrap_or(source);
+        info!(
+            file=?path,
+            "converted page with {} code segments and {} markup elements",
+            page.document.segments.len(),
+            page.elements.len()
+        );
+        build_view_graph(
+            &self.sgl,
+            stack_graph,
+            file,
+            path,
+            &mut page.document,
+            globals,
+            cancellation_flag,
+        )?;
+        for range in page.type_references {
+            add_type_reference(stack_graph, file, source, range);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum MarkupType {
+    Directive,
+    Control,
+}
+
+impl MarkupType {
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            MarkupType::Directive => "directive",
+            MarkupType::Control => "control",
+        }
+    }
+}
+
+/// A directive or a server control of a page.
+#[derive(Debug, Clone)]
+pub(crate) struct MarkupElement {
+    pub(crate) markup_type: MarkupType,
+    /// The directive name or the control tag, e.g. `Page` or `asp:GridView`.
+    pub(crate) name: String,
+    pub(crate) attributes: BTreeMap<String, String>,
+    /// The type or namespace the element references, for controls this is resolved from the
+    /// registered tag prefix.
+    pub(crate) reference: Option<String>,
+    /// The `Src` of the user control, when the tag prefix is registered to a user control.
+    pub(crate) user_control: Option<String>,
+    /// The byte range of the directive or the opening tag.
+    pub(crate) span: Range<usize>,
+}
+
+/// A page converted to C#, with its directives and server controls.
+pub(crate) struct WebFormsPage {
+    pub(crate) document: ViewDocument,
+    pub(crate) elements: Vec<MarkupElement>,
+    /// The byte ranges of the types named by the directives and server controls.
+    pub(crate) type_references: Vec<Range<usize>>,
+}
+
+impl WebFormsPage {
+    /// Parse a page, the byte offsets are after the BOM.
+    pub(crate) fn parse(source: &str) -> WebFormsPage {
+        let source = source.strip_prefix('\u{feff}').unwrap_or(source);
+        let mut scanner = Scanner {
+            w: ViewWriter::new(source),
+            vb: false,
+            namespaces: HashMap::from([("asp".to_string(), ASP_CONTROLS_NAMESPACE.to_string())]),
+            user_controls: HashMap::new(),
+            elements: vec![],
+            type_references: vec![],
+        };
+        scanner.document();
+        // The `asp` controls are imported by every page.
+        scanner
+            .w
+            .append(&format!("using {};", ASP_CONTROLS_NAMESPACE));
+        let Scanner {
+            w,
+            namespaces,
+            user_controls,
+            mut elements,
+            type_references,
+            ..
+        } = scanner;
+        // Register directives apply to the whole page, wherever they are.
+        for element in elements
+            .iter_mut()
+            .filter(|e| e.markup_type == MarkupType::Control)
+        {
+            let Some((prefix, tag_name)) = element.name.split_once(':') else {
+                continue;
+            };
+            let prefix = prefix.to_lowercase();
+            if let Some(namespace) = namespaces.get(&prefix) {
+                element.reference = Some(format!("{}.{}", namespace, tag_name));
+            } else if let Some(src) = user_controls.get(&(prefix, tag_name.to_lowercase())) {
+                element.user_control = Some(src.clone());
+            }
+        }
+        WebFormsPage {
+            document: w.finish(),
+            elements,
+            type_references,
+        }
+    }
+}
+
+struct Attribute {
+    name: String,
+    value: String,
+    value_range: Range<usize>,
+}
+
+/// Converts the page, see [`ViewWriter`] for how the positions are kept.
+struct Scanner<'a> {
+    w: ViewWriter<'a>,
+    /// The code of VB pages is not analyzed, only the directives and controls.
+    vb: bool,
+    /// The namespaces of the tag prefixes, by lower case prefix.
+    namespaces: HashMap<String, String>,
+    /// The `Src` of the user controls, by lower case prefix and tag name.
+    user_controls: HashMap<(String, String), String>,
+    elements: Vec<MarkupElement>,
+    type_references: Vec<Range<usize>>,
+}
+
+impl Scanner<'_> {
+    fn document(&mut self) {
+        while !self.w.at_end() {
+            if self.w.starts_with(b"<%--") {
+                let end = self
+                    .find(b"--%>", self.w.pos)
+                    .map_or(self.w.src.len(), |i| i + 4);
+                self.w.blank_n(end - self.w.pos);
+            } else if self.w.starts_with(b"<%") {
+                self.nugget();
+            } else if self.w.src[self.w.pos] == b'<'
+                && self.w.peek(1).is_some_and(|b| b.is_ascii_alphabetic())
+            {
+                self.tag();
+            } else {
+                self.w.blank();
+            }
+        }
+    }
+
+    fn find(&self, needle: &[u8], from: usize) -> Option<usize> {
+        self.w.src[from..]
+            .windows(needle.len())
+            .position(|w| w == needle)
+            .map(|i| from + i)
+    }
+
+    /// Handle `<% %>`, `<%= %>`, `<%: %>`, `<%# %>`, `<%$ %>` and `<%@ %>`.
+    fn nugget(&mut self) {
+        let end = self.find(b"%>", self.w.pos + 2).unwrap_or(self.w.src.len());
+        let sigil = self.w.peek(2);
+        if sigil == Some(b'@') {
+            self.directive(end);
+            return;
+        }
+        if self.vb || sigil == Some(b'$') {
+            // Expression builders (`<%$ Resources: ... %>`) are not code.
+            self.w.blank_n(end - self.w.pos);
+        } else if matches!(sigil, Some(b'=' | b':' | b'#')) {
+            // `<%= Html.Encode(x) %>` becomes ` !( Html.Encode(x) );`, the `!` makes any
+            // expression a statement without moving it.
+            self.w.blank();
+            if sigil == Some(b'#') && self.w.peek(2) == Some(b':') {
+                self.w.blank();
+            }
+            let start = self.w.start_segment();
+            self.w.replace(b'!');
+            self.w.replace(b'(');
+            self.w.copy_n(end - self.w.pos);
+            self.w.suffix(");");
+            self.w.end_segment(start);
+        } else {
+            self.w.blank_n(2);
+            let start = self.w.start_segment();
+            self.w.copy_n(end - self.w.pos);
+            self.w.end_segment(start);
+        }
+        let nugget_end = (end + 2).min(self.w.src.len());
+        self.w.blank_n(nugget_end - self.w.pos);
+    }
+
+    /// `<%@ Import Namespace="X" %>` becomes `using X;` and the directives that reference a type
+    /// become a local declaration of that type.
+    fn directive(&mut self, end: usize) {
+        let start = self.w.pos;
+        let name_start = self.w.skip_whitespace_from(start + 3);
+        let mut name = self.w.word_at(name_start).to_string();
+        let mut attributes_start = name_start + name.len();
+        if self
+            .w
+            .src
+            .get(self.w.skip_whitespace_from(attributes_start))
+            == Some(&b'=')
+        {
+            // The directive name is optional, this is the first attribute.
+            name = String::new();
+            attributes_start = name_start;
+        }
+        let (attributes, _) = self.attributes(attributes_start, end);
+        let attribute = |n: &str| attributes.iter().find(|a| a.name.eq_ignore_ascii_case(n));
+
+        if MAIN_DIRECTIVES
+            .iter()
+            .any(|d| d.eq_ignore_ascii_case(&name))
+        {
+            self.vb = attribute("Language").is_some_and(|a| a.value.eq_ignore_ascii_case("vb"));
+        }
+        let namespace = attribute("Namespace");
+        let reference =
+            if name.eq_ignore_ascii_case("Import") || name.eq_ignore_ascii_case("Register") {
+                if let (Some(prefix), Some(namespace)) = (attribute("TagPrefix"), namespace) {
+                    self.namespaces
+                        .insert(prefix.value.to_lowercase(), namespace.value.clone());
+                } else if let (Some(prefix), Some(tag_name), Some(src)) = (
+                    attribute("TagPrefix"),
+                    attribute("TagName"),
+                    attribute("Src"),
+                ) {
+                    self.user_controls.insert(
+                        (prefix.value.to_lowercase(), tag_name.value.to_lowercase()),
+                        src.value.clone(),
+                    );
+                }
+                namespace.filter(|n| is_type_name(&n.value)).inspect(|n| {
+                    let segment = self.w.start_segment();
+                    for b in "using".bytes() {
+                        self.w.replace(b);
+                    }
+                    self.w.blank_n(n.value_range.start - self.w.pos);
+                    self.w.copy_n(n.value_range.len());
+                    self.w.suffix(";");
+                    self.w.end_segment(segment);
+                })
+            } else {
+                TYPE_ATTRIBUTES
+                    .iter()
+                    .find_map(|n| attribute(n))
+                    .filter(|t| is_type_name(&t.value))
+                    .inspect(|t| {
+                        self.w.blank_n(t.value_range.start - self.w.pos);
+                        let segment = self.w.start_segment();
+                        for b in unqualified_type(&t.value).bytes() {
+                            if b == b' ' {
+                                self.w.blank();
+                            } else {
+                                self.w.copy();
+                            }
+                        }
+                        let local = TYPE_ATTRIBUTES
+                            .iter()
+                            .find(|n| n.eq_ignore_ascii_case(&t.name))
+                            .unwrap_or(&"Type");
+                        self.w.suffix(&format!(" {};", local));
+                        for namespace in type_namespaces(&t.value) {
+                            self.w.suffix(&format!(" using {};", namespace));
+                        }
+                        self.w.end_segment(segment);
+                        self.type_references.push(t.value_range.clone());
+                    })
+            }
+            .map(|a| a.value.clone());
+
+        let directive_end = (end + 2).min(self.w.src.len());
+        self.elements.push(MarkupElement {
+            markup_type: MarkupType::Directive,
+            name,
+            attributes: attributes.into_iter().map(|a| (a.name, a.value)).collect(),
+            reference,
+            user_control: None,
+            span: start..directive_end,
+        });
+        self.w.blank_n(directive_end - self.w.pos);
+    }
+
+    /// Handle a tag, the position is at the `<`. Server controls become a local declaration of
+    /// the control, `<script runat="server">` blocks become a class.
+    fn tag(&mut self) {
+        let start = self.w.pos;
+        let name_len = self.w.src[start + 1..]
+            .iter()
+            .position(|b| b.is_ascii_whitespace() || matches!(b, b'/' | b'>'))
+            .unwrap_or(self.w.src.len() - start - 1);
+        let name =
+            String::from_utf8_lossy(&self.w.src[start + 1..start + 1 + name_len]).to_string();
+        let (attributes, tag_end) = self.attributes(start + 1 + name_len, self.w.src.len());
+        let runat_server = attributes.iter().any(|a| {
+            a.name.eq_ignore_ascii_case("runat") && a.value.eq_ignore_ascii_case("server")
+        });
+        if !runat_server {
+            self.w.blank();
+            return;
+        }
+
+        if name.eq_ignore_ascii_case("script") {
+            self.server_script(tag_end);
+            return;
+        }
+        let Some((prefix, tag_name)) = name.split_once(':') else {
+            self.w.blank();
+            return;
+        };
+        let id = attributes
+            .iter()
+            .find(|a| a.name.eq_ignore_ascii_case("ID"))
+            .map(|a| a.value.clone());
+        self.elements.push(MarkupElement {
+            markup_type: MarkupType::Control,
+            name: name.clone(),
+            attributes: attributes.into_iter().map(|a| (a.name, a.value)).collect(),
+            reference: None,
+            user_control: None,
+            span: start..tag_end,
+        });
+        if !self.namespaces.contains_key(&prefix.to_lowercase()) || !is_type_name(tag_name) {
+            self.w.blank();
+            return;
+        }
+        // `<asp:GridView ID="dinners" runat="server">` becomes `GridView dinners;`, the rest of
+        // the tag is handled as markup since attributes can hold data binding expressions.
+        self.w.blank_n(prefix.len() + 2);
+        self.type_references
+            .push(self.w.pos..self.w.pos + tag_name.len());
+        let segment = self.w.start_segment();
+        self.w.copy_n(tag_name.len());
+        let local = id
+            .filter(|id| is_type_name(id) && !id.contains('.'))
+            .unwrap_or("_".to_string());
+        self.w.suffix(&format!(" {};", local));
+        self.w.end_segment(segment);
+    }
+
+    /// The members in `<script runat="server">` are copied into a class.
+    fn server_script(&mut self, tag_end: usize) {
+        let close = self.find_ignore_case(b"</script", tag_end);
+        let close_end = close
+            .and_then(|c| self.find(b">", c))
+            .map_or(self.w.src.len(), |i| i + 1);
+        if self.vb || close.is_none() {
+            self.w.blank_n(close_end - self.w.pos);
+            return;
+        }
+        let close = close.unwrap_or(self.w.src.len());
+        let segment = self.w.start_segment();
+        self.w.suffix("class Script {");
+        self.w.blank_n(tag_end - self.w.pos);
+        self.w.copy_n(close - self.w.pos);
+        self.w.suffix("}");
+        self.w.blank_n(close_end - self.w.pos);
+        self.w.end_segment(segment);
+    }
+
+    fn find_ignore_case(&self, needle: &[u8], from: usize) -> Option<usize> {
+        self.w.src[from.min(self.w.src.len())..]
+            .windows(needle.len())
+            .position(|w| w.eq_ignore_ascii_case(needle))
+            .map(|i| from + i)
+    }
+
+    /// Parse the attributes from `pos` up to the end of the tag or `end`, code nuggets in the
+    /// values are skipped. Returns the attributes and the position after the tag.
+    fn attributes(&self, mut pos: usize, end: usize) -> (Vec<Attribute>, usize) {
+        let src = self.w.src;
+        let mut attributes = vec![];
+        loop {
+            pos = self.w.skip_whitespace_from(pos);
+            if pos >= end {
+                return (attributes, end);
+            }
+            match src[pos] {
+                b'>' => return (attributes, pos + 1),
+                b'/' if src.get(pos + 1) == Some(&b'>') => return (attributes, pos + 2),
+                b'<' if src.get(pos + 1) == Some(&b'%') => {
+                    pos = self.find(b"%>", pos + 2).map_or(end, |i| i + 2);
+                    continue;
+                }
+                _ => {}
+            }
+            let name_end = src[pos..end]
+                .iter()
+                .position(|b| b.is_ascii_whitespace() || matches!(b, b'=' | b'>' | b'/'))
+                .map_or(end, |i| pos + i);
+            if name_end == pos {
+                pos += 1;
+                continue;
+            }
+            let name = String::from_utf8_lossy(&src[pos..name_end]).to_string();
+            pos = self.w.skip_whitespace_from(name_end);
+            if pos >= end || src[pos] != b'=' {
+                attributes.push(Attribute {
+                    name,
+                    value: String::new(),
+                    value_range: name_end..name_end,
+                });
+                continue;
+            }
+            pos = self.w.skip_whitespace_from(pos + 1);
+            let quote = src.get(pos).copied().filter(|q| matches!(q, b'"' | b'\''));
+            let value_start = if quote.is_some() { pos + 1 } else { pos };
+            let mut value_end = value_start;
+            while value_end < end {
+                match (quote, src[value_end]) {
+                    (_, b'<') if src.get(value_end + 1) == Some(&b'%') => {
+                        value_end = self.find(b"%>", value_end + 2).map_or(end, |i| i + 2);
+                        continue;
+                    }
+                    (Some(q), b) if b == q => break,
+                    (None, b) if b.is_ascii_whitespace() || b == b'>' => break,
+                    _ => value_end += 1,
+                }
+            }
+            let value_end = value_end.min(end);
+            attributes.push(Attribute {
+                name,
+                value: String::from_utf8_lossy(&src[value_start..value_end]).to_string(),
+                value_range: value_start..value_end,
+            });
+            pos = if quote.is_some() {
+                value_end + 1
+            } else {
+                value_end
+            };
+        }
+    }
+}
+
+/// Whether a directive value can be written as a C# type or namespace, VB and CLR generic
+/// syntax can not.
+fn is_type_name(value: &str) -> bool {
+    value
+        .bytes()
+        .next()
+        .is_some_and(|b| b.is_ascii_alphabetic() || b == b'_')
+        && value
+            .bytes()
+            .all(|b| is_identifier_byte(b) || matches!(b, b'.' | b'<' | b'>' | b',' | b' '))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The C# text at the position of `needle` in the page.
+    fn code_at<'a>(page: &str, document: &'a ViewDocument, needle: &str) -> &'a str {
+        let start = page.find(needle).expect("needle in page");
+        &document.code[start..start + needle.len()]
+    }
+
+    #[test]
+    fn test_directives() {
+        let source = r#"<%@ Page Language="C#" Inherits="System.Web.Mvc.ViewPage<NerdDinner.Models.Dinner>" %>
+<%@ Import Namespace="NerdDinner.Helpers" %>
+<%@ Register TagPrefix="nd" Namespace="NerdDinner.Controls" Assembly="NerdDinner" %>
+<%@ Register TagPrefix="uc" TagName="Map" Src="~/Controls/Map.ascx" %>"#;
+        let page = WebFormsPage::parse(source);
+        let lines: Vec<&str> = page.document.code.lines().map(|l| l.trim_end()).collect();
+        assert_eq!(
+            lines[0],
+            "                                                ViewPage<                  Dinner> Inherits; using System.Web.Mvc; using NerdDinner.Models;"
+        );
+        assert_eq!(lines[1], "using                 NerdDinner.Helpers;");
+        assert!(lines[2].starts_with("using ") && lines[2].ends_with(" NerdDinner.Controls;"));
+        assert_eq!(
+            lines[2].find("NerdDinner.Controls"),
+            source.lines().nth(2).unwrap().find("NerdDinner.Controls")
+        );
+        assert_eq!(lines[3].trim(), "using System.Web.UI.WebControls;");
+        assert_eq!(
+            &source[page.type_references[0].clone()],
+            "System.Web.Mvc.ViewPage<NerdDinner.Models.Dinner>"
+        );
+
+        let names: Vec<&str> = page.elements.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["Page", "Import", "Register", "Register"]);
+        assert_eq!(
+            page.elements[0].reference.as_deref(),
+            Some("System.Web.Mvc.ViewPage<NerdDinner.Models.Dinner>")
+        );
+        assert_eq!(page.elements[0].attributes["Language"], "C#");
+        assert_eq!(
+            page.elements[1].reference.as_deref(),
+            Some("NerdDinner.Helpers")
+        );
+        assert!(page.elements[3].reference.is_none());
+        assert_eq!(page.elements[0].span, 0..source.find('\n').unwrap());
+    }
+
+    #[test]
+    fn test_nuggets_keep_their_position() {
+        let source = r#"<%-- <%= Ignored %> --%>
+<% foreach (var dinner in Model) { %>
+    <li><%: Html.ActionLink(dinner.Title, "Details") %></li>
+<% } %>
+<p><%$ Resources:Strings, Title %></p>"#;
+        let page = WebFormsPage::parse(source);
+        let lines: Vec<&str> = page.document.code.lines().map(|l| l.trim_end()).collect();
+        assert_eq!(lines[0], "");
+        assert_eq!(lines[1], "   foreach (var dinner in Model) {");
+        assert_eq!(
+            code_at(
+                source,
+                &page.document,
+                r#"<%: Html.ActionLink(dinner.Title, "Details") %>"#
+            ),
+            r#" !( Html.ActionLink(dinner.Title, "Details") );"#
+        );
+        assert_eq!(lines[3], "   }");
+        assert_eq!(lines[4].trim(), "using System.Web.UI.WebControls;");
+        assert_eq!(page.document.segments.len(), 4);
+    }
+
+    #[test]
+    fn test_server_controls() {
+        let source = r#"<%@ Register TagPrefix="nd" Namespace="NerdDinner.Controls" %>
+<%@ Register TagPrefix="uc" TagName="Map" Src="~/Controls/Map.ascx" %>
+<asp:GridView ID="dinners" runat="server" DataSourceID='<%# Eval("Source") %>'>
+</asp:GridView>
+<nd:Pager runat="server" />
+<uc:Map ID="map" runat="server" />
+<asp:Label Text="not a server control" />"#;
+        let page = WebFormsPage::parse(source);
+        let lines: Vec<&str> = page.document.code.lines().map(|l| l.trim_end()).collect();
+        assert!(lines[2].starts_with("     GridView dinners;"));
+        assert_eq!(
+            code_at(source, &page.document, r#"<%# Eval("Source") %>"#),
+            r#" !( Eval("Source") );"#
+        );
+        assert_eq!(lines[4], "    Pager _;");
+        assert_eq!(lines[5], "");
+        assert_eq!(lines[6].trim(), "using System.Web.UI.WebControls;");
+        assert_eq!(page.type_references.len(), 2);
+        assert_eq!(&source[page.type_references[0].clone()], "GridView");
+
+        let controls: Vec<&MarkupElement> = page
+            .elements
+            .iter()
+            .filter(|e| e.markup_type == MarkupType::Control)
+            .collect();
+        assert_eq!(controls.len(), 3);
+        assert_eq!(
+            controls[0].reference.as_deref(),
+            Some("System.Web.UI.WebControls.GridView")
+        );
+        assert_eq!(controls[0].attributes["ID"], "dinners");
+        assert_eq!(
+            controls[1].reference.as_deref(),
+            Some("NerdDinner.Controls.Pager")
+        );
+        assert!(controls[2].reference.is_none());
+        assert_eq!(
+            controls[2].user_control.as_deref(),
+            Some("~/Controls/Map.ascx")
+        );
+    }
+
+    #[test]
+    fn test_server_script_and_vb() {
+        let source = "\u{feff}<%@ Application Inherits=\"NerdDinner.MvcApplication\" Language=\"C#\" %>\n<script runat=\"server\">\nvoid Session_Start() { }\n</script>\n";
+        let page = WebFormsPage::parse(source);
+        let lines: Vec<&str> = page.document.code.lines().map(|l| l.trim_end()).collect();
+        assert_eq!(
+            lines[0],
+            "                                     MvcApplication Inherits; using NerdDinner;"
+        );
+        assert_eq!(lines[1], "class Script {");
+        assert_eq!(lines[2], "void Session_Start() { }");
+        assert_eq!(lines[3], "}");
+
+        let source = "<%@ Page Language=\"VB\" Inherits=\"NerdDinner.Index\" %>\n<% If True Then %>\n<%= Model.Title %>\n";
+        let page = WebFormsPage::parse(source);
+        let lines: Vec<&str> = page.document.code.lines().map(|l| l.trim_end()).collect();
+        assert!(lines[0].ends_with(" Index Inherits; using NerdDinner;"));
+        assert_eq!(lines[1], "");
+        assert_eq!(lines[2], "");
+    }
+
+    #[test]
+    fn test_page_graph_query() {
+        use stack_graphs::graph::StackGraph;
+        use tree_sitter_stack_graphs::NoCancellation;
+
+        use crate::c_sharp_graph::language_config::SourceNodeLanguageConfiguration;
+        use crate::c_sharp_graph::loader::load_graph_for_file;
+        use crate::c_sharp_graph::query::{Query, QueryOptions, QueryType};
+        use crate::c_sharp_graph::symbol_index::SymbolIndex;
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("Library.cs"),
+            r#"namespace System.Web.UI.WebControls
+{
+    public class GridView { }
+}
+namespace NerdDinner.Controls
+{
+    public class Pager { }
+}
+namespace NerdDinner.Views
+{
+    public class DinnerPage { }
+}
+"#,
+        )
+        .unwrap();
+        let page = dir.path().join("Dinners.aspx");
+        std::fs::write(
+            &page,
+            r#"<%@ Page Language="C#" Inherits="NerdDinner.Views.DinnerPage" %>
+<%@ Register TagPrefix="nd" Namespace="NerdDinner.Controls" %>
+<asp:GridView ID="dinners" runat="server" />
+<nd:Pager runat="server" />
+"#,
+        )
+        .unwrap();
+        let lc = SourceNodeLanguageConfiguration::new(&NoCancellation).unwrap();
+        let mut graph = StackGraph::new();
+        let _ = graph.add_from_graph(&lc.language_config.builtins);
+        for file in [dir.path().join("Library.cs"), page] {
+            load_graph_for_file(
+                file,
+                &mut graph,
+                &lc.language_config,
+                &lc.source_type_node_info,
+                &NoCancellation,
+            )
+            .unwrap();
+        }
+        let index = SymbolIndex::new(&graph);
+        let query = |pattern: &str| -> Vec<(usize, usize, String, String)> {
+            QueryType::Class {
+                graph: &graph,
+                source_type: &lc.source_type_node_info,
+                options: QueryOptions::indexed(&index),
+            }
+            .query(pattern.to_string())
+            .unwrap()
+            .into_iter()
+            .filter(|r| r.file_uri.ends_with("Dinners.aspx"))
+            .map(|r| {
+                (
+                    r.line_number,
+                    r.code_location.start_position.character,
+                    r.variables["symbol"].as_str().unwrap().to_string(),
+                    r.variables["syntax_type"].as_str().unwrap().to_string(),
+                )
+            })
+            .collect()
+        };
+        let reference = |line: usize, character: usize, symbol: &str| {
+            vec![(
+                line,
+                character,
+                symbol.to_string(),
+                "type_reference".to_string(),
+            )]
+        };
+        assert_eq!(
+            query("NerdDinner.Views.DinnerPage"),
+            reference(0, 33, "DinnerPage")
+        );
+        assert_eq!(
+            query("System.Web.UI.WebControls.GridView"),
+            reference(2, 5, "GridView")
+        );
+        assert_eq!(query("NerdDinner.Controls.Pager"), reference(3, 4, "Pager"));
+    }
+}
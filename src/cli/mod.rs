@@ -0,0 +1,313 @@
+mod rules;
+mod sarif;
+
+use std::path::{absolute, PathBuf};
+
+use anyhow::{anyhow, Error};
+use clap::{Args, Subcommand, ValueEnum};
+use prost_types::value::Kind;
+use serde_json::json;
+use tonic::Request;
+
+use crate::analyzer_service::{provider_service_server::ProviderService, Config, EvaluateRequest};
+use crate::c_sharp_graph::results::{serde_json_to_prost, ResultNode};
+use crate::cli::sarif::{sarif, SarifResult, SarifRule};
+use crate::provider::cache::{self, CacheEntry};
+use crate::provider::{AnalysisMode, CSharpProvider};
+
+#[derive(Args, Debug)]
+pub struct ProjectArgs {
+    /// The directory of the project.
+    #[arg(value_name = "LOCATION")]
+    path: PathBuf,
+    /// The provider specific config as a JSON object, the same as the one sent to Init.
+    #[arg(long)]
+    provider_config: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct IndexArgs {
+    #[command(flatten)]
+    project: ProjectArgs,
+}
+
+#[derive(Args, Debug)]
+pub struct QueryArgs {
+    #[command(flatten)]
+    project: ProjectArgs,
+    /// The FQDN pattern of a `referenced` condition.
+    #[arg(long)]
+    pattern: String,
+    #[arg(long, value_enum, ignore_case = true, default_value_t = QueryLocation::All)]
+    location: QueryLocation,
+    #[arg(long, value_enum, default_value_t = QueryKind::Any)]
+    kind: QueryKind,
+    #[arg(long, value_enum, default_value_t = OutputFormat::Yaml)]
+    output: OutputFormat,
+}
+
+#[derive(Args, Debug)]
+pub struct AnalyzeArgs {
+    #[command(flatten)]
+    project: ProjectArgs,
+    /// A rule file or a directory of rulesets.
+    #[arg(long)]
+    rules: PathBuf,
+    /// Where to write the output.
+    #[arg(long, default_value = "output.yaml")]
+    output_file: PathBuf,
+    /// The format of the output, `yaml` and `json` are the format of the analyzer.
+    #[arg(long, value_enum, default_value_t = OutputFormat::Yaml)]
+    output: OutputFormat,
+}
+
+#[derive(Args, Debug)]
+pub struct CacheArgs {
+    #[command(subcommand)]
+    command: CacheCommand,
+}
+
+#[derive(Subcommand, Debug)]
+enum CacheCommand {
+    /// List the graph databases in the cache directory.
+    List,
+    /// Print the graph database of a project and what it was built from.
+    Inspect {
+        /// The directory of the project.
+        #[arg(value_name = "LOCATION")]
+        path: PathBuf,
+    },
+    /// Remove the graph database of a project, or every database in the cache directory.
+    Purge {
+        /// The directory of the project.
+        #[arg(value_name = "LOCATION", required_unless_present = "all")]
+        path: Option<PathBuf>,
+        #[arg(long, conflicts_with = "path")]
+        all: bool,
+    },
+}
+
+#[derive(Clone, Debug, ValueEnum)]
+#[value(rename_all = "UPPER")]
+enum QueryLocation {
+    All,
+    Method,
+    Field,
+    Class,
+    Property,
+    Event,
+}
+
+#[derive(Clone, Debug, ValueEnum)]
+enum QueryKind {
+    Declaration,
+    Reference,
+    Any,
+}
+
+#[derive(Clone, Debug, ValueEnum)]
+enum OutputFormat {
+    Json,
+    Yaml,
+    /// SARIF 2.1.0, for code scanning dashboards.
+    Sarif,
+}
+
+/// The name of a value as it is written on the command line and in a condition.
+fn value_name<T: ValueEnum>(value: &T) -> String {
+    value
+        .to_possible_value()
+        .map(|v| v.get_name().to_string())
+        .unwrap_or_default()
+}
+
+/// Run Init for the project the same way the analyzer does, the graph is loaded from the
+/// database when it has been indexed before.
+async fn init(provider: &CSharpProvider, project: &ProjectArgs) -> Result<(), Error> {
+    let provider_specific_config = match &project.provider_config {
+        Some(config) => match serde_json_to_prost(serde_json::from_str(config)?).kind {
+            Some(Kind::StructValue(config)) => Some(config),
+            _ => return Err(anyhow!("--provider-config must be a JSON object")),
+        },
+        None => None,
+    };
+    let config = Config {
+        location: absolute(&project.path)?.to_string_lossy().to_string(),
+        analysis_mode: "source-only".to_string(),
+        provider_specific_config,
+        ..Default::default()
+    };
+    let response = provider
+        .init(Request::new(config))
+        .await
+        .map_err(|status| anyhow!("init failed: {}", status.message()))?;
+    if !response.get_ref().successful {
+        return Err(anyhow!("init failed: {}", response.get_ref().error));
+    }
+    Ok(())
+}
+
+pub async fn index(provider: &CSharpProvider, args: &IndexArgs) -> Result<(), Error> {
+    init(provider, &args.project).await?;
+    let db_path = match provider.project.lock().await.as_ref() {
+        Some(project) => project.db_path.clone(),
+        None => return Err(anyhow!("init did not create a project")),
+    };
+    println!(
+        "indexed {} into {}",
+        args.project.path.display(),
+        db_path.display()
+    );
+    Ok(())
+}
+
+/// List, inspect and purge the graph databases. `--db-path` replaces the database of a project
+/// the same way it does for the other commands.
+pub fn cache(provider: &CSharpProvider, args: &CacheArgs) -> Result<(), Error> {
+    // The commands always analyze in source-only mode, see init.
+    let db_path = |path: &PathBuf| {
+        provider
+            .db_path
+            .clone()
+            .unwrap_or_else(|| cache::default_db_path(path, &AnalysisMode::SourceOnly))
+    };
+    match &args.command {
+        CacheCommand::List => {
+            let dir = cache::cache_dir();
+            let entries = cache::list_caches(&dir)?;
+            if entries.is_empty() {
+                println!("no graph databases in {}", dir.display());
+            }
+            for entry in entries {
+                let location = entry
+                    .manifest
+                    .as_ref()
+                    .map_or("unknown project".to_string(), |m| {
+                        m.location.display().to_string()
+                    });
+                println!("{}\t{}\t{}", entry.db_path.display(), entry.size, location);
+            }
+        }
+        CacheCommand::Inspect { path } => {
+            let db_path = db_path(path);
+            if !db_path.exists() {
+                return Err(anyhow!("{} has not been indexed", path.display()));
+            }
+            print_cache_entry(&CacheEntry::new(db_path));
+        }
+        CacheCommand::Purge { path: _, all: true } => {
+            for entry in cache::list_caches(&cache::cache_dir())? {
+                cache::purge(&entry.db_path)?;
+                println!("removed {}", entry.db_path.display());
+            }
+        }
+        CacheCommand::Purge { path, all: false } => {
+            let Some(path) = path else {
+                return Err(anyhow!("a project or --all is required"));
+            };
+            let db_path = db_path(path);
+            if cache::purge(&db_path)? {
+                println!("removed {}", db_path.display());
+            } else {
+                println!("{} has not been indexed", path.display());
+            }
+        }
+    }
+    Ok(())
+}
+
+fn print_cache_entry(entry: &CacheEntry) {
+    println!("database: {}", entry.db_path.display());
+    println!("size: {}", entry.size);
+    match &entry.manifest {
+        Some(manifest) => {
+            println!("location: {}", manifest.location.display());
+            println!("analysis mode: {}", manifest.analysis_mode);
+            println!("provider version: {}", manifest.provider_version);
+        }
+        None => println!("manifest: missing, init only rebuilds it in the cache directory"),
+    }
+}
+
+/// Evaluate a `referenced` condition and print the incidents in the format of the demo outputs.
+pub async fn query(provider: &CSharpProvider, args: &QueryArgs) -> Result<(), Error> {
+    init(provider, &args.project).await?;
+    let condition = json!({
+        "referenced": {
+            "pattern": args.pattern,
+            "location": value_name(&args.location),
+            "kind": value_name(&args.kind),
+        }
+    });
+    let response = provider
+        .evaluate(Request::new(EvaluateRequest {
+            id: 0,
+            cap: "referenced".to_string(),
+            condition_info: condition.to_string(),
+        }))
+        .await
+        .map_err(|status| anyhow!("evaluate failed: {}", status.message()))?
+        .into_inner();
+    if !response.successful {
+        return Err(anyhow!("evaluate failed: {}", response.error));
+    }
+    let results: Vec<ResultNode> = response
+        .response
+        .map(|r| r.incident_contexts.iter().map(Into::into).collect())
+        .unwrap_or_default();
+    match args.output {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&results)?),
+        OutputFormat::Yaml => print!("{}", serde_yml::to_string(&results)?),
+        OutputFormat::Sarif => {
+            let rule_id = "csharp.referenced";
+            let rule = SarifRule {
+                id: rule_id.to_string(),
+                description: format!("References to {}", args.pattern),
+                help_uri: None,
+                tags: vec![],
+                category: None,
+                effort: None,
+            };
+            let messages: Vec<String> = results
+                .iter()
+                .map(|r| format!("{} is referenced", r.get_fqdn_symbol()))
+                .collect();
+            let sarif_results: Vec<SarifResult> = results
+                .iter()
+                .zip(&messages)
+                .map(|(result, message)| SarifResult {
+                    rule_id,
+                    message,
+                    file_uri: &result.file_uri,
+                    code_location: &result.code_location,
+                    variables: &result.variables,
+                })
+                .collect();
+            let log = sarif(&[rule], &sarif_results, &absolute(&args.project.path)?);
+            println!("{}", serde_json::to_string_pretty(&log)?);
+        }
+    }
+    Ok(())
+}
+
+/// Evaluate the rules and write their violations the way the analyzer does.
+pub async fn analyze(provider: &CSharpProvider, args: &AnalyzeArgs) -> Result<(), Error> {
+    init(provider, &args.project).await?;
+    let output = rules::analyze(provider, &args.rules).await?;
+    let content = match args.output {
+        OutputFormat::Json => serde_json::to_string_pretty(&output)?,
+        OutputFormat::Yaml => serde_yml::to_string(&output)?,
+        OutputFormat::Sarif => {
+            let (rules, results) = rules::sarif_rules(&output);
+            let log = sarif(&rules, &results, &absolute(&args.project.path)?);
+            serde_json::to_string_pretty(&log)?
+        }
+    };
+    std::fs::write(&args.output_file, content)?;
+    println!(
+        "wrote the results of {} rulesets to {}",
+        output.len(),
+        args.output_file.display()
+    );
+    Ok(())
+}
@@ -0,0 +1,736 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Error};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tonic::Request;
+use tracing::{debug, info, warn};
+use url::Url;
+use walkdir::WalkDir;
+
+use crate::analyzer_service::{
+    provider_code_location_service_server::ProviderCodeLocationService,
+    provider_service_server::ProviderService, ConditionsByCapability, EvaluateRequest,
+    GetCodeSnipRequest, PrepareRequest,
+};
+use crate::c_sharp_graph::results::{Location, ResultNode};
+use crate::cli::sarif::{SarifResult, SarifRule};
+use crate::provider::CSharpProvider;
+
+const PROVIDER: &str = "csharp";
+const RULESET_FILE: &str = "ruleset.yaml";
+
+#[derive(Deserialize, Debug, Default)]
+struct RuleSetMetadata {
+    name: String,
+    description: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Link {
+    url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    title: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Rule {
+    #[serde(rename = "ruleID")]
+    rule_id: String,
+    #[serde(default)]
+    description: String,
+    category: Option<String>,
+    #[serde(default)]
+    labels: Vec<String>,
+    effort: Option<i64>,
+    #[serde(default)]
+    links: Vec<Link>,
+    #[serde(default)]
+    message: String,
+    when: Value,
+}
+
+/// A ruleset of one directory, the name and description come from its `ruleset.yaml`.
+#[derive(Debug)]
+struct RuleSet {
+    metadata: RuleSetMetadata,
+    rules: Vec<Rule>,
+}
+
+#[derive(Debug, PartialEq)]
+enum ConditionKind {
+    And(Vec<Condition>),
+    Or(Vec<Condition>),
+    /// A `<provider>.<capability>` condition, only `csharp` ones can be evaluated here.
+    Capability {
+        provider: String,
+        capability: String,
+        info: Value,
+    },
+}
+
+#[derive(Debug, PartialEq)]
+struct Condition {
+    kind: ConditionKind,
+    not: bool,
+    /// The incidents are only used to match the rule and are not reported.
+    ignore: bool,
+    /// Save the result to be used by a condition with the same `from`.
+    r#as: Option<String>,
+    from: Option<String>,
+}
+
+/// The result of an `as` condition that a `from` condition can use in its templates.
+#[derive(Debug, Default, Clone)]
+struct ChainTemplate {
+    matched: bool,
+    filepaths: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+struct Outcome {
+    matched: bool,
+    incidents: Vec<ResultNode>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Incident {
+    uri: String,
+    message: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    code_snip: String,
+    line_number: usize,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    variables: BTreeMap<String, Value>,
+    #[serde(skip)]
+    code_location: Location,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Violation {
+    description: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    category: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    labels: Vec<String>,
+    incidents: Vec<Incident>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    links: Vec<Link>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    effort: Option<i64>,
+}
+
+/// A ruleset in the output format of the analyzer, rules without an effort are insights.
+#[derive(Serialize, Debug)]
+pub struct RuleSetOutput {
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    violations: BTreeMap<String, Violation>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    insights: BTreeMap<String, Violation>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    errors: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    unmatched: Vec<String>,
+    /// Rules with conditions of other providers.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    skipped: Vec<String>,
+}
+
+impl Condition {
+    fn parse(value: &Value) -> Result<Condition, Error> {
+        let Value::Object(fields) = value else {
+            return Err(anyhow!("condition must be a map: {}", value));
+        };
+        let mut kind = None;
+        for (key, value) in fields {
+            let parsed = match key.as_str() {
+                "not" | "ignore" | "as" | "from" => continue,
+                "and" | "or" => {
+                    let Value::Array(conditions) = value else {
+                        return Err(anyhow!("{} must be a list of conditions", key));
+                    };
+                    let conditions = conditions
+                        .iter()
+                        .map(Condition::parse)
+                        .collect::<Result<Vec<_>, _>>()?;
+                    if key == "and" {
+                        ConditionKind::And(conditions)
+                    } else {
+                        ConditionKind::Or(conditions)
+                    }
+                }
+                _ => match key.split_once('.') {
+                    Some((provider, capability)) => ConditionKind::Capability {
+                        provider: provider.to_string(),
+                        capability: capability.to_string(),
+                        info: value.clone(),
+                    },
+                    None => return Err(anyhow!("unknown condition: {}", key)),
+                },
+            };
+            if kind.replace(parsed).is_some() {
+                return Err(anyhow!(
+                    "a condition can only have one of and, or or a capability"
+                ));
+            }
+        }
+        let name = |key: &str| fields.get(key).and_then(|v| v.as_str()).map(String::from);
+        Ok(Condition {
+            kind: kind.ok_or_else(|| anyhow!("condition has no capability: {}", value))?,
+            not: fields.get("not").and_then(|v| v.as_bool()).unwrap_or(false),
+            ignore: fields
+                .get("ignore")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false),
+            r#as: name("as"),
+            from: name("from"),
+        })
+    }
+
+    fn providers(&self, providers: &mut BTreeSet<String>) {
+        match &self.kind {
+            ConditionKind::And(conditions) | ConditionKind::Or(conditions) => {
+                conditions.iter().for_each(|c| c.providers(providers))
+            }
+            ConditionKind::Capability { provider, .. } => {
+                providers.insert(provider.clone());
+            }
+        }
+    }
+
+    /// The condition info of the provider's capabilities, in the form evaluate gets them.
+    fn capabilities(&self, capabilities: &mut BTreeMap<String, BTreeSet<String>>) {
+        match &self.kind {
+            ConditionKind::And(conditions) | ConditionKind::Or(conditions) => {
+                conditions.iter().for_each(|c| c.capabilities(capabilities))
+            }
+            ConditionKind::Capability {
+                provider,
+                capability,
+                info,
+            } if provider == PROVIDER => {
+                capabilities
+                    .entry(capability.clone())
+                    .or_default()
+                    .insert(json!({ capability: info }).to_string());
+            }
+            ConditionKind::Capability { .. } => {}
+        }
+    }
+}
+
+/// Load the rulesets from a rule file or a directory, every directory with rule files is a
+/// ruleset.
+fn load_rulesets(path: &Path) -> Result<Vec<RuleSet>, Error> {
+    if path.is_file() {
+        let name = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        return Ok(vec![RuleSet {
+            metadata: RuleSetMetadata {
+                name,
+                description: None,
+            },
+            rules: load_rules(path)?,
+        }]);
+    }
+    let mut rulesets = vec![];
+    for entry in WalkDir::new(path).sort_by_file_name() {
+        let entry = entry?;
+        if !entry.file_type().is_dir() {
+            continue;
+        }
+        let dir = entry.path();
+        let metadata = match std::fs::read_to_string(dir.join(RULESET_FILE)) {
+            Ok(content) => serde_yml::from_str(&content)?,
+            Err(_) => RuleSetMetadata {
+                name: dir
+                    .file_name()
+                    .map(|s| s.to_string_lossy().to_string())
+                    .unwrap_or_default(),
+                description: None,
+            },
+        };
+        let mut files: Vec<PathBuf> = std::fs::read_dir(dir)?
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| {
+                p.is_file()
+                    && matches!(p.extension(), Some(e) if e == "yaml" || e == "yml")
+                    && p.file_name().is_some_and(|n| n != RULESET_FILE)
+            })
+            .collect();
+        files.sort();
+        let mut rules = vec![];
+        for file in files {
+            rules.extend(load_rules(&file)?);
+        }
+        if !rules.is_empty() {
+            rulesets.push(RuleSet { metadata, rules });
+        }
+    }
+    Ok(rulesets)
+}
+
+fn load_rules(path: &Path) -> Result<Vec<Rule>, Error> {
+    let content = std::fs::read_to_string(path)?;
+    serde_yml::from_str(&content).map_err(|e| anyhow!("unable to load rules {:?}: {}", path, e))
+}
+
+/// Replace the `{{ name.filepaths }}` of a chained condition, a string that is only the
+/// template becomes the list of files.
+fn template(info: &Value, chain: &HashMap<String, ChainTemplate>) -> Value {
+    let regex = Regex::new(r"\{\{\s*([\w-]+)\.filepaths\s*\}\}").expect("valid template regex");
+    match info {
+        Value::String(s) => {
+            if let Some(captures) = regex.captures(s) {
+                if captures[0].len() == s.trim().len() {
+                    let filepaths = chain
+                        .get(&captures[1])
+                        .map(|c| c.filepaths.clone())
+                        .unwrap_or_default();
+                    return json!(filepaths);
+                }
+            }
+            Value::from(regex.replace_all(s, |captures: &regex::Captures| {
+                chain
+                    .get(&captures[1])
+                    .map(|c| c.filepaths.join(","))
+                    .unwrap_or_default()
+            }))
+        }
+        Value::Array(values) => Value::Array(values.iter().map(|v| template(v, chain)).collect()),
+        Value::Object(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|(k, v)| (k.clone(), template(v, chain)))
+                .collect(),
+        ),
+        value => value.clone(),
+    }
+}
+
+/// Render the `{{ variable }}` of a message with the variables of the incident.
+fn render_message(message: &str, variables: &BTreeMap<String, Value>) -> String {
+    let regex = Regex::new(r"\{\{\{?\s*([\w.-]+)\s*\}?\}\}").expect("valid message regex");
+    regex
+        .replace_all(message, |captures: &regex::Captures| {
+            match variables.get(&captures[1]) {
+                Some(Value::String(s)) => s.clone(),
+                Some(value) => value.to_string(),
+                None => String::new(),
+            }
+        })
+        .to_string()
+}
+
+fn file_path(file_uri: &str) -> Option<String> {
+    Url::parse(file_uri)
+        .ok()?
+        .to_file_path()
+        .ok()
+        .map(|p| p.to_string_lossy().to_string())
+}
+
+struct Engine<'a> {
+    provider: &'a CSharpProvider,
+}
+
+impl Engine<'_> {
+    /// Send every condition of the rulesets to prepare, so the searches they share are only
+    /// computed once.
+    async fn prepare(&self, rulesets: &[RuleSet]) {
+        let mut capabilities = BTreeMap::new();
+        for rule in rulesets.iter().flat_map(|r| &r.rules) {
+            if let Ok(condition) = Condition::parse(&rule.when) {
+                condition.capabilities(&mut capabilities);
+            }
+        }
+        let conditions = capabilities
+            .into_iter()
+            .map(|(cap, infos)| ConditionsByCapability {
+                cap,
+                condition_info: infos.into_iter().collect(),
+            })
+            .collect();
+        let response = self
+            .provider
+            .prepare(Request::new(PrepareRequest { conditions, id: 0 }))
+            .await;
+        match response {
+            Ok(response) if response.get_ref().error.is_empty() => {}
+            Ok(response) => warn!("unable to prepare rules: {}", response.get_ref().error),
+            Err(status) => warn!("unable to prepare rules: {}", status.message()),
+        }
+    }
+
+    async fn evaluate(
+        &self,
+        rule_id: &str,
+        condition: &Condition,
+        chain: &mut HashMap<String, ChainTemplate>,
+    ) -> Result<Outcome, Error> {
+        let mut outcome = match &condition.kind {
+            ConditionKind::Capability { .. }
+                if condition
+                    .from
+                    .as_ref()
+                    .is_some_and(|from| !chain.get(from).is_some_and(|c| c.matched)) =>
+            {
+                // Nothing to chain from.
+                Outcome::default()
+            }
+            ConditionKind::Capability {
+                capability, info, ..
+            } => {
+                self.capability(rule_id, capability, &template(info, chain))
+                    .await?
+            }
+            ConditionKind::And(conditions) | ConditionKind::Or(conditions) => {
+                let is_and = matches!(condition.kind, ConditionKind::And(_));
+                let mut outcome = Outcome {
+                    matched: is_and,
+                    incidents: vec![],
+                };
+                for child in chain_order(conditions)? {
+                    let child_outcome = Box::pin(self.evaluate(rule_id, child, chain)).await?;
+                    if is_and {
+                        outcome.matched &= child_outcome.matched;
+                    } else {
+                        outcome.matched |= child_outcome.matched;
+                    }
+                    if child_outcome.matched {
+                        outcome.incidents.extend(child_outcome.incidents);
+                    }
+                }
+                if !outcome.matched {
+                    outcome.incidents.clear();
+                }
+                outcome
+            }
+        };
+        if let Some(name) = &condition.r#as {
+            let filepaths: BTreeSet<String> = outcome
+                .incidents
+                .iter()
+                .filter_map(|i| file_path(&i.file_uri))
+                .collect();
+            chain.insert(
+                name.clone(),
+                ChainTemplate {
+                    matched: outcome.matched,
+                    filepaths: filepaths.into_iter().collect(),
+                },
+            );
+        }
+        if condition.not {
+            outcome.matched = !outcome.matched;
+            outcome.incidents.clear();
+        }
+        if condition.ignore {
+            outcome.incidents.clear();
+        }
+        Ok(outcome)
+    }
+
+    async fn capability(
+        &self,
+        rule_id: &str,
+        capability: &str,
+        info: &Value,
+    ) -> Result<Outcome, Error> {
+        let condition_info = json!({ capability: info }).to_string();
+        debug!(
+            "evaluating {} for {}: {}",
+            capability, rule_id, condition_info
+        );
+        let response = self
+            .provider
+            .evaluate(Request::new(EvaluateRequest {
+                id: 0,
+                cap: capability.to_string(),
+                condition_info,
+            }))
+            .await
+            .map_err(|status| anyhow!("{}", status.message()))?
+            .into_inner();
+        if !response.successful {
+            return Err(anyhow!("{}", response.error));
+        }
+        let mut incidents: Vec<ResultNode> = response
+            .response
+            .map(|r| r.incident_contexts.iter().map(Into::into).collect())
+            .unwrap_or_default();
+        // The rule ID is known here, so comments that name it can be applied.
+        let suppressions = match self.provider.project.lock().await.as_ref() {
+            Some(project) => project.get_suppressions(),
+            None => None,
+        };
+        if let Some(suppressions) = suppressions {
+            suppressions.apply(&mut incidents, Some(rule_id));
+        }
+        Ok(Outcome {
+            matched: !incidents.is_empty(),
+            incidents,
+        })
+    }
+
+    async fn incident(&self, message: &str, result: ResultNode) -> Incident {
+        let snip = self
+            .provider
+            .get_code_snip(Request::new(GetCodeSnipRequest {
+                uri: result.file_uri.clone(),
+                code_location: Some(result.code_location.clone().into()),
+            }))
+            .await;
+        let code_snip = match snip {
+            Ok(response) => response.into_inner().snip,
+            Err(status) => {
+                warn!("unable to get code snip: {}", status.message());
+                String::new()
+            }
+        };
+        Incident {
+            message: render_message(message, &result.variables),
+            uri: result.file_uri,
+            code_snip,
+            line_number: result.line_number,
+            variables: result.variables,
+            code_location: result.code_location,
+        }
+    }
+
+    async fn ruleset(&self, ruleset: RuleSet) -> RuleSetOutput {
+        let mut output = RuleSetOutput {
+            name: ruleset.metadata.name,
+            description: ruleset.metadata.description,
+            violations: BTreeMap::new(),
+            insights: BTreeMap::new(),
+            errors: BTreeMap::new(),
+            unmatched: vec![],
+            skipped: vec![],
+        };
+        for rule in ruleset.rules {
+            let condition = match Condition::parse(&rule.when) {
+                Ok(condition) => condition,
+                Err(e) => {
+                    output.errors.insert(rule.rule_id, e.to_string());
+                    continue;
+                }
+            };
+            let mut providers = BTreeSet::new();
+            condition.providers(&mut providers);
+            if providers.iter().any(|p| p != PROVIDER) {
+                info!(
+                    "skipping {}, it uses providers {:?}",
+                    rule.rule_id, providers
+                );
+                output.skipped.push(rule.rule_id);
+                continue;
+            }
+            let outcome = match self
+                .evaluate(&rule.rule_id, &condition, &mut HashMap::new())
+                .await
+            {
+                Ok(outcome) => outcome,
+                Err(e) => {
+                    output.errors.insert(rule.rule_id, e.to_string());
+                    continue;
+                }
+            };
+            if !outcome.matched {
+                output.unmatched.push(rule.rule_id);
+                continue;
+            }
+            let mut results = outcome.incidents;
+            results.sort();
+            results.dedup_by(|a, b| a.file_uri == b.file_uri && a.code_location == b.code_location);
+            let mut incidents = vec![];
+            for result in results {
+                incidents.push(self.incident(&rule.message, result).await);
+            }
+            let violation = Violation {
+                description: rule.description,
+                category: rule.category,
+                labels: rule.labels,
+                incidents,
+                links: rule.links,
+                effort: rule.effort.filter(|e| *e > 0),
+            };
+            if violation.effort.is_some() {
+                output.violations.insert(rule.rule_id, violation);
+            } else {
+                output.insights.insert(rule.rule_id, violation);
+            }
+        }
+        output.unmatched.sort();
+        output.skipped.sort();
+        output
+    }
+}
+
+/// The conditions in the order they can be evaluated, a `from` condition goes after the `as`
+/// condition it uses.
+fn chain_order(conditions: &[Condition]) -> Result<Vec<&Condition>, Error> {
+    let mut ordered: Vec<&Condition> = vec![];
+    let mut pending: Vec<&Condition> = conditions.iter().collect();
+    let mut defined: BTreeSet<&str> = BTreeSet::new();
+    let names: BTreeSet<&str> = conditions
+        .iter()
+        .filter_map(|c| c.r#as.as_deref())
+        .collect();
+    while !pending.is_empty() {
+        let Some(index) = pending.iter().position(|c| match c.from.as_deref() {
+            // A name from an enclosing condition is already in the chain.
+            Some(from) => !names.contains(from) || defined.contains(from),
+            None => true,
+        }) else {
+            return Err(anyhow!("conditions chain from each other"));
+        };
+        let condition = pending.remove(index);
+        if let Some(name) = condition.r#as.as_deref() {
+            defined.insert(name);
+        }
+        ordered.push(condition);
+    }
+    Ok(ordered)
+}
+
+/// Evaluate the rules in `path` against the initialized project of the provider.
+pub async fn analyze(provider: &CSharpProvider, path: &Path) -> Result<Vec<RuleSetOutput>, Error> {
+    let engine = Engine { provider };
+    let rulesets = load_rulesets(path)?;
+    engine.prepare(&rulesets).await;
+    let mut output = vec![];
+    for ruleset in rulesets {
+        info!(
+            "evaluating {} rules of {}",
+            ruleset.rules.len(),
+            ruleset.metadata.name
+        );
+        output.push(engine.ruleset(ruleset).await);
+    }
+    output.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(output)
+}
+
+/// The rules and incidents of the output for a SARIF log.
+pub(super) fn sarif_rules(output: &[RuleSetOutput]) -> (Vec<SarifRule>, Vec<SarifResult<'_>>) {
+    let mut rules = vec![];
+    let mut results = vec![];
+    let violations = output
+        .iter()
+        .flat_map(|ruleset| ruleset.violations.iter().chain(ruleset.insights.iter()));
+    for (rule_id, violation) in violations {
+        rules.push(SarifRule {
+            id: rule_id.clone(),
+            description: violation.description.clone(),
+            help_uri: violation.links.first().map(|l| l.url.clone()),
+            tags: violation.labels.clone(),
+            category: violation.category.clone(),
+            effort: violation.effort,
+        });
+        results.extend(violation.incidents.iter().map(|incident| SarifResult {
+            rule_id,
+            message: if incident.message.is_empty() {
+                &violation.description
+            } else {
+                &incident.message
+            },
+            file_uri: &incident.uri,
+            code_location: &incident.code_location,
+            variables: &incident.variables,
+        }));
+    }
+    (rules, results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_condition() {
+        let when: Value = serde_yml::from_str(
+            r#"
+and:
+- csharp.project:
+    pattern: ^PackageReference$
+    value: EntityFramework
+  as: ef
+  ignore: true
+- or:
+  - csharp.referenced:
+      pattern: System.Data.Entity.DbContext
+      file_paths: "{{ef.filepaths}}"
+    from: ef
+  - builtin.file:
+      pattern: web.config
+    not: true
+"#,
+        )
+        .unwrap();
+        let condition = Condition::parse(&when).unwrap();
+        let ConditionKind::And(conditions) = &condition.kind else {
+            panic!("expected and: {:?}", condition);
+        };
+        assert_eq!(conditions[0].r#as.as_deref(), Some("ef"));
+        assert!(conditions[0].ignore);
+        let ConditionKind::Or(alternatives) = &conditions[1].kind else {
+            panic!("expected or: {:?}", conditions[1]);
+        };
+        assert_eq!(alternatives[0].from.as_deref(), Some("ef"));
+        assert!(alternatives[1].not);
+
+        let mut providers = BTreeSet::new();
+        condition.providers(&mut providers);
+        assert_eq!(
+            providers.into_iter().collect::<Vec<_>>(),
+            vec!["builtin", "csharp"]
+        );
+        assert!(Condition::parse(&json!({"csharp.referenced": {}, "or": []})).is_err());
+    }
+
+    #[test]
+    fn test_chain_order() {
+        let when = json!({"and": [
+            {"csharp.referenced": {"pattern": "B"}, "from": "a"},
+            {"csharp.project": {"pattern": "A"}, "as": "a"},
+        ]});
+        let ConditionKind::And(conditions) = Condition::parse(&when).unwrap().kind else {
+            panic!("expected and");
+        };
+        let ordered = chain_order(&conditions).unwrap();
+        assert_eq!(ordered[0].r#as.as_deref(), Some("a"));
+
+        let mut chain = HashMap::new();
+        chain.insert(
+            "a".to_string(),
+            ChainTemplate {
+                matched: true,
+                filepaths: vec!["/src/App.csproj".to_string()],
+            },
+        );
+        assert_eq!(
+            template(&json!({"file_paths": "{{ a.filepaths }}"}), &chain),
+            json!({"file_paths": ["/src/App.csproj"]})
+        );
+    }
+
+    #[test]
+    fn test_render_message() {
+        let mut variables = BTreeMap::new();
+        variables.insert("symbol".to_string(), json!("HttpContext.Current"));
+        variables.insert("line".to_string(), json!(3));
+        assert_eq!(
+            render_message("Replace {{symbol}} on {{ line }}{{missing}}", &variables),
+            "Replace HttpContext.Current on 3"
+        );
+    }
+}
@@ -0,0 +1,332 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use serde_json::{json, Value};
+use url::Url;
+
+use crate::c_sharp_graph::results::Location;
+use crate::provider::fingerprint::FINGERPRINT_VARIABLE;
+
+const SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+const SOURCE_ROOT: &str = "SRCROOT";
+const PARTIAL_FINGERPRINT: &str = "incident/v1";
+
+/// A rule of the SARIF tool driver.
+#[derive(Debug)]
+pub(super) struct SarifRule {
+    pub(super) id: String,
+    pub(super) description: String,
+    pub(super) help_uri: Option<String>,
+    pub(super) tags: Vec<String>,
+    pub(super) category: Option<String>,
+    pub(super) effort: Option<i64>,
+}
+
+/// An incident of a rule.
+#[derive(Debug)]
+pub(super) struct SarifResult<'a> {
+    pub(super) rule_id: &'a str,
+    pub(super) message: &'a str,
+    pub(super) file_uri: &'a str,
+    pub(super) code_location: &'a Location,
+    pub(super) variables: &'a BTreeMap<String, Value>,
+}
+
+/// The level of a result, mandatory changes are errors and insights are notes.
+fn level(rule: Option<&SarifRule>) -> &'static str {
+    match rule {
+        Some(rule) if rule.effort.is_none() => "note",
+        Some(rule) => match rule.category.as_deref() {
+            Some("mandatory") => "error",
+            Some("potential") => "note",
+            _ => "warning",
+        },
+        None => "warning",
+    }
+}
+
+/// The URI relative to the project when the file is in it, so the results line up with the
+/// files of a checkout wherever the project was analyzed.
+fn artifact_location(file_uri: &str, root: &Path) -> Value {
+    let relative = Url::parse(file_uri)
+        .ok()
+        .zip(Url::from_directory_path(root).ok())
+        .and_then(|(uri, root)| {
+            uri.path()
+                .starts_with(root.path())
+                .then(|| root.make_relative(&uri))
+                .flatten()
+        });
+    match relative {
+        Some(uri) => json!({ "uri": uri, "uriBaseId": SOURCE_ROOT }),
+        None => json!({ "uri": file_uri }),
+    }
+}
+
+/// The lines of the file, `None` when it can not be read.
+fn source_lines(file_uri: &str) -> Option<Vec<String>> {
+    let path = Url::parse(file_uri).ok()?.to_file_path().ok()?;
+    let source = std::fs::read_to_string(path).ok()?;
+    Some(source.lines().map(str::to_string).collect())
+}
+
+/// The column of a byte offset in UTF-16 code units, the default `columnKind` of SARIF. The byte
+/// offset is kept when the line is not known.
+fn utf16_column(lines: Option<&[String]>, line: usize, byte: usize) -> usize {
+    let Some(text) = lines.and_then(|lines| lines.get(line)) else {
+        return byte;
+    };
+    let mut end = byte.min(text.len());
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    text[..end].encode_utf16().count()
+}
+
+/// SARIF lines and columns start at 1, the end column is the one after the incident. The
+/// locations of the graph count bytes, they are converted to UTF-16 code units.
+fn region(location: &Location, lines: Option<&[String]>) -> Value {
+    let (start, end) = (&location.start_position, &location.end_position);
+    json!({
+        "startLine": start.line + 1,
+        "startColumn": utf16_column(lines, start.line, start.character) + 1,
+        "endLine": end.line + 1,
+        "endColumn": utf16_column(lines, end.line, end.character) + 1,
+    })
+}
+
+/// The resolved FQDN of the incident, with the kind of its innermost part.
+fn logical_location(variables: &BTreeMap<String, Value>) -> Option<Value> {
+    let part = |key: &str| variables.get(key).and_then(|v| v.as_str());
+    let parts: Vec<&str> = [
+        "fqdn_namespace",
+        "fqdn_class",
+        "fqdn_method",
+        "fqdn_field",
+        "fqdn_property",
+        "fqdn_event",
+    ]
+    .into_iter()
+    .filter_map(part)
+    .collect();
+    if parts.is_empty() {
+        return None;
+    }
+    let kind = if part("fqdn_method").is_some() {
+        "function"
+    } else if ["fqdn_field", "fqdn_property", "fqdn_event"]
+        .into_iter()
+        .any(|k| part(k).is_some())
+    {
+        "member"
+    } else if part("fqdn_class").is_some() {
+        "type"
+    } else {
+        "namespace"
+    };
+    Some(json!({ "fullyQualifiedName": parts.join("."), "kind": kind }))
+}
+
+/// Build a SARIF 2.1.0 log with a single run of the provider.
+pub(super) fn sarif(rules: &[SarifRule], results: &[SarifResult], root: &Path) -> Value {
+    let rule_indexes: BTreeMap<&str, usize> = rules
+        .iter()
+        .enumerate()
+        .map(|(i, r)| (r.id.as_str(), i))
+        .collect();
+    let driver_rules: Vec<Value> = rules
+        .iter()
+        .map(|rule| {
+            let mut value = json!({
+                "id": rule.id,
+                "shortDescription": { "text": rule.description },
+                "properties": { "tags": rule.tags },
+            });
+            if let Some(help_uri) = &rule.help_uri {
+                value["helpUri"] = json!(help_uri);
+            }
+            if let Some(category) = &rule.category {
+                value["properties"]["category"] = json!(category);
+            }
+            if let Some(effort) = rule.effort {
+                value["properties"]["effort"] = json!(effort);
+            }
+            value
+        })
+        .collect();
+    let mut sources: HashMap<&str, Option<Vec<String>>> = HashMap::new();
+    let results: Vec<Value> = results
+        .iter()
+        .map(|result| {
+            let index = rule_indexes.get(result.rule_id).copied();
+            let lines = sources
+                .entry(result.file_uri)
+                .or_insert_with(|| source_lines(result.file_uri));
+            let mut location = json!({
+                "physicalLocation": {
+                    "artifactLocation": artifact_location(result.file_uri, root),
+                    "region": region(result.code_location, lines.as_deref()),
+                },
+            });
+            if let Some(logical) = logical_location(result.variables) {
+                location["logicalLocations"] = json!([logical]);
+            }
+            let mut value = json!({
+                "ruleId": result.rule_id,
+                "level": level(index.map(|i| &rules[i])),
+                "message": { "text": result.message },
+                "locations": [location],
+                "properties": result.variables,
+            });
+            if let Some(index) = index {
+                value["ruleIndex"] = json!(index);
+            }
+            if let Some(fingerprint) = result.variables.get(FINGERPRINT_VARIABLE) {
+                value["partialFingerprints"] = json!({ PARTIAL_FINGERPRINT: fingerprint });
+            }
+            value
+        })
+        .collect();
+    let root = Url::from_directory_path(root)
+        .map(|u| u.to_string())
+        .unwrap_or_default();
+    json!({
+        "$schema": SCHEMA,
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": env!("CARGO_PKG_NAME"),
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "rules": driver_rules,
+                },
+            },
+            "originalUriBaseIds": { SOURCE_ROOT: { "uri": root } },
+            "results": results,
+        }],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::c_sharp_graph::results::Position;
+
+    #[test]
+    fn test_sarif() {
+        let mut variables = BTreeMap::new();
+        variables.insert("fqdn_namespace".to_string(), json!("System.Web"));
+        variables.insert("fqdn_class".to_string(), json!("HttpContext"));
+        variables.insert("fqdn_property".to_string(), json!("Current"));
+        variables.insert(FINGERPRINT_VARIABLE.to_string(), json!("abc123"));
+        let location = Location {
+            start_position: Position {
+                line: 4,
+                character: 8,
+            },
+            end_position: Position {
+                line: 4,
+                character: 27,
+            },
+        };
+        let rules = vec![SarifRule {
+            id: "httpcontext-current-01".to_string(),
+            description: "HttpContext.Current is not available".to_string(),
+            help_uri: Some("https://learn.microsoft.com".to_string()),
+            tags: vec!["konveyor.io/target=dotnet-core".to_string()],
+            category: Some("mandatory".to_string()),
+            effort: Some(5),
+        }];
+        let no_variables = BTreeMap::new();
+        let results = vec![
+            SarifResult {
+                rule_id: "httpcontext-current-01",
+                message: "Replace HttpContext.Current",
+                file_uri: "file:///src/app/Controllers/Home.cs",
+                code_location: &location,
+                variables: &variables,
+            },
+            SarifResult {
+                rule_id: "other",
+                message: "",
+                file_uri: "file:///elsewhere/Home.cs",
+                code_location: &location,
+                variables: &no_variables,
+            },
+        ];
+        let log = sarif(&rules, &results, Path::new("/src/app"));
+        let run = &log["runs"][0];
+        assert_eq!(run["tool"]["driver"]["rules"][0]["properties"]["effort"], 5);
+        assert_eq!(
+            run["originalUriBaseIds"]["SRCROOT"]["uri"],
+            "file:///src/app/"
+        );
+
+        let result = &run["results"][0];
+        assert_eq!(result["ruleIndex"], 0);
+        assert_eq!(result["level"], "error");
+        assert_eq!(result["partialFingerprints"]["incident/v1"], "abc123");
+        let location = &result["locations"][0];
+        assert_eq!(
+            location["physicalLocation"]["artifactLocation"],
+            json!({"uri": "Controllers/Home.cs", "uriBaseId": "SRCROOT"})
+        );
+        assert_eq!(
+            location["physicalLocation"]["region"],
+            json!({"startLine": 5, "startColumn": 9, "endLine": 5, "endColumn": 28})
+        );
+        assert_eq!(
+            location["logicalLocations"][0],
+            json!({"fullyQualifiedName": "System.Web.HttpContext.Current", "kind": "member"})
+        );
+
+        let outside = &run["results"][1];
+        assert!(outside.get("ruleIndex").is_none());
+        assert!(outside.get("partialFingerprints").is_none());
+        assert_eq!(
+            outside["locations"][0]["physicalLocation"]["artifactLocation"],
+            json!({"uri": "file:///elsewhere/Home.cs"})
+        );
+    }
+
+    #[test]
+    fn test_sarif_utf16_columns() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("Home.cs");
+        std::fs::write(
+            &file,
+            "// déjà vu 😀\nvar s = \"😀\"; HttpContext.Current.User;\n",
+        )
+        .unwrap();
+        let file_uri = Url::from_file_path(&file).unwrap().to_string();
+        // `HttpContext.Current` on the second line, the emoji is 4 bytes and 2 UTF-16 code units.
+        let location = Location {
+            start_position: Position {
+                line: 1,
+                character: 16,
+            },
+            end_position: Position {
+                line: 1,
+                character: 35,
+            },
+        };
+        let variables = BTreeMap::new();
+        let results = vec![SarifResult {
+            rule_id: "rule",
+            message: "",
+            file_uri: &file_uri,
+            code_location: &location,
+            variables: &variables,
+        }];
+        let log = sarif(&[], &results, dir.path());
+        assert_eq!(
+            log["runs"][0]["results"][0]["locations"][0]["physicalLocation"]["region"],
+            json!({"startLine": 2, "startColumn": 15, "endLine": 2, "endColumn": 34})
+        );
+
+        let lines = vec!["déjà".to_string()];
+        assert_eq!(utf16_column(Some(&lines), 0, 3), 2);
+        assert_eq!(utf16_column(Some(&lines), 0, 6), 4);
+        assert_eq!(utf16_column(None, 0, 6), 6);
+    }
+}
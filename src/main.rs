@@ -1,10 +1,10 @@
 mod analyzer_service;
 mod c_sharp_graph;
+mod cli;
 mod pipe_stream;
 mod provider;
 
 use std::{
-    env::temp_dir,
     path::PathBuf,
     sync::{
         atomic::{AtomicUsize, Ordering},
@@ -13,10 +13,10 @@ use std::{
     time::Duration,
 };
 
-use clap::{command, Parser};
-use tokio::runtime;
+use clap::{Parser, Subcommand};
+use tokio::runtime::{self, Runtime};
 use tonic::transport::Server;
-use tracing::{debug, info, instrument::WithSubscriber};
+use tracing::{debug, error, info, instrument::WithSubscriber};
 use tracing_log::LogTracer;
 use tracing_subscriber::{fmt, layer::SubscriberExt, EnvFilter};
 
@@ -30,6 +30,28 @@ use crate::provider::CSharpProvider;
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+    #[command(flatten)]
+    serve: ServeArgs,
+
+    #[arg(long, global = true)]
+    log_file: Option<String>,
+    #[command(flatten)]
+    verbosity: clap_verbosity_flag::Verbosity,
+    /// The graph database, by default each project gets its own in the cache directory.
+    #[arg(long, global = true)]
+    db_path: Option<PathBuf>,
+    #[arg(long, global = true)]
+    context_lines: Option<usize>,
+    /// The number of threads that build the graph of the source files and prepare searches, the
+    /// number of CPUs by default.
+    #[arg(long, global = true)]
+    index_workers: Option<usize>,
+}
+
+#[derive(clap::Args)]
+struct ServeArgs {
     #[arg(long)]
     port: Option<usize>,
 
@@ -38,14 +60,20 @@ struct Args {
 
     #[arg(long)]
     name: Option<String>,
-    #[arg(long)]
-    log_file: Option<String>,
-    #[command(flatten)]
-    verbosity: clap_verbosity_flag::Verbosity,
-    #[arg(long)]
-    db_path: Option<PathBuf>,
-    #[arg(long)]
-    context_lines: Option<usize>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Run the gRPC provider, the default when no subcommand is given.
+    Serve(ServeArgs),
+    /// Build the stack graph database of a project.
+    Index(cli::IndexArgs),
+    /// Evaluate a referenced condition against a project and print the incidents.
+    Query(cli::QueryArgs),
+    /// Evaluate rulesets against a project and write the output of the analyzer.
+    Analyze(cli::AnalyzeArgs),
+    /// List, inspect and purge the graph databases of the projects.
+    Cache(cli::CacheArgs),
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -79,6 +107,20 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 .with_thread_names(true),
         );
 
+        tracing::subscriber::set_global_default(subscriber)?;
+    } else if matches!(
+        args.command,
+        Some(Command::Index(_) | Command::Query(_) | Command::Analyze(_) | Command::Cache(_))
+    ) {
+        _guard = None;
+
+        // Keep stdout for the output of the command
+        let subscriber = tracing_subscriber::registry().with(filter).with(
+            fmt::layer()
+                .with_writer(std::io::stderr)
+                .with_thread_names(true),
+        );
+
         tracing::subscriber::set_global_default(subscriber)?;
     } else {
         _guard = None;
@@ -100,20 +142,39 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         .enable_all()
         .build()?;
 
-    let db_path = args
-        .db_path
-        .map_or(temp_dir().join("c_sharp_provider.db"), |x| x);
+    let index_workers = args
+        .index_workers
+        .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get()));
     let provider = Arc::new(CSharpProvider::new(
-        db_path,
+        args.db_path.clone(),
         args.context_lines.unwrap_or(10),
+        index_workers,
     ));
+
+    match &args.command {
+        Some(Command::Index(index)) => rt.block_on(cli::index(&provider, index))?,
+        Some(Command::Query(query)) => rt.block_on(cli::query(&provider, query))?,
+        Some(Command::Analyze(analyze)) => rt.block_on(cli::analyze(&provider, analyze))?,
+        Some(Command::Cache(cache)) => cli::cache(&provider, cache)?,
+        Some(Command::Serve(serve)) => serve_grpc(&rt, provider, serve)?,
+        None => serve_grpc(&rt, provider, &args.serve)?,
+    }
+
+    Ok(())
+}
+
+fn serve_grpc(
+    rt: &Runtime,
+    provider: Arc<CSharpProvider>,
+    args: &ServeArgs,
+) -> Result<(), Box<dyn std::error::Error>> {
     let service = tonic_reflection::server::Builder::configure()
         .register_encoded_file_descriptor_set(proto::FILE_DESCRIPTOR_SET)
         .build_v1alpha()
         .unwrap();
 
-    if args.port.is_some() {
-        let s = format!("[::]:{}", args.port.unwrap());
+    if let Some(port) = args.port {
+        let s = format!("[::]:{}", port);
         info!("Using gRPC over HTTP/2 on port {}", s);
 
         let addr = s.parse()?;
@@ -126,7 +187,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     provider.clone(),
                 ))
                 .add_service(service)
-                .serve(addr)
+                .serve_with_shutdown(addr, shutdown_signal(provider.clone()))
                 .with_current_subscriber()
                 .await;
         });
@@ -140,7 +201,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 use tokio::net::UnixListener;
                 use tokio_stream::wrappers::UnixListenerStream;
 
-                let uds = UnixListener::bind(args.socket.unwrap());
+                let uds = UnixListener::bind(args.socket.clone().unwrap());
                 if let Err(err) = uds {
                     use tracing_log::log::error;
 
@@ -157,7 +218,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                         provider.clone(),
                     ))
                     .add_service(service)
-                    .serve_with_incoming(uds_stream)
+                    .serve_with_incoming_shutdown(uds_stream, shutdown_signal(provider.clone()))
                     .with_current_subscriber()
                     .await;
             });
@@ -176,7 +237,10 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                         provider.clone(),
                     ))
                     .add_service(service)
-                    .serve_with_incoming(get_named_pipe_connection_stream(args.socket.unwrap()))
+                    .serve_with_incoming_shutdown(
+                        get_named_pipe_connection_stream(args.socket.clone().unwrap()),
+                        shutdown_signal(provider.clone()),
+                    )
                     .with_current_subscriber()
                     .await;
             });
@@ -185,3 +249,34 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+/// Resolves when the server should shut down: once the provider is stopped, or on SIGINT or
+/// SIGTERM, which stop the provider like the `Stop` call does.
+async fn shutdown_signal(provider: Arc<CSharpProvider>) {
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                terminate.recv().await;
+            }
+            Err(e) => {
+                error!("unable to listen for SIGTERM: {e}");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = provider.stopped() => {
+            info!("provider stopped, shutting down the server");
+            return;
+        }
+        _ = tokio::signal::ctrl_c() => info!("received SIGINT, shutting down"),
+        _ = terminate => info!("received SIGTERM, shutting down"),
+    }
+    provider.shutdown().await;
+}
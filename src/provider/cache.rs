@@ -0,0 +1,255 @@
+use std::fs;
+use std::path::{absolute, Path, PathBuf};
+
+use anyhow::{anyhow, Context, Error};
+use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
+use tracing::{info, warn};
+
+use crate::provider::project::AnalysisMode;
+
+const MANIFEST_EXTENSION: &str = "manifest.json";
+const DB_EXTENSION: &str = "db";
+
+/// What a graph database was built from, stored next to it. A database is only used for the
+/// project and configuration in its manifest, so two projects never share a graph.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CacheManifest {
+    pub location: PathBuf,
+    pub analysis_mode: String,
+    /// The graph depends on the TSG of the provider that built it.
+    pub provider_version: String,
+}
+
+impl CacheManifest {
+    pub fn new(location: &Path, analysis_mode: &AnalysisMode) -> CacheManifest {
+        CacheManifest {
+            location: absolute(location).unwrap_or_else(|_| location.to_path_buf()),
+            analysis_mode: analysis_mode.as_str().to_string(),
+            provider_version: env!("CARGO_PKG_VERSION").to_string(),
+        }
+    }
+
+    pub fn path(db_path: &Path) -> PathBuf {
+        db_path.with_extension(MANIFEST_EXTENSION)
+    }
+
+    /// The manifest of the database, `None` when it has none or it can not be read.
+    pub fn read(db_path: &Path) -> Option<CacheManifest> {
+        let content = fs::read_to_string(Self::path(db_path)).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    fn write(&self, db_path: &Path) -> Result<(), Error> {
+        fs::write(Self::path(db_path), serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("unable to write the manifest of {}", db_path.display()))
+    }
+}
+
+/// A graph database along with its manifest.
+#[derive(Debug)]
+pub struct CacheEntry {
+    pub db_path: PathBuf,
+    pub size: u64,
+    pub manifest: Option<CacheManifest>,
+}
+
+impl CacheEntry {
+    pub fn new(db_path: PathBuf) -> CacheEntry {
+        let size = fs::metadata(&db_path).map_or(0, |m| m.len());
+        let manifest = CacheManifest::read(&db_path);
+        CacheEntry {
+            db_path,
+            size,
+            manifest,
+        }
+    }
+}
+
+/// The directory of the databases used when no `--db-path` is given.
+pub fn cache_dir() -> PathBuf {
+    std::env::temp_dir().join("c_sharp_provider")
+}
+
+/// The database of a project in the cache directory, named after the project directory and a
+/// hash of its manifest.
+pub fn default_db_path(location: &Path, analysis_mode: &AnalysisMode) -> PathBuf {
+    let manifest = CacheManifest::new(location, analysis_mode);
+    let mut hasher = Sha1::new();
+    hasher.update(manifest.location.to_string_lossy().as_bytes());
+    hasher.update([0]);
+    hasher.update(manifest.analysis_mode.as_bytes());
+    let hash = format!("{:x}", hasher.finalize());
+    let name = manifest
+        .location
+        .file_name()
+        .map_or("project".to_string(), |n| n.to_string_lossy().to_string());
+    cache_dir().join(format!("{}-{}.{}", name, &hash[..16], DB_EXTENSION))
+}
+
+/// Make `db_path` usable for the project of `manifest`. A database built for another project,
+/// configuration or provider version, or one without a manifest, is not used so that graphs are
+/// never mixed. When `rebuild` is set, which it is for the databases of the cache directory, it
+/// is removed and built again, otherwise it is an error: a database given with `--db-path` is
+/// only removed by `cache purge`.
+pub(crate) fn prepare_db(
+    db_path: &Path,
+    manifest: &CacheManifest,
+    rebuild: bool,
+) -> Result<(), Error> {
+    if db_path.exists() {
+        let mismatch = match CacheManifest::read(db_path) {
+            Some(stored) if stored == *manifest => {
+                info!("using the graph database at {}", db_path.display());
+                return Ok(());
+            }
+            Some(stored) => format!(
+                "graph database {} was built for {} in {} mode by provider {}, not for {} in {} \
+                 mode by provider {}",
+                db_path.display(),
+                stored.location.display(),
+                stored.analysis_mode,
+                stored.provider_version,
+                manifest.location.display(),
+                manifest.analysis_mode,
+                manifest.provider_version
+            ),
+            None => format!("graph database {} has no manifest", db_path.display()),
+        };
+        if !rebuild {
+            return Err(anyhow!(
+                "{}, remove it with `cache purge` or use another --db-path",
+                mismatch
+            ));
+        }
+        warn!("{}, rebuilding it", mismatch);
+        purge(db_path)?;
+    }
+    if let Some(parent) = db_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    manifest.write(db_path)
+}
+
+/// The databases in `dir`, sorted by path.
+pub fn list_caches(dir: &Path) -> Result<Vec<CacheEntry>, Error> {
+    if !dir.exists() {
+        return Ok(vec![]);
+    }
+    let mut entries = vec![];
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|e| e == DB_EXTENSION) {
+            entries.push(CacheEntry::new(path));
+        }
+    }
+    entries.sort_by(|a, b| a.db_path.cmp(&b.db_path));
+    Ok(entries)
+}
+
+/// Remove a database along with its manifest and SQLite journal, returning whether there was
+/// a database to remove.
+pub fn purge(db_path: &Path) -> Result<bool, Error> {
+    let existed = db_path.exists();
+    let mut paths = vec![db_path.to_path_buf(), CacheManifest::path(db_path)];
+    for suffix in ["-journal", "-wal", "-shm"] {
+        let mut journal = db_path.as_os_str().to_owned();
+        journal.push(suffix);
+        paths.push(PathBuf::from(journal));
+    }
+    for path in paths.iter().filter(|p| p.exists()) {
+        fs::remove_file(path).with_context(|| format!("unable to remove {}", path.display()))?;
+    }
+    Ok(existed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_db_path() {
+        let first = default_db_path(Path::new("/work/first"), &AnalysisMode::SourceOnly);
+        assert_eq!(
+            first,
+            default_db_path(Path::new("/work/first"), &AnalysisMode::SourceOnly)
+        );
+        assert!(first.starts_with(cache_dir()));
+        let name = first.file_name().unwrap().to_string_lossy().to_string();
+        assert!(name.starts_with("first-") && name.ends_with(".db"));
+        assert_ne!(
+            first,
+            default_db_path(Path::new("/work/second/first"), &AnalysisMode::SourceOnly)
+        );
+        assert_ne!(
+            first,
+            default_db_path(Path::new("/work/first"), &AnalysisMode::Full)
+        );
+    }
+
+    #[test]
+    fn test_prepare_db() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("project.db");
+        let first = CacheManifest::new(Path::new("/work/first"), &AnalysisMode::SourceOnly);
+        let second = CacheManifest::new(Path::new("/work/second"), &AnalysisMode::SourceOnly);
+
+        prepare_db(&db_path, &first, true).unwrap();
+        assert_eq!(CacheManifest::read(&db_path), Some(first.clone()));
+        fs::write(&db_path, "graph").unwrap();
+
+        // The database of the same project is kept.
+        prepare_db(&db_path, &first, true).unwrap();
+        assert!(db_path.exists());
+        let caches = list_caches(dir.path()).unwrap();
+        assert_eq!(caches.len(), 1);
+        assert_eq!(caches[0].size, 5);
+        assert_eq!(caches[0].manifest, Some(first));
+
+        // The database of another project is removed.
+        prepare_db(&db_path, &second, true).unwrap();
+        assert!(!db_path.exists());
+        assert_eq!(CacheManifest::read(&db_path), Some(second.clone()));
+
+        // A database without a manifest is removed.
+        fs::write(&db_path, "graph").unwrap();
+        fs::remove_file(CacheManifest::path(&db_path)).unwrap();
+        prepare_db(&db_path, &second, true).unwrap();
+        assert!(!db_path.exists());
+
+        fs::write(&db_path, "graph").unwrap();
+        assert!(purge(&db_path).unwrap());
+        assert!(!purge(&db_path).unwrap());
+        assert!(list_caches(dir.path()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_prepare_db_without_rebuild() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("graph.db");
+        let first = CacheManifest::new(Path::new("/work/first"), &AnalysisMode::SourceOnly);
+        let second = CacheManifest::new(Path::new("/work/second"), &AnalysisMode::SourceOnly);
+
+        prepare_db(&db_path, &first, false).unwrap();
+        fs::write(&db_path, "graph").unwrap();
+        prepare_db(&db_path, &first, false).unwrap();
+
+        // The database of another project is kept and refused.
+        let e = prepare_db(&db_path, &second, false).unwrap_err();
+        assert!(e.to_string().contains("/work/first"), "{}", e);
+        assert!(e.to_string().contains("/work/second"), "{}", e);
+        assert!(db_path.exists());
+        assert_eq!(CacheManifest::read(&db_path), Some(first));
+
+        // So is a database without a manifest.
+        fs::remove_file(CacheManifest::path(&db_path)).unwrap();
+        let e = prepare_db(&db_path, &second, false).unwrap_err();
+        assert!(e.to_string().contains("has no manifest"), "{}", e);
+        assert!(db_path.exists());
+        assert_eq!(CacheManifest::read(&db_path), None);
+
+        assert!(purge(&db_path).unwrap());
+        assert!(!purge(&db_path).unwrap());
+        assert!(list_caches(dir.path()).unwrap().is_empty());
+    }
+}
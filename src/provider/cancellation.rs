@@ -0,0 +1,180 @@
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::watch;
+use tonic::metadata::MetadataMap;
+
+/// Cancels the work of the provider when it is stopped. Clones share the same state, and it is
+/// the cancellation flag of the stack graph operations, which are otherwise not cancellable.
+#[derive(Clone, Debug)]
+pub struct Cancellation {
+    cancelled: Arc<watch::Sender<bool>>,
+    /// The cancellation of the provider, when this is the cancellation of a request.
+    parent: Option<Arc<watch::Sender<bool>>>,
+}
+
+/// The query did not finish before the deadline of the request.
+#[derive(Debug, Clone)]
+pub(crate) struct DeadlineExceeded(pub Duration);
+
+impl fmt::Display for DeadlineExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "query did not finish within the deadline of {:?}",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for DeadlineExceeded {}
+
+/// The time the client gives the request, from its `grpc-timeout` header.
+pub(crate) fn request_timeout(metadata: &MetadataMap) -> Option<Duration> {
+    let value = metadata.get("grpc-timeout")?.to_str().ok()?;
+    let (amount, unit) = value.split_at(value.len().checked_sub(1)?);
+    let amount: u64 = amount.parse().ok()?;
+    match unit {
+        "H" => Some(Duration::from_secs(amount.saturating_mul(3600))),
+        "M" => Some(Duration::from_secs(amount.saturating_mul(60))),
+        "S" => Some(Duration::from_secs(amount)),
+        "m" => Some(Duration::from_millis(amount)),
+        "u" => Some(Duration::from_micros(amount)),
+        "n" => Some(Duration::from_nanos(amount)),
+        _ => None,
+    }
+}
+
+/// Cancels a request when dropped, which tonic does to the request future when the client
+/// cancels or the deadline of the call passes.
+pub(crate) struct CancelOnDrop {
+    cancellation: Cancellation,
+}
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.cancellation.cancel();
+    }
+}
+
+impl Default for Cancellation {
+    fn default() -> Self {
+        Cancellation {
+            cancelled: Arc::new(watch::Sender::new(false)),
+            parent: None,
+        }
+    }
+}
+
+impl Cancellation {
+    /// The cancellation of a request, cancelled on its own or with this one.
+    pub(crate) fn child(&self) -> Cancellation {
+        Cancellation {
+            cancelled: Arc::new(watch::Sender::new(false)),
+            parent: Some(self.cancelled.clone()),
+        }
+    }
+
+    pub(crate) fn cancel_on_drop(&self) -> CancelOnDrop {
+        CancelOnDrop {
+            cancellation: self.clone(),
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.send_replace(true);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        *self.cancelled.borrow() || self.parent.as_ref().is_some_and(|parent| *parent.borrow())
+    }
+
+    /// Wait until the work is cancelled.
+    pub async fn cancelled(&self) {
+        let mut receiver = self.cancelled.subscribe();
+        let Some(parent) = &self.parent else {
+            let _ = receiver.wait_for(|cancelled| *cancelled).await;
+            return;
+        };
+        let mut parent = parent.subscribe();
+        tokio::select! {
+            _ = receiver.wait_for(|cancelled| *cancelled) => {}
+            _ = parent.wait_for(|cancelled| *cancelled) => {}
+        }
+    }
+}
+
+impl stack_graphs::CancellationFlag for Cancellation {
+    fn check(&self, at: &'static str) -> Result<(), stack_graphs::CancellationError> {
+        if self.is_cancelled() {
+            return Err(stack_graphs::CancellationError(at));
+        }
+        Ok(())
+    }
+}
+
+impl tree_sitter_stack_graphs::CancellationFlag for Cancellation {
+    fn check(&self, at: &'static str) -> Result<(), tree_sitter_stack_graphs::CancellationError> {
+        if self.is_cancelled() {
+            return Err(tree_sitter_stack_graphs::CancellationError(at));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cancel() {
+        let cancellation = Cancellation::default();
+        let clone = cancellation.clone();
+        let flag: &dyn stack_graphs::CancellationFlag = &clone;
+        assert!(flag.check("before").is_ok());
+
+        cancellation.cancel();
+        assert!(clone.is_cancelled());
+        assert!(flag.check("after").is_err());
+        let flag: &dyn tree_sitter_stack_graphs::CancellationFlag = &clone;
+        assert!(flag.check("after").is_err());
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        runtime.block_on(clone.cancelled());
+    }
+
+    #[test]
+    fn test_request_timeout() {
+        let timeout = |value: &str| {
+            let mut metadata = MetadataMap::new();
+            metadata.insert("grpc-timeout", value.parse().unwrap());
+            request_timeout(&metadata)
+        };
+        assert_eq!(timeout("2S"), Some(Duration::from_secs(2)));
+        assert_eq!(timeout("1500m"), Some(Duration::from_millis(1500)));
+        assert_eq!(timeout("1M"), Some(Duration::from_secs(60)));
+        assert_eq!(timeout("10x"), None);
+        assert_eq!(timeout("S"), None);
+        assert_eq!(request_timeout(&MetadataMap::new()), None);
+    }
+
+    #[test]
+    fn test_child() {
+        let provider = Cancellation::default();
+        let request = provider.child();
+        drop(request.cancel_on_drop());
+        assert!(request.is_cancelled());
+        assert!(!provider.is_cancelled());
+
+        let request = provider.child();
+        provider.cancel();
+        assert!(request.is_cancelled());
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        runtime.block_on(request.cancelled());
+    }
+}
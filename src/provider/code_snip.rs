@@ -87,11 +87,10 @@ impl ProviderCodeLocationService for CSharpProvider {
             .take(take)
             .enumerate()
             .map(|(index, s)| {
-                if s.is_err() {
-                    "".to_string()
-                } else {
-                    let s = s.unwrap();
+                if let Ok(s) = s {
                     format!("{} {}\n", skip_lines + index, s)
+                } else {
+                    "".to_string()
                 }
             })
             .collect();
@@ -0,0 +1,185 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Error};
+use regex::Regex;
+use serde_json::Value;
+use tracing::{debug, info, warn};
+use url::Url;
+use walkdir::{DirEntry, WalkDir};
+
+use crate::c_sharp_graph::query::Query;
+use crate::c_sharp_graph::results::ResultNode;
+use crate::provider::xml_query::{value_matches, XmlElement, XmlMatch, XmlPath};
+
+/// Directories that only hold build output or restored packages.
+const SKIPPED_DIRECTORIES: [&str; 4] = ["bin", "obj", "packages", "node_modules"];
+
+/// Queries the `*.config` files of a project (web.config, app.config and their transforms) with
+/// an XPath-like query.
+pub(crate) struct ConfigQuery {
+    pub(crate) location: PathBuf,
+    /// Regex that the selected attribute value or element text must match.
+    pub(crate) value: Option<String>,
+    /// Regex on the file name, to restrict the query to some of the config files.
+    pub(crate) file_pattern: Option<String>,
+}
+
+impl Query for ConfigQuery {
+    fn query(self, query: String) -> anyhow::Result<Vec<ResultNode>, Error> {
+        let path = XmlPath::parse(&query)?;
+        let value = self.value.as_deref().map(Regex::new).transpose()?;
+        let file_pattern = self.file_pattern.as_deref().map(Regex::new).transpose()?;
+
+        let mut results = vec![];
+        for file in find_config_files(&self.location) {
+            let file_name = file
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default();
+            if file_pattern
+                .as_ref()
+                .is_some_and(|p| !p.is_match(&file_name))
+            {
+                continue;
+            }
+            let document = match XmlElement::from_file(&file) {
+                Ok(d) => d,
+                Err(e) => {
+                    warn!("skipping config file {:?}: {}", file, e);
+                    continue;
+                }
+            };
+            let file_uri = Url::from_file_path(&file)
+                .map_err(|_| anyhow!("unable to get the file url for file: {:?}", file))?
+                .to_string();
+            let transform = get_transform(&file_name);
+            for m in path.select(&document) {
+                if !value_matches(value.as_ref(), m.value.as_deref()) {
+                    continue;
+                }
+                results.push(to_result_node(&file_uri, &m, transform.as_deref()));
+            }
+        }
+        results.sort();
+        info!("found {} config results for '{}'", results.len(), query);
+        Ok(results)
+    }
+}
+
+/// Build an incident for a matched XML element, this is shared by the XML based capabilities.
+pub(crate) fn to_result_node(file_uri: &str, m: &XmlMatch, transform: Option<&str>) -> ResultNode {
+    let mut var: BTreeMap<String, Value> = BTreeMap::new();
+    var.insert("file".to_string(), Value::from(file_uri));
+    var.insert("element".to_string(), Value::from(m.element.name.clone()));
+    var.insert("path".to_string(), Value::from(m.element.path.clone()));
+    if !m.element.attributes.is_empty() {
+        let attributes: serde_json::Map<String, Value> = m
+            .element
+            .attributes
+            .iter()
+            .map(|(k, v)| (k.clone(), Value::from(v.clone())))
+            .collect();
+        var.insert("attributes".to_string(), Value::Object(attributes));
+    }
+    if let Some(value) = &m.value {
+        var.insert("value".to_string(), Value::from(value.clone()));
+    }
+    if let Some(transform) = transform {
+        var.insert("transform".to_string(), Value::from(transform));
+    }
+    ResultNode {
+        file_uri: file_uri.to_string(),
+        line_number: m.element.location.start_position.line,
+        variables: var,
+        code_location: m.element.location.clone(),
+    }
+}
+
+/// Whether a directory is hidden or only holds build output or restored packages.
+pub(crate) fn is_skipped(entry: &DirEntry) -> bool {
+    entry.depth() > 0
+        && entry.file_type().is_dir()
+        && entry.file_name().to_str().is_some_and(|name| {
+            name.starts_with('.') || SKIPPED_DIRECTORIES.contains(&name.to_lowercase().as_str())
+        })
+}
+
+fn find_config_files(location: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = WalkDir::new(location)
+        .into_iter()
+        .filter_entry(|e| !is_skipped(e))
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .map(|e| e.into_path())
+        .filter(|p| {
+            p.extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("config"))
+        })
+        .collect();
+    files.sort();
+    debug!("found config files: {:?}", files);
+    files
+}
+
+/// Config transforms are named after the file they transform and the build configuration, e.g.
+/// `Web.Release.config` transforms `Web.config` for the `Release` configuration.
+fn get_transform(file_name: &str) -> Option<String> {
+    let parts: Vec<&str> = file_name.split('.').collect();
+    if parts.len() != 3 {
+        return None;
+    }
+    let base = parts[0].to_lowercase();
+    if base != "web" && base != "app" {
+        return None;
+    }
+    Some(parts[1].to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_transform() {
+        assert_eq!(
+            get_transform("Web.Release.config"),
+            Some("Release".to_string())
+        );
+        assert_eq!(get_transform("App.Debug.config"), Some("Debug".to_string()));
+        assert_eq!(get_transform("Web.config"), None);
+        assert_eq!(get_transform("packages.config"), None);
+    }
+
+    #[test]
+    fn test_config_query_on_nerd_dinner() {
+        let location = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("testdata/nerd-dinner");
+        let query = ConfigQuery {
+            location,
+            value: None,
+            file_pattern: Some("^Web\\.config$".to_string()),
+        };
+        let results = query
+            .query("/configuration/system.web/authentication[@mode='Forms']".to_string())
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert!(results[0].file_uri.ends_with("mvc4/NerdDinner/Web.config"));
+        assert_eq!(results[0].line_number, 44);
+        assert_eq!(results[0].code_location.start_position.character, 4);
+        assert_eq!(
+            results[0].variables["path"],
+            Value::from("/configuration/system.web/authentication")
+        );
+
+        let location = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("testdata/nerd-dinner");
+        let query = ConfigQuery {
+            location,
+            value: None,
+            file_pattern: None,
+        };
+        let results = query.query("//system.web/compilation".to_string()).unwrap();
+        assert!(results
+            .iter()
+            .any(|r| r.variables.get("transform") == Some(&Value::from("Release"))));
+    }
+}
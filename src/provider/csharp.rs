@@ -1,31 +1,48 @@
-use std::path::PathBuf;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Duration;
 
 use serde::Deserialize;
 use stack_graphs::graph::StackGraph;
 use stack_graphs::storage::SQLiteWriter;
-use tokio::sync::Mutex;
+use tokio::sync::{Mutex, RwLock, RwLockReadGuard};
+use tokio::task::JoinHandle;
 use tokio_stream::wrappers::ReceiverStream;
 use tonic::{Request, Response, Status};
 use tracing::{debug, error, info, warn};
 use url::Url;
 use utoipa::{OpenApi, ToSchema};
 
-use crate::c_sharp_graph::loader::load_and_store_file;
-use crate::c_sharp_graph::query::{Query, QueryType};
+use crate::c_sharp_graph::call_graph::CallsQuery;
+use crate::c_sharp_graph::literal_query::{ArgumentOf, LiteralQuery};
+use crate::c_sharp_graph::loader::{load_and_store_file, SourceType};
+use crate::c_sharp_graph::query::{Query, QueryOptions, QueryType};
+use crate::c_sharp_graph::razor_analyzer::RazorFileAnalyzer;
 use crate::c_sharp_graph::results::ResultNode;
+use crate::c_sharp_graph::web_forms_analyzer::WebFormsFileAnalyzer;
 use crate::c_sharp_graph::NotFoundError;
 //use crate::c_sharp_graph::find_node::FindNode;
+use crate::provider::cache::{self, CacheManifest};
+use crate::provider::cancellation::{request_timeout, Cancellation, DeadlineExceeded};
+use crate::provider::config_file::ConfigQuery;
+use crate::provider::fingerprint::add_fingerprints;
+use crate::provider::markup_file::MarkupQuery;
+use crate::provider::preprocessor::{Preprocessor, PreprocessorSettings};
+use crate::provider::progress::{Progress, ProgressKind};
+use crate::provider::project::GraphSnapshot;
+use crate::provider::project_file::ProjectQuery;
 use crate::provider::sdk_detection::{SdkDetector, SdkSource};
+use crate::provider::suppression::Suppressions;
 use crate::provider::target_framework;
 use crate::provider::AnalysisMode;
 use crate::{
     analyzer_service::{
-        provider_service_server::ProviderService, CapabilitiesResponse, Capability, Config,
-        DependencyDagResponse, DependencyResponse, EvaluateRequest, EvaluateResponse,
-        IncidentContext, InitResponse, NotifyFileChangesRequest, NotifyFileChangesResponse,
-        PrepareProgressRequest, PrepareRequest, PrepareResponse, ProgressEvent, ProgressEventType,
-        ProviderEvaluateResponse, ServiceRequest,
+        provider_service_server::ProviderService, CapabilitiesResponse, Capability,
+        ConditionsByCapability, Config, DependencyDagResponse, DependencyResponse, EvaluateRequest,
+        EvaluateResponse, IncidentContext, InitResponse, NotifyFileChangesRequest,
+        NotifyFileChangesResponse, PrepareProgressRequest, PrepareRequest, PrepareResponse,
+        ProgressEvent, ProviderEvaluateResponse, ServiceRequest,
     },
     provider::Project,
 };
@@ -38,6 +55,28 @@ enum Locations {
     Method,
     Field,
     Class,
+    Property,
+    Event,
+}
+
+#[derive(Clone, ToSchema, Deserialize, Default, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum Kind {
+    Declaration,
+    Reference,
+    #[default]
+    Any,
+}
+
+impl Kind {
+    fn matches(&self, result: &ResultNode) -> bool {
+        let kind = result.variables.get("kind").and_then(|k| k.as_str());
+        match self {
+            Kind::Any => true,
+            Kind::Declaration => kind == Some("declaration"),
+            Kind::Reference => kind == Some("reference"),
+        }
+    }
 }
 
 #[derive(ToSchema, Deserialize, Debug)]
@@ -45,30 +84,343 @@ struct ReferenceCondition {
     pattern: String,
     #[serde(default)]
     location: Locations,
-    #[allow(dead_code)]
+    #[serde(default)]
+    kind: Kind,
+    /// Only report incidents in these files or directories.
     file_paths: Option<Vec<String>>,
 }
 
+/// Whether the result is in one of the files or directories, a relative path matches the end
+/// of the file path.
+fn in_file_paths(result: &ResultNode, file_paths: &[String]) -> bool {
+    let Some(path) = Url::parse(&result.file_uri)
+        .ok()
+        .and_then(|u| u.to_file_path().ok())
+    else {
+        return false;
+    };
+    file_paths
+        .iter()
+        .any(|p| path.starts_with(p) || path.ends_with(p))
+}
+
 #[derive(ToSchema, Deserialize, Debug)]
 struct CSharpCondition {
     referenced: ReferenceCondition,
 }
 
+#[derive(ToSchema, Deserialize, Debug)]
+struct CallsCondition {
+    /// The FQDN pattern that is being called, resolved the same way as referenced. The calls
+    /// between source methods are matched by name and the declared type of the qualifier, not
+    /// resolved through the stack graph, and inherited methods of a typed qualifier are not
+    /// followed. A caller is reported with `call_resolution: type` when every call of its path
+    /// was matched by type, and `call_resolution: name` when one was only matched by name.
+    pattern: String,
+    #[serde(default)]
+    location: Locations,
+    /// Only report callers whose FQDN matches this pattern.
+    callers: Option<String>,
+    max_depth: Option<usize>,
+}
+
+#[derive(ToSchema, Deserialize, Debug)]
+struct CSharpCallsCondition {
+    calls: CallsCondition,
+}
+
+#[derive(ToSchema, Deserialize, Debug)]
+struct LiteralCondition {
+    /// Regex matched against the contents of string literals, interpolated strings keep their
+    /// interpolations as written.
+    pattern: String,
+    /// Only match literals passed as an argument to something matching this referenced pattern.
+    argument_of: Option<String>,
+    /// The location used to resolve argument_of.
+    #[serde(default)]
+    location: Locations,
+}
+
+#[derive(ToSchema, Deserialize, Debug)]
+struct CSharpLiteralCondition {
+    literal: LiteralCondition,
+}
+
+#[derive(ToSchema, Deserialize, Debug)]
+struct ConfigCondition {
+    /// XPath-like query, e.g. `//system.web/authentication[@mode='Forms']`.
+    xpath: String,
+    /// Regex that the selected attribute value or element text must match.
+    value: Option<String>,
+    /// Regex on the file name, by default every `*.config` file is searched.
+    file_pattern: Option<String>,
+}
+
+#[derive(ToSchema, Deserialize, Debug)]
+struct CSharpConfigCondition {
+    config: ConfigCondition,
+}
+
+#[derive(ToSchema, Deserialize, Debug)]
+struct ProjectCondition {
+    /// Regex on the property, item or import element name, e.g. `^PackageReference$`.
+    pattern: String,
+    /// Regex on the property text, the item `Include` or the imported project.
+    value: Option<String>,
+    /// Regex on the version of an item.
+    version: Option<String>,
+}
+
+#[derive(ToSchema, Deserialize, Debug)]
+struct CSharpProjectCondition {
+    project: ProjectCondition,
+}
+
+#[derive(ToSchema, Deserialize, Debug)]
+struct MarkupCondition {
+    /// Regex on the type a directive or server control references, or on the namespace of an
+    /// `Import` or `Register` directive.
+    pattern: String,
+    /// Regex on the directive name or the control tag, e.g. `^Page$` or `^asp:`.
+    element: Option<String>,
+}
+
+#[derive(ToSchema, Deserialize, Debug)]
+struct CSharpMarkupCondition {
+    markup: MarkupCondition,
+}
+
+impl Locations {
+    fn query_type<'a>(
+        &self,
+        graph: &'a StackGraph,
+        source_type: &'a SourceType,
+        options: QueryOptions<'a>,
+    ) -> QueryType<'a> {
+        match self {
+            Locations::All => QueryType::All {
+                graph,
+                source_type,
+                options,
+            },
+            Locations::Method => QueryType::Method {
+                graph,
+                source_type,
+                options,
+            },
+            Locations::Field => QueryType::Field {
+                graph,
+                source_type,
+                options,
+            },
+            Locations::Class => QueryType::Class {
+                graph,
+                source_type,
+                options,
+            },
+            Locations::Property => QueryType::Property {
+                graph,
+                source_type,
+                options,
+            },
+            Locations::Event => QueryType::Event {
+                graph,
+                source_type,
+                options,
+            },
+        }
+    }
+}
+
+/// The status of a query that did not finish because its deadline passed or it was cancelled.
+fn interrupted_status(e: &anyhow::Error) -> Option<Status> {
+    if e.is::<DeadlineExceeded>() {
+        return Some(Status::deadline_exceeded(e.to_string()));
+    }
+    if e.is::<tree_sitter_stack_graphs::CancellationError>() {
+        return Some(Status::cancelled(e.to_string()));
+    }
+    None
+}
+
 pub struct CSharpProvider {
-    pub db_path: PathBuf,
+    /// The graph database given on the command line, by default each project gets its own in
+    /// the cache directory.
+    pub db_path: Option<PathBuf>,
     pub config: Arc<Mutex<Option<Config>>>,
     pub project: Arc<Mutex<Option<Arc<Project>>>>,
     pub context_lines: usize,
+    /// The number of threads that build the graph of the source files on init and prepare
+    /// searches.
+    pub index_workers: usize,
+    progress: Progress,
+    cancellation: Cancellation,
+    /// Held by the requests that do work, so a stop can wait for them to end.
+    work: Arc<RwLock<()>>,
+    /// Lets a test hold `notify_file_changes` after the files are stored and before the graph is
+    /// reloaded.
+    #[cfg(test)]
+    reload_barrier: Option<Arc<tokio::sync::Barrier>>,
 }
 
 impl CSharpProvider {
-    pub fn new(db_path: PathBuf, context_lines: usize) -> CSharpProvider {
+    pub fn new(
+        db_path: Option<PathBuf>,
+        context_lines: usize,
+        index_workers: usize,
+    ) -> CSharpProvider {
         CSharpProvider {
             db_path,
             config: Arc::new(Mutex::new(None)),
             project: Arc::new(Mutex::new(None)),
             context_lines,
+            index_workers,
+            progress: Progress::new(),
+            cancellation: Cancellation::default(),
+            work: Arc::new(RwLock::new(())),
+            #[cfg(test)]
+            reload_barrier: None,
+        }
+    }
+
+    /// Cancel the work in flight and wait for it to end, so the SQLite writers are closed. New
+    /// requests are rejected after it.
+    pub async fn shutdown(&self) {
+        info!("stopping the provider");
+        self.cancellation.cancel();
+        let _work = self.work.write().await;
+        info!("provider stopped");
+    }
+
+    /// Resolves once the provider is stopped.
+    pub async fn stopped(&self) {
+        self.cancellation.cancelled().await
+    }
+
+    /// Ends an init once the provider is stopped, after the SDK loading is done so that it does
+    /// not write to the database after the stop.
+    async fn check_cancelled(
+        &self,
+        sdk_xml_handle: &mut Option<JoinHandle<anyhow::Result<usize>>>,
+    ) -> Result<(), Status> {
+        if !self.cancellation.is_cancelled() {
+            return Ok(());
+        }
+        if let Some(handle) = sdk_xml_handle.take() {
+            let _ = handle.await;
+        }
+        Err(Status::cancelled("init was cancelled"))
+    }
+
+    async fn begin_work(&self) -> Result<RwLockReadGuard<'_, ()>, Status> {
+        if self.cancellation.is_cancelled() {
+            return Err(Status::cancelled("the provider is stopping"));
         }
+        Ok(self.work.read().await)
+    }
+
+    /// Run a query against the files of the project, this does not need the graph.
+    async fn query_project_files<F>(
+        &self,
+        query: F,
+    ) -> Result<anyhow::Result<Vec<ResultNode>>, EvaluateResponse>
+    where
+        F: FnOnce(PathBuf) -> anyhow::Result<Vec<ResultNode>>,
+    {
+        let project_guard = self.project.lock().await;
+        let location = match project_guard.as_ref() {
+            Some(x) => x.location.clone(),
+            None => {
+                return Err(EvaluateResponse {
+                    error: "project may not be initialized".to_string(),
+                    successful: false,
+                    response: None,
+                });
+            }
+        };
+        drop(project_guard);
+        Ok(query(location))
+    }
+
+    /// Run a query against the project graph, the outer error is the response to return when
+    /// the project or graph is not ready to be queried. A query still running shortly before
+    /// `timeout` is cancelled with a `DeadlineExceeded` error.
+    async fn query_graph<F>(
+        &self,
+        timeout: Option<Duration>,
+        query: F,
+    ) -> Result<anyhow::Result<Vec<ResultNode>>, EvaluateResponse>
+    where
+        F: FnOnce(&GraphSnapshot, &SourceType, QueryOptions) -> anyhow::Result<Vec<ResultNode>>
+            + Send
+            + 'static,
+    {
+        let project_guard = self.project.lock().await;
+        let project = match project_guard.as_ref() {
+            Some(x) => x.clone(),
+            None => {
+                return Err(EvaluateResponse {
+                    error: "project may not be initialized".to_string(),
+                    successful: false,
+                    response: None,
+                });
+            }
+        };
+        let source_type = match project.get_source_type().await {
+            Some(s) => s,
+            None => {
+                return Err(EvaluateResponse {
+                    error: "project may not be initialized".to_string(),
+                    successful: false,
+                    response: None,
+                });
+            }
+        };
+        // Release the project lock, so other evaluate calls can continue
+        drop(project_guard);
+
+        // The query runs on a blocking thread, so that this future is dropped when the client
+        // cancels or the deadline passes, which cancels the query and releases its graph snapshot.
+        let cancellation = self.cancellation.child();
+        let _cancel_on_drop = cancellation.cancel_on_drop();
+        let queried = tokio::task::spawn_blocking(move || {
+            let snapshot = match project.graph_snapshot() {
+                Some(s) => s,
+                None => {
+                    // Graph was invalidated (e.g., by notify_file_changes) and needs to be
+                    // rebuilt. Return an error so the client knows to re-initialize the provider.
+                    return Err(EvaluateResponse {
+                        error: "No project initialized. Graph cache was invalidated.".to_string(),
+                        successful: false,
+                        response: None,
+                    });
+                }
+            };
+
+            let prepared = snapshot.prepared_searches();
+            let options = QueryOptions {
+                prepared: Some(&prepared),
+                cancellation: Some(&cancellation),
+                ..QueryOptions::indexed(&snapshot.index)
+            };
+            Ok(query(&snapshot, &source_type, options))
+        });
+        let queried = match timeout {
+            // Answer before the deadline passes, tonic then drops the request and reports it as
+            // cancelled, the same as a client that gave up.
+            Some(timeout) => match tokio::time::timeout(timeout - timeout / 10, queried).await {
+                Ok(queried) => queried,
+                Err(_) => return Ok(Err(anyhow::anyhow!(DeadlineExceeded(timeout)))),
+            },
+            None => queried.await,
+        };
+        queried.unwrap_or_else(|e| {
+            Err(EvaluateResponse {
+                error: format!("query failed: {}", e),
+                successful: false,
+                response: None,
+            })
+        })
     }
 }
 
@@ -90,14 +442,38 @@ impl ProviderService for CSharpProvider {
         debug!("returning refernced capability: {:?}", json.ok());
 
         return Ok(Response::new(CapabilitiesResponse {
-            capabilities: vec![Capability {
-                name: "referenced".to_string(),
-                template_context: None,
-            }],
+            capabilities: vec![
+                Capability {
+                    name: "referenced".to_string(),
+                    template_context: None,
+                },
+                Capability {
+                    name: "calls".to_string(),
+                    template_context: None,
+                },
+                Capability {
+                    name: "literal".to_string(),
+                    template_context: None,
+                },
+                Capability {
+                    name: "config".to_string(),
+                    template_context: None,
+                },
+                Capability {
+                    name: "project".to_string(),
+                    template_context: None,
+                },
+                Capability {
+                    name: "markup".to_string(),
+                    template_context: None,
+                },
+            ],
         }));
     }
 
     async fn init(&self, r: Request<Config>) -> Result<Response<InitResponse>, Status> {
+        let _work = self.begin_work().await?;
+        let _operation = self.progress.begin();
         let mut config_guard = self.config.lock().await;
         let saved_config = config_guard.insert(r.get_ref().clone());
 
@@ -110,11 +486,30 @@ impl ProviderService for CSharpProvider {
         let location = PathBuf::from(saved_config.location.clone());
         let tools = Project::get_tools(&saved_config.provider_specific_config)
             .map_err(|e| Status::invalid_argument(format!("unalble to find tools: {}", e)))?;
+        let preprocessor_settings =
+            PreprocessorSettings::from_config(&saved_config.provider_specific_config);
+        let suppressions = Suppressions::new(&location, &saved_config.provider_specific_config);
+        // Only the databases of the cache directory are rebuilt when they do not match.
+        let (db_path, rebuild) = match &self.db_path {
+            Some(db_path) => (db_path.clone(), false),
+            None => (cache::default_db_path(&location, &analysis_mode), true),
+        };
+        cache::prepare_db(
+            &db_path,
+            &CacheManifest::new(&location, &analysis_mode),
+            rebuild,
+        )
+        .map_err(|e| {
+            Status::failed_precondition(format!("unable to prepare the graph database: {}", e))
+        })?;
         let project = Arc::new(Project::new(
             location,
-            self.db_path.clone(),
+            db_path,
             analysis_mode,
             tools,
+            self.index_workers,
+            self.progress.clone(),
+            self.cancellation.clone(),
         ));
         let project_lock = self.project.clone();
         let mut project_guard = project_lock.lock().await;
@@ -132,11 +527,14 @@ impl ProviderService for CSharpProvider {
             }
         };
 
+        project.set_preprocessor(Preprocessor::new(&project.location, preprocessor_settings));
+        project.set_suppressions(suppressions);
+
         info!("getting the dotnet target framework for the project");
 
         // Detect target framework from .csproj files (optional)
         // Note: SDK installation only works for .NET Core and .NET 5+, not old .NET Framework
-        let sdk_xml_handle =
+        let mut sdk_xml_handle =
             match target_framework::TargetFrameworkHelper::get_earliest_from_directory(
                 &project.location,
             ) {
@@ -247,7 +645,9 @@ impl ProviderService for CSharpProvider {
                 "unable to create language configuration for project",
             ));
         }
-        let stats = project.get_project_graph().await.map_err(|err| {
+        let stats = project.get_project_graph().await;
+        self.check_cancelled(&mut sdk_xml_handle).await?;
+        let stats = stats.map_err(|err| {
             error!("{:?}", err);
             Status::new(tonic::Code::Internal, "failed")
         })?;
@@ -255,7 +655,9 @@ impl ProviderService for CSharpProvider {
         let get_deps_handle = project.resolve();
 
         // Await dependency resolution
-        let res = match get_deps_handle.await {
+        let res = get_deps_handle.await;
+        self.check_cancelled(&mut sdk_xml_handle).await?;
+        let res = match res {
             Ok(res) => res,
             Err(e) => {
                 debug!("unable to get deps: {}", e);
@@ -265,7 +667,7 @@ impl ProviderService for CSharpProvider {
         debug!("got task result: {:?} -- project: {:?}", res, project);
 
         // Await SDK XML loading if it was spawned
-        if let Some(handle) = sdk_xml_handle {
+        if let Some(handle) = sdk_xml_handle.take() {
             match handle.await {
                 Ok(Ok(count)) => {
                     info!("Successfully loaded {} SDK XML files into database", count);
@@ -282,6 +684,7 @@ impl ProviderService for CSharpProvider {
 
         info!("adding depdencies to stack graph database");
         let res = project.load_to_database().await;
+        self.check_cancelled(&mut sdk_xml_handle).await?;
         debug!(
             "loading project to database: {:?} -- project: {:?}",
             res, project
@@ -297,132 +700,221 @@ impl ProviderService for CSharpProvider {
 
     async fn prepare(
         &self,
-        _r: Request<PrepareRequest>,
+        r: Request<PrepareRequest>,
     ) -> Result<Response<PrepareResponse>, Status> {
-        Result::Ok(Response::new(PrepareResponse {
-            error: String::new(),
-        }))
+        let _work = self.begin_work().await?;
+        let _operation = self.progress.begin();
+        self.progress.update(ProgressKind::Prepare, 0, 0);
+        let patterns = prepare_patterns(&r.get_ref().conditions);
+        let project = self.project.lock().await.clone();
+        let Some(project) = project else {
+            return Ok(Response::new(PrepareResponse {
+                error: "project may not be initialized".to_string(),
+            }));
+        };
+        info!("preparing {} referenced patterns", patterns.len());
+        let progress = self.progress.clone();
+        let prepared = tokio::task::spawn_blocking(move || {
+            project.prepare_searches(patterns, |patterns_processed, total_patterns| {
+                progress.update(ProgressKind::Prepare, patterns_processed, total_patterns)
+            })
+        })
+        .await;
+        let error = match prepared {
+            Ok(Ok(added)) => {
+                info!("prepared {} patterns", added);
+                String::new()
+            }
+            Ok(Err(e)) => e.to_string(),
+            Err(e) => e.to_string(),
+        };
+        Ok(Response::new(PrepareResponse { error }))
     }
 
     async fn stream_prepare_progress(
         &self,
         _r: Request<PrepareProgressRequest>,
     ) -> Result<Response<Self::StreamPrepareProgressStream>, Status> {
-        let (tx, rx) = tokio::sync::mpsc::channel(32);
-
-        // Send a single progress event and close the stream
-        tokio::spawn(async move {
-            if let Err(e) = tx
-                .send(Ok(ProgressEvent {
-                    r#type: ProgressEventType::Prepare as i32,
-                    provider_name: "c-sharp".to_string(),
-                    files_processed: 0,
-                    total_files: 0,
-                }))
-                .await
-            {
-                error!(
-                    "Failed to send Prepare progress event for c-sharp provider: {:?}",
-                    e
-                );
-            }
-        });
-
-        Ok(Response::new(ReceiverStream::new(rx)))
+        Ok(Response::new(self.progress.stream()))
     }
 
     async fn evaluate(
         &self,
         r: Request<EvaluateRequest>,
     ) -> Result<Response<EvaluateResponse>, Status> {
+        let _work = self.begin_work().await?;
         info!("request: {:?}", r);
         let evaluate_request = r.get_ref();
         debug!("evaluate request: {:?}", evaluate_request.condition_info);
-
-        if evaluate_request.cap != "referenced" {
-            return Ok(Response::new(EvaluateResponse {
-                error: "unable to find referenced capability".to_string(),
-                successful: false,
-                response: None,
-            }));
-        }
-        let condition: CSharpCondition =
-            serde_yml::from_str(evaluate_request.condition_info.as_str()).map_err(|err| {
-                error!("{:?}", err);
-                Status::new(tonic::Code::Internal, "failed")
-            })?;
-
-        debug!("condition: {:?}", condition);
-        let project_guard = self.project.lock().await;
-        let project = match project_guard.as_ref() {
-            Some(x) => x,
-            None => {
-                return Ok(Response::new(EvaluateResponse {
-                    error: "project may not be initialized".to_string(),
-                    successful: false,
-                    response: None,
-                }));
+        let timeout = request_timeout(r.metadata());
+
+        let results = match evaluate_request.cap.as_str() {
+            "referenced" => {
+                let condition: CSharpCondition = serde_yml::from_str(
+                    evaluate_request.condition_info.as_str(),
+                )
+                .map_err(|err| {
+                    error!("{:?}", err);
+                    Status::new(tonic::Code::Internal, "failed")
+                })?;
+                debug!("condition: {:?}", condition);
+                self.query_graph(timeout, move |snapshot, source_type, options| {
+                    let query = condition.referenced.location.query_type(
+                        &snapshot.graph,
+                        source_type,
+                        options,
+                    );
+                    let results = query.query(condition.referenced.pattern.clone())?;
+                    Ok(results
+                        .into_iter()
+                        .filter(|r| condition.referenced.kind.matches(r))
+                        .filter(|r| {
+                            condition
+                                .referenced
+                                .file_paths
+                                .as_ref()
+                                .is_none_or(|paths| in_file_paths(r, paths))
+                        })
+                        .collect())
+                })
+                .await
             }
-        };
-        let graph_guard = project.graph.clone();
-
-        let source_type = match project.get_source_type().await {
-            Some(s) => s,
-            None => {
-                return Ok(Response::new(EvaluateResponse {
-                    error: "project may not be initialized".to_string(),
-                    successful: false,
-                    response: None,
-                }));
+            "calls" => {
+                let condition: CSharpCallsCondition = serde_yml::from_str(
+                    evaluate_request.condition_info.as_str(),
+                )
+                .map_err(|err| {
+                    error!("{:?}", err);
+                    Status::new(tonic::Code::Internal, "failed")
+                })?;
+                debug!("condition: {:?}", condition);
+                self.query_graph(timeout, move |snapshot, source_type, options| {
+                    let call_graph = snapshot.call_graph(source_type, &options)?;
+                    let query = CallsQuery {
+                        call_graph: &call_graph,
+                        target: condition.calls.location.query_type(
+                            &snapshot.graph,
+                            source_type,
+                            options,
+                        ),
+                        callers: condition.calls.callers.clone(),
+                        max_depth: condition.calls.max_depth,
+                    };
+                    query.query(condition.calls.pattern.clone())
+                })
+                .await
             }
-        };
-        // Release the project lock, so other evaluate calls can continue
-        drop(project_guard);
-        let graph = graph_guard.lock();
-        let graph_option = match graph {
-            Ok(g) => g,
-            Err(e) => {
-                graph_guard.clear_poison();
-                e.into_inner()
+            "literal" => {
+                let condition: CSharpLiteralCondition = serde_yml::from_str(
+                    evaluate_request.condition_info.as_str(),
+                )
+                .map_err(|err| {
+                    error!("{:?}", err);
+                    Status::new(tonic::Code::Internal, "failed")
+                })?;
+                debug!("condition: {:?}", condition);
+                self.query_graph(timeout, move |snapshot, source_type, options| {
+                    let graph = &snapshot.graph;
+                    let query = LiteralQuery {
+                        graph,
+                        source_type,
+                        argument_of: condition.literal.argument_of.clone().map(|pattern| {
+                            ArgumentOf {
+                                target: condition.literal.location.query_type(
+                                    graph,
+                                    source_type,
+                                    options,
+                                ),
+                                pattern,
+                            }
+                        }),
+                    };
+                    query.query(condition.literal.pattern.clone())
+                })
+                .await
             }
-        };
-
-        let graph = match graph_option.as_ref() {
-            Some(g) => g,
-            None => {
-                // Graph was invalidated (e.g., by notify_file_changes) and needs to be rebuilt.
-                // Return an error so the client knows to re-initialize the provider.
+            "config" => {
+                let condition: CSharpConfigCondition = serde_yml::from_str(
+                    evaluate_request.condition_info.as_str(),
+                )
+                .map_err(|err| {
+                    error!("{:?}", err);
+                    Status::new(tonic::Code::Internal, "failed")
+                })?;
+                debug!("condition: {:?}", condition);
+                self.query_project_files(|location| {
+                    let query = ConfigQuery {
+                        location,
+                        value: condition.config.value.clone(),
+                        file_pattern: condition.config.file_pattern.clone(),
+                    };
+                    query.query(condition.config.xpath.clone())
+                })
+                .await
+            }
+            "project" => {
+                let condition: CSharpProjectCondition = serde_yml::from_str(
+                    evaluate_request.condition_info.as_str(),
+                )
+                .map_err(|err| {
+                    error!("{:?}", err);
+                    Status::new(tonic::Code::Internal, "failed")
+                })?;
+                debug!("condition: {:?}", condition);
+                self.query_project_files(|location| {
+                    let query = ProjectQuery {
+                        location,
+                        value: condition.project.value.clone(),
+                        version: condition.project.version.clone(),
+                    };
+                    query.query(condition.project.pattern.clone())
+                })
+                .await
+            }
+            "markup" => {
+                let condition: CSharpMarkupCondition = serde_yml::from_str(
+                    evaluate_request.condition_info.as_str(),
+                )
+                .map_err(|err| {
+                    error!("{:?}", err);
+                    Status::new(tonic::Code::Internal, "failed")
+                })?;
+                debug!("condition: {:?}", condition);
+                self.query_project_files(|location| {
+                    let query = MarkupQuery {
+                        location,
+                        element: condition.markup.element.clone(),
+                    };
+                    query.query(condition.markup.pattern.clone())
+                })
+                .await
+            }
+            cap => {
                 return Ok(Response::new(EvaluateResponse {
-                    error: "No project initialized. Graph cache was invalidated.".to_string(),
+                    error: format!("unable to find {} capability", cap),
                     successful: false,
                     response: None,
                 }));
             }
         };
-
-        // As we are passing an unmutable reference, we can drop the guard here.
-
-        let query = match condition.referenced.location {
-            Locations::All => QueryType::All {
-                graph,
-                source_type: &source_type,
-            },
-            Locations::Method => QueryType::Method {
-                graph,
-                source_type: &source_type,
-            },
-            Locations::Field => QueryType::Field {
-                graph,
-                source_type: &source_type,
-            },
-            Locations::Class => QueryType::Class {
-                graph,
-                source_type: &source_type,
-            },
+        let results = match results {
+            Err(response) => return Ok(Response::new(response)),
+            Ok(results) => results,
         };
-        let results = query.query(condition.referenced.pattern.clone());
+        let (preprocessor, suppressions, location) = match self.project.lock().await.as_ref() {
+            Some(project) => (
+                project.get_preprocessor(),
+                project.get_suppressions(),
+                Some(project.location.clone()),
+            ),
+            None => (None, None, None),
+        };
+        let condition = &evaluate_request.condition_info;
         let results = match results {
             Err(e) => {
+                if let Some(status) = interrupted_status(&e) {
+                    return Err(status);
+                }
                 if let Some(_e) = e.downcast_ref::<NotFoundError>() {
                     EvaluateResponse {
                         error: String::new(),
@@ -441,11 +933,25 @@ impl ProviderService for CSharpProvider {
                     }
                 }
             }
-            Ok(res) => {
-                // Deduplicate: group by file+line and keep the one with smallest span
-                let new_results = deduplicate_results(&res);
+            Ok(mut res) => {
+                if let Some(preprocessor) = preprocessor {
+                    preprocessor.tag_results(&mut res);
+                }
                 info!("found {} results for search: {:?}", res.len(), &condition);
-                let mut i: Vec<IncidentContext> = new_results.into_iter().map(Into::into).collect();
+                // Deduplicate: group by file+line and keep the one with smallest span
+                let mut new_results: Vec<ResultNode> =
+                    deduplicate_results(&res).into_iter().cloned().collect();
+                // The fingerprints are computed on the incidents that are returned, the baseline
+                // suppressions match them.
+                if let Some(location) = location {
+                    add_fingerprints(&mut new_results, &location);
+                }
+                // The rule ID is not part of an evaluate request, so comments that only name a
+                // rule do not suppress anything here. The `analyze` command applies them.
+                if let Some(suppressions) = suppressions {
+                    suppressions.apply(&mut new_results, None);
+                }
+                let mut i: Vec<IncidentContext> = new_results.iter().map(Into::into).collect();
                 i.sort_by_key(|i| format!("{}-{:?}", i.file_uri, i.line_number()));
 
                 // Log detailed results for debugging non-determinism
@@ -489,7 +995,8 @@ impl ProviderService for CSharpProvider {
     }
 
     async fn stop(&self, _: Request<ServiceRequest>) -> Result<Response<()>, Status> {
-        return Ok(Response::new(()));
+        self.shutdown().await;
+        Ok(Response::new(()))
     }
 
     async fn get_dependencies(
@@ -518,6 +1025,7 @@ impl ProviderService for CSharpProvider {
         &self,
         request: Request<NotifyFileChangesRequest>,
     ) -> Result<Response<NotifyFileChangesResponse>, Status> {
+        let _work = self.begin_work().await?;
         let changes = &request.get_ref().changes;
 
         debug!("notify_file_changes called with {} changes", changes.len());
@@ -533,7 +1041,12 @@ impl ProviderService for CSharpProvider {
         // Get the changed C# file paths, converting file:// URIs to filesystem paths
         let csharp_file_paths: Vec<PathBuf> = changes
             .iter()
-            .filter(|change| change.uri.ends_with(".cs") || change.uri.ends_with(".csproj"))
+            .filter(|change| {
+                change.uri.ends_with(".cs")
+                    || change.uri.ends_with(".csproj")
+                    || RazorFileAnalyzer::matches(Path::new(&change.uri))
+                    || WebFormsFileAnalyzer::matches(Path::new(&change.uri))
+            })
             .filter_map(|change| {
                 // Parse the URI and convert to filesystem path
                 match Url::parse(&change.uri) {
@@ -546,7 +1059,7 @@ impl ProviderService for CSharpProvider {
             })
             .collect();
 
-        if csharp_file_paths.len() == 0 {
+        if csharp_file_paths.is_empty() {
             info!("No C# file changes detected, skipping graph invalidation");
             return Ok(Response::new(NotifyFileChangesResponse {
                 error: String::new(),
@@ -558,9 +1071,9 @@ impl ProviderService for CSharpProvider {
             csharp_file_paths.len(),
         );
 
-        // Incrementally update the graph for changed files
-        let project_guard = self.project.lock().await;
-        let project = match project_guard.as_ref() {
+        // Incrementally update the graph for changed files. The project lock is only held to
+        // clone the project, evaluations take it too and run on the previous graph meanwhile.
+        let project = match self.project.lock().await.as_ref() {
             Some(p) => p.clone(),
             None => {
                 warn!("No project initialized, cannot update graph");
@@ -622,6 +1135,7 @@ impl ProviderService for CSharpProvider {
                 &language_config.language_config,
                 &source_type,
                 &mut db,
+                &project.cancellation,
             ) {
                 Ok(Some(_file_handle)) => {
                     debug!("    SUCCESS reloaded and stored file: {:?}", file_path);
@@ -634,8 +1148,18 @@ impl ProviderService for CSharpProvider {
                 }
             }
         }
+        drop(db);
+        drop(lc_guard);
+
+        #[cfg(test)]
+        if let Some(barrier) = &self.reload_barrier {
+            // Once to tell the test the reload is held, and once more to be released.
+            barrier.wait().await;
+            barrier.wait().await;
+        }
 
-        // Reload the graph from the database (now without the cleaned files)
+        // Reload the graph from the database (now without the cleaned files), it is published
+        // once it is built.
         info!("reloading graph from database");
         match project.get_project_graph().await {
             Ok(file_count) => {
@@ -654,6 +1178,36 @@ impl ProviderService for CSharpProvider {
     }
 }
 
+/// The referenced patterns of the conditions, along with the patterns that calls and literal
+/// conditions resolve the same way. Only the pattern is read, the rest of a condition may still
+/// hold the templates of a chained condition.
+fn prepare_patterns(conditions: &[ConditionsByCapability]) -> Vec<String> {
+    let mut patterns = BTreeSet::new();
+    for by_capability in conditions {
+        let cap = by_capability.cap.as_str();
+        let field = match cap {
+            "referenced" | "calls" => "pattern",
+            "literal" => "argument_of",
+            _ => continue,
+        };
+        for condition_info in &by_capability.condition_info {
+            match serde_yml::from_str::<serde_yml::Value>(condition_info) {
+                Ok(condition) => {
+                    if let Some(pattern) = condition
+                        .get(cap)
+                        .and_then(|c| c.get(field))
+                        .and_then(|p| p.as_str())
+                    {
+                        patterns.insert(pattern.to_string());
+                    }
+                }
+                Err(e) => debug!("unable to parse {} condition to prepare: {}", cap, e),
+            }
+        }
+    }
+    patterns.into_iter().collect()
+}
+
 /// Deduplicate results by grouping by (file_uri, line_number) and keeping the result
 /// with the smallest span. When spans are equal, prefer earlier start character and
 /// earlier end character for deterministic selection.
@@ -976,4 +1530,384 @@ mod tests {
         assert!(deduplicated.iter().any(|r| r.line_number == 180));
         assert!(deduplicated.iter().any(|r| r.line_number == 181));
     }
+
+    #[test]
+    fn test_condition_property_and_event_locations() {
+        let condition: super::CSharpCondition = serde_yml::from_str(
+            r#"{"referenced": {"pattern": "System.Web.HttpContext.Current", "location": "PROPERTY"}}"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            condition.referenced.location,
+            super::Locations::Property
+        ));
+
+        let condition: super::CSharpCondition = serde_yml::from_str(
+            r#"{"referenced": {"pattern": "System.Web.HttpApplication.BeginRequest", "location": "EVENT"}}"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            condition.referenced.location,
+            super::Locations::Event
+        ));
+    }
+
+    #[test]
+    fn test_calls_condition() {
+        let condition: super::CSharpCallsCondition = serde_yml::from_str(
+            r#"{"calls": {"pattern": "System.Web.HttpContext.Current", "callers": "NerdDinner.Controllers.*", "max_depth": 3}}"#,
+        )
+        .unwrap();
+        assert!(matches!(condition.calls.location, super::Locations::All));
+        assert_eq!(
+            condition.calls.callers,
+            Some("NerdDinner.Controllers.*".to_string())
+        );
+        assert_eq!(condition.calls.max_depth, Some(3));
+    }
+
+    #[test]
+    fn test_condition_kind() {
+        let condition: super::CSharpCondition =
+            serde_yml::from_str(r#"{"referenced": {"pattern": "*Controller", "location": "CLASS", "kind": "declaration"}}"#)
+                .unwrap();
+        assert_eq!(condition.referenced.kind, super::Kind::Declaration);
+
+        let condition: super::CSharpCondition =
+            serde_yml::from_str(r#"{"referenced": {"pattern": "System.Web.Mvc.Controller"}}"#)
+                .unwrap();
+        assert_eq!(condition.referenced.kind, super::Kind::Any);
+
+        let mut declaration = create_result_node("file1.cs", 10, 10, 0, 10, 5);
+        declaration
+            .variables
+            .insert("kind".to_string(), serde_json::Value::from("declaration"));
+        let mut reference = create_result_node("file1.cs", 12, 12, 0, 12, 5);
+        reference
+            .variables
+            .insert("kind".to_string(), serde_json::Value::from("reference"));
+
+        assert!(super::Kind::Declaration.matches(&declaration));
+        assert!(!super::Kind::Declaration.matches(&reference));
+        assert!(super::Kind::Reference.matches(&reference));
+        assert!(!super::Kind::Reference.matches(&declaration));
+        assert!(super::Kind::Any.matches(&declaration));
+        assert!(super::Kind::Any.matches(&reference));
+    }
+
+    #[test]
+    fn test_in_file_paths() {
+        let result = create_result_node("file:///src/app/Controllers/Home.cs", 1, 1, 0, 1, 5);
+        let paths = |paths: &[&str]| paths.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+        assert!(super::in_file_paths(
+            &result,
+            &paths(&["/src/app/Controllers/Home.cs"])
+        ));
+        assert!(super::in_file_paths(
+            &result,
+            &paths(&["/src/app/Controllers"])
+        ));
+        assert!(super::in_file_paths(
+            &result,
+            &paths(&["Controllers/Home.cs"])
+        ));
+        assert!(!super::in_file_paths(
+            &result,
+            &paths(&["Models", "/src/app/Home.cs"])
+        ));
+        assert!(!super::in_file_paths(&result, &[]));
+    }
+
+    #[test]
+    fn test_prepare_patterns() {
+        let conditions = |cap: &str, infos: &[&str]| super::ConditionsByCapability {
+            cap: cap.to_string(),
+            condition_info: infos.iter().map(|i| i.to_string()).collect(),
+        };
+        let patterns = super::prepare_patterns(&[
+            conditions(
+                "referenced",
+                &[
+                    r#"{"referenced": {"pattern": "System.Web.HttpContext", "location": "CLASS"}}"#,
+                    r#"{"referenced": {"pattern": "System.Web.HttpContext", "file_paths": "{{web.filepaths}}"}}"#,
+                    "not: [a condition",
+                ],
+            ),
+            conditions(
+                "calls",
+                &[r#"{"calls": {"pattern": "System.Web.Mvc.Controller.*"}}"#],
+            ),
+            conditions(
+                "literal",
+                &[
+                    r#"{"literal": {"pattern": "^Default$", "argument_of": "System.Configuration.*"}}"#,
+                    r#"{"literal": {"pattern": "^Default$"}}"#,
+                ],
+            ),
+            conditions("config", &[r#"{"config": {"xpath": "//system.web"}}"#]),
+        ]);
+        assert_eq!(
+            patterns,
+            vec![
+                "System.Configuration.*",
+                "System.Web.HttpContext",
+                "System.Web.Mvc.Controller.*",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_literal_condition() {
+        let condition: super::CSharpLiteralCondition = serde_yml::from_str(
+            r#"{"literal": {"pattern": "^DefaultConnection$", "argument_of": "System.Configuration.ConfigurationManager.ConnectionStrings", "location": "PROPERTY"}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            condition.literal.argument_of,
+            Some("System.Configuration.ConfigurationManager.ConnectionStrings".to_string())
+        );
+        assert!(matches!(
+            condition.literal.location,
+            super::Locations::Property
+        ));
+
+        let condition: super::CSharpLiteralCondition =
+            serde_yml::from_str(r#"{"literal": {"pattern": "^https?://"}}"#).unwrap();
+        assert!(condition.literal.argument_of.is_none());
+    }
+
+    #[test]
+    fn test_config_condition() {
+        let condition: super::CSharpConfigCondition = serde_yml::from_str(
+            r#"{"config": {"xpath": "//system.web/httpModules/add", "file_pattern": "(?i)^web\\..*config$"}}"#,
+        )
+        .unwrap();
+        assert_eq!(condition.config.xpath, "//system.web/httpModules/add");
+        assert!(condition.config.value.is_none());
+        assert!(condition.config.file_pattern.is_some());
+    }
+
+    #[test]
+    fn test_project_condition() {
+        let condition: super::CSharpProjectCondition = serde_yml::from_str(
+            r#"{"project": {"pattern": "^PackageReference$", "value": "^Newtonsoft\\.Json$", "version": "^(9|10|11)\\."}}"#,
+        )
+        .unwrap();
+        assert_eq!(condition.project.pattern, "^PackageReference$");
+        assert_eq!(
+            condition.project.value,
+            Some("^Newtonsoft\\.Json$".to_string())
+        );
+        assert_eq!(condition.project.version, Some("^(9|10|11)\\.".to_string()));
+    }
+
+    #[test]
+    fn test_markup_condition() {
+        let condition: super::CSharpMarkupCondition = serde_yml::from_str(
+            r#"{"markup": {"pattern": "^System\\.Web\\.UI\\.WebControls\\.", "element": "^asp:"}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            condition.markup.pattern,
+            "^System\\.Web\\.UI\\.WebControls\\."
+        );
+        assert_eq!(condition.markup.element, Some("^asp:".to_string()));
+    }
+
+    /// A provider with a project of the given source files loaded, the way init loads it but
+    /// without resolving the dependencies.
+    async fn provider_for_sources(
+        sources: &[(&str, &str)],
+    ) -> (super::CSharpProvider, tempfile::TempDir) {
+        use super::{AnalysisMode, Arc, CSharpProvider, PathBuf, Project, Suppressions};
+        use crate::provider::project::Tools;
+
+        let dir = tempfile::tempdir().unwrap();
+        let location = dir.path().join("project");
+        std::fs::create_dir(&location).unwrap();
+        for (name, source) in sources {
+            std::fs::write(location.join(name), source).unwrap();
+        }
+        let provider = CSharpProvider::new(Some(dir.path().join("graph.db")), 0, 1);
+        let project = Arc::new(Project::new(
+            location.clone(),
+            dir.path().join("graph.db"),
+            AnalysisMode::SourceOnly,
+            Tools {
+                ilspy_cmd: PathBuf::new(),
+                paket_cmd: PathBuf::new(),
+                dotnet_install_cmd: None,
+                dotnet_sdk_path: None,
+            },
+            1,
+            provider.progress.clone(),
+            provider.cancellation.clone(),
+        ));
+        project.validate_language_configuration().await.unwrap();
+        project.get_project_graph().await.unwrap();
+        project.set_suppressions(Suppressions::new(&location, &None));
+        provider.project.lock().await.replace(project);
+        (provider, dir)
+    }
+
+    /// The lines of the incidents of a referenced condition.
+    async fn evaluate_lines(provider: &super::CSharpProvider, condition_info: &str) -> Vec<i64> {
+        use super::{EvaluateRequest, ProviderService, Request};
+
+        let response = provider
+            .evaluate(Request::new(EvaluateRequest {
+                id: 0,
+                cap: "referenced".to_string(),
+                condition_info: condition_info.to_string(),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(response.successful, "{}", response.error);
+        response
+            .response
+            .map(|r| {
+                r.incident_contexts
+                    .iter()
+                    .filter_map(|i| i.line_number)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    #[tokio::test]
+    async fn test_evaluate_suppressions_without_rule_id() {
+        let source = r#"using Demo;
+
+namespace Demo
+{
+    public class Settings
+    {
+        public static void Load() { }
+    }
+}
+
+namespace Demo.App
+{
+    public class Startup
+    {
+        public void Run()
+        {
+            Settings.Load(); // konveyor-ignore: demo-00010
+            Settings.Load(); // konveyor-ignore: Demo.Settings.*
+            Settings.Load(); // konveyor-ignore:
+            Settings.Load();
+        }
+    }
+}
+"#;
+        let line_of = |text: &str| {
+            source
+                .lines()
+                .position(|l| l.ends_with(text))
+                .map(|l| l as i64)
+                .unwrap()
+        };
+        let (provider, _dir) = provider_for_sources(&[("Startup.cs", source)]).await;
+        let lines = evaluate_lines(
+            &provider,
+            r#"{"referenced": {"pattern": "Demo.Settings.Load", "location": "METHOD", "kind": "reference"}}"#,
+        )
+        .await;
+
+        // The rule ID is not known over gRPC, only the FQDN pattern and the bare comment apply.
+        assert!(lines.contains(&line_of("demo-00010")));
+        assert!(!lines.contains(&line_of("Demo.Settings.*")));
+        assert!(!lines.contains(&line_of("konveyor-ignore:")));
+        assert!(lines.contains(&line_of("Settings.Load();")));
+    }
+
+    #[tokio::test]
+    async fn test_query_graph_deadline_exceeded() {
+        use super::{interrupted_status, request_timeout, Request};
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::sync::Arc;
+
+        let (provider, _dir) = provider_for_sources(&[(
+            "Settings.cs",
+            "namespace Demo { public class Settings { public void Load() { } } }",
+        )])
+        .await;
+        let mut request = Request::new(());
+        request
+            .metadata_mut()
+            .insert("grpc-timeout", "50m".parse().unwrap());
+
+        // The query only returns once it is cancelled, which happens when the deadline passes.
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let query_cancelled = cancelled.clone();
+        let results = provider
+            .query_graph(request_timeout(request.metadata()), move |_, _, options| {
+                while options.check_cancelled("test").is_ok() {
+                    std::thread::sleep(std::time::Duration::from_millis(1));
+                }
+                query_cancelled.store(true, Ordering::SeqCst);
+                Ok(vec![])
+            })
+            .await
+            .unwrap();
+        let status = interrupted_status(&results.unwrap_err()).unwrap();
+        assert_eq!(status.code(), tonic::Code::DeadlineExceeded);
+
+        // The blocking thread of the query sees the cancellation and ends.
+        while !cancelled.load(Ordering::SeqCst) {
+            tokio::time::sleep(std::time::Duration::from_millis(1)).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_evaluate_during_notify_file_changes() {
+        use super::{NotifyFileChangesRequest, ProviderService, Request};
+        use crate::analyzer_service::FileChange;
+        use std::sync::Arc;
+        use std::time::Duration;
+
+        let (mut provider, dir) = provider_for_sources(&[(
+            "Settings.cs",
+            "namespace Demo { public class Settings { public void Load() { } } }",
+        )])
+        .await;
+        let barrier = Arc::new(tokio::sync::Barrier::new(2));
+        provider.reload_barrier = Some(barrier.clone());
+        let provider = Arc::new(provider);
+        let path = dir.path().join("project").join("Settings.cs");
+        std::fs::write(
+            &path,
+            "namespace Demo { public class Options { public void Load() { } } }",
+        )
+        .unwrap();
+
+        let notifying = provider.clone();
+        let notify = tokio::spawn(async move {
+            let changes = vec![FileChange {
+                uri: path.display().to_string(),
+                content: String::new(),
+                saved: true,
+            }];
+            notifying
+                .notify_file_changes(Request::new(NotifyFileChangesRequest { changes, id: 0 }))
+                .await
+        });
+        barrier.wait().await;
+
+        // The reload is held, the evaluation runs on the previous graph without waiting for it.
+        let settings = r#"{"referenced": {"pattern": "Demo.Settings", "location": "CLASS"}}"#;
+        let options = r#"{"referenced": {"pattern": "Demo.Options", "location": "CLASS"}}"#;
+        let lines =
+            tokio::time::timeout(Duration::from_secs(30), evaluate_lines(&provider, settings))
+                .await
+                .expect("the evaluation waited for the reload");
+        assert_eq!(lines, vec![0]);
+
+        barrier.wait().await;
+        let response = notify.await.unwrap().unwrap().into_inner();
+        assert_eq!(response.error, "");
+        assert_eq!(evaluate_lines(&provider, settings).await, Vec::<i64>::new());
+        assert_eq!(evaluate_lines(&provider, options).await, vec![0]);
+    }
 }
@@ -15,7 +15,6 @@ use stack_graphs::stitching::ForwardPartialPathStitcher;
 use stack_graphs::stitching::StitcherConfig;
 use stack_graphs::storage::SQLiteReader;
 use stack_graphs::storage::SQLiteWriter;
-use stack_graphs::NoCancellation;
 use tokio::fs::{self, File};
 use tokio::io::{AsyncBufReadExt, BufReader};
 use tokio::task::JoinSet;
@@ -27,6 +26,7 @@ use crate::c_sharp_graph::language_config::SourceNodeLanguageConfiguration;
 use crate::c_sharp_graph::loader::add_dir_to_graph;
 use crate::c_sharp_graph::loader::AsyncInitializeGraph;
 use crate::c_sharp_graph::loader::SourceType;
+use crate::provider::progress::ProgressKind;
 use crate::provider::project::Tools;
 use crate::provider::target_framework::TargetFrameworkHelper;
 use crate::provider::AnalysisMode;
@@ -307,7 +307,17 @@ impl Project {
             }
             // reset deps, as all the deps should be moved into the threads.
             let mut deps = vec![];
-            while let Some(res) = set.join_next().await {
+            loop {
+                let res = tokio::select! {
+                    res = set.join_next() => res,
+                    _ = self.cancellation.cancelled() => {
+                        set.shutdown().await;
+                        return Err(anyhow!("dependency resolution was cancelled"));
+                    }
+                };
+                let Some(res) = res else {
+                    break;
+                };
                 match res {
                     std::result::Result::Ok(d) => {
                         deps.push(d);
@@ -333,15 +343,28 @@ impl Project {
     }
 
     pub async fn load_to_database(&self) -> Result<(), Error> {
-        let set = if self.analysis_mode == AnalysisMode::Full {
+        let mut set = if self.analysis_mode == AnalysisMode::Full {
             self.load_to_database_full_analysis().await?
         } else {
             self.load_to_database_source_only().await?
         };
-        for res in set.join_all().await {
+        let total = set.len();
+        let mut processed = 0;
+        self.progress
+            .update(ProgressKind::IndexDependencies, processed, total);
+        while let Some(res) = set.join_next().await {
+            processed += 1;
+            self.progress
+                .update(ProgressKind::IndexDependencies, processed, total);
+            let res = match res {
+                std::result::Result::Ok(res) => res,
+                Err(e) => Err(anyhow!(e)),
+            };
             let (init_graph, dep_name) = match res {
                 std::result::Result::Ok((i, dep_name)) => (i, dep_name),
                 Err(e) => {
+                    // Wait for the other tasks, so none is left writing to the database.
+                    set.shutdown().await;
                     return Err(anyhow!(
                         "unable to get graph, project may not have been initialized: {}",
                         e
@@ -353,15 +376,11 @@ impl Project {
                 init_graph.files_loaded, dep_name
             );
         }
-        let mut graph_guard = self
-            .graph
-            .lock()
-            .expect("project may not have been initialized");
         info!("adding all dependency and source to graph");
         let mut db_reader = SQLiteReader::open(&self.db_path)?;
         // Load graphs from project location
         info!("Loading project graphs from: {:?}", &self.location);
-        db_reader.load_graphs_for_file_or_directory(&self.location, &NoCancellation)?;
+        db_reader.load_graphs_for_file_or_directory(&self.location, &self.cancellation)?;
 
         // Also load SDK XML files if target framework is set
         if let Some(sdk_path) = self.get_sdk_path() {
@@ -370,7 +389,7 @@ impl Project {
             if sdk_path.exists() {
                 info!("Loading SDK graphs from: {:?}", sdk_path);
                 if let Err(e) =
-                    db_reader.load_graphs_for_file_or_directory(&sdk_path, &NoCancellation)
+                    db_reader.load_graphs_for_file_or_directory(&sdk_path, &self.cancellation)
                 {
                     error!("Failed to load SDK graphs: {}", e);
                 } else {
@@ -394,7 +413,7 @@ impl Project {
             "new graph: {:?}",
             databse.to_serializable(&new_graph, partials)
         );
-        let _ = graph_guard.insert(new_graph);
+        self.publish_graph(new_graph);
         Ok(())
     }
 
@@ -420,6 +439,7 @@ impl Project {
                     // Use new type of loader, to handle this.
                     let db_path = self.db_path.clone();
                     let dep_name = d.name.clone();
+                    let cancellation = self.cancellation.clone();
                     set.spawn(async move {
                         info!(
                             "indexing dep: {} with xml file: {:?} into a graph",
@@ -448,6 +468,7 @@ impl Project {
                             &source_lc.dependnecy_type_node_info,
                             &source_lc.language_config,
                             graph,
+                            &cancellation,
                         )?;
                         let root_node = graph.stack_graph.iter_nodes().find(|n| {
                             let node = &graph.stack_graph[*n];
@@ -483,7 +504,7 @@ impl Project {
                                     &mut partials,
                                     file_handle,
                                     StitcherConfig::default().with_collect_stats(true),
-                                    &NoCancellation,
+                                    &cancellation,
                                     |_, _, p| paths.push(p.clone()),
                                 )?;
                             db.store_result_for_file(
@@ -527,6 +548,7 @@ impl Project {
                     let lc = self.source_language_config.clone();
                     let db_path = self.db_path.clone();
                     let dep_name = d.name.clone();
+                    let cancellation = self.cancellation.clone();
                     set.spawn(async move {
                         info!(
                             "indexing dep: {} with size: {} into a graph",
@@ -551,6 +573,7 @@ impl Project {
                             &lc.dependnecy_type_node_info,
                             &lc.language_config,
                             graph,
+                            &cancellation,
                         )?;
                         drop(lc_guard);
                         let mut db: SQLiteWriter = SQLiteWriter::open(db_path)?;
@@ -568,7 +591,7 @@ impl Project {
                                     &mut partials,
                                     file_handle,
                                     StitcherConfig::default().with_collect_stats(true),
-                                    &NoCancellation,
+                                    &cancellation,
                                     |_, _, p| paths.push(p.clone()),
                                 )?;
                             db.store_result_for_file(
@@ -768,13 +791,19 @@ impl Project {
         let mut success_count = 0;
         let mut combined_file_to_tag = std::collections::HashMap::new();
 
-        for file in &valid_files {
+        for (processed, file) in valid_files.iter().enumerate() {
+            if self.cancellation.is_cancelled() {
+                return Err(anyhow!("loading the SDK was cancelled"));
+            }
+            self.progress
+                .update(ProgressKind::LoadSdk, processed, valid_files.len());
             info!("Indexing SDK XML file: {:?} into graph", file);
             match add_dir_to_graph(
                 file,
                 &source_lc.dependnecy_type_node_info,
                 &source_lc.language_config,
                 current_graph,
+                &self.cancellation,
             ) {
                 std::result::Result::Ok(result) => {
                     success_count += result.files_loaded;
@@ -836,7 +865,7 @@ impl Project {
                 &mut partials,
                 file_handle,
                 StitcherConfig::default().with_collect_stats(true),
-                &NoCancellation,
+                &self.cancellation,
                 |_, _, p| paths.push(p.clone()),
             )?;
 
@@ -850,6 +879,8 @@ impl Project {
             );
         }
 
+        self.progress
+            .update(ProgressKind::LoadSdk, valid_files.len(), valid_files.len());
         info!(
             "SDK XML loading complete: {} files successfully processed",
             success_count
@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde_json::Value;
+use sha1::{Digest, Sha1};
+use url::Url;
+
+use crate::c_sharp_graph::results::ResultNode;
+
+pub(crate) const FINGERPRINT_VARIABLE: &str = "fingerprint";
+
+/// Add a `fingerprint` variable to every result that does not change with edits elsewhere in
+/// the file. It is a hash of the project relative path, the resolved symbol, the enclosing
+/// declaration and the source text of the incident, along with the index of the incident among
+/// the ones that share all of these.
+pub(crate) fn add_fingerprints(results: &mut [ResultNode], root: &Path) {
+    let mut sources: HashMap<String, Option<String>> = HashMap::new();
+    let mut order: Vec<usize> = (0..results.len()).collect();
+    order.sort_by(|a, b| {
+        let (a, b) = (&results[*a], &results[*b]);
+        (&a.file_uri, &a.code_location).cmp(&(&b.file_uri, &b.code_location))
+    });
+    let mut occurrences: HashMap<String, usize> = HashMap::new();
+    for i in order {
+        let result = &results[i];
+        let source = sources
+            .entry(result.file_uri.clone())
+            .or_insert_with(|| read_source(&result.file_uri));
+        let key = fingerprint_key(result, root, source.as_deref());
+        let occurrence = occurrences.entry(key.clone()).or_insert(0);
+        let fingerprint = hash(&format!("{}\n{}", key, occurrence));
+        *occurrence += 1;
+        results[i]
+            .variables
+            .insert(FINGERPRINT_VARIABLE.to_string(), Value::from(fingerprint));
+    }
+}
+
+fn read_source(file_uri: &str) -> Option<String> {
+    let path = Url::parse(file_uri).ok()?.to_file_path().ok()?;
+    std::fs::read_to_string(path).ok()
+}
+
+fn fingerprint_key(result: &ResultNode, root: &Path, source: Option<&str>) -> String {
+    let variable = |name: &str| {
+        result
+            .variables
+            .get(name)
+            .and_then(|v| v.as_str())
+            .unwrap_or_default()
+    };
+    [
+        relative_path(&result.file_uri, root),
+        result.get_fqdn_symbol(),
+        variable("syntax_type").to_string(),
+        variable("enclosing_namespace").to_string(),
+        variable("enclosing_type").to_string(),
+        variable("enclosing_member").to_string(),
+        source.map(|s| span_text(s, result)).unwrap_or_default(),
+    ]
+    .join("\n")
+}
+
+/// The path of the file relative to the project, with `/` separators so the fingerprint is the
+/// same wherever the project is checked out.
+fn relative_path(file_uri: &str, root: &Path) -> String {
+    let Some(path) = Url::parse(file_uri)
+        .ok()
+        .and_then(|u| u.to_file_path().ok())
+    else {
+        return file_uri.to_string();
+    };
+    let path = path.strip_prefix(root).unwrap_or(&path);
+    path.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// The source text of the first line of the incident with the whitespace collapsed, the rest of
+/// a declaration such as a class body is left out.
+fn span_text(source: &str, result: &ResultNode) -> String {
+    let start = &result.code_location.start_position;
+    let end = &result.code_location.end_position;
+    let Some(line) = source.lines().nth(start.line) else {
+        return String::new();
+    };
+    let text = if end.line == start.line {
+        line.get(start.character..end.character)
+    } else {
+        line.get(start.character..)
+    };
+    text.unwrap_or(line)
+        .trim_start_matches('\u{feff}')
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn hash(key: &str) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(key);
+    hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use crate::c_sharp_graph::results::{Location, Position};
+
+    fn result(line: usize, start: usize, end: usize, symbol: &str) -> ResultNode {
+        let mut variables = BTreeMap::new();
+        variables.insert("symbol".to_string(), Value::from(symbol));
+        variables.insert("enclosing_member".to_string(), Value::from("Index"));
+        ResultNode {
+            file_uri: String::new(),
+            line_number: line,
+            variables,
+            code_location: Location {
+                start_position: Position {
+                    line,
+                    character: start,
+                },
+                end_position: Position {
+                    line,
+                    character: end,
+                },
+            },
+        }
+    }
+
+    fn fingerprints(source: &str, results: Vec<ResultNode>) -> Vec<String> {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("Home.cs");
+        std::fs::write(&file, source).unwrap();
+        let mut results: Vec<ResultNode> = results
+            .into_iter()
+            .map(|mut r| {
+                r.file_uri = Url::from_file_path(&file).unwrap().to_string();
+                r
+            })
+            .collect();
+        add_fingerprints(&mut results, dir.path());
+        results
+            .iter()
+            .map(|r| {
+                r.variables[FINGERPRINT_VARIABLE]
+                    .as_str()
+                    .unwrap()
+                    .to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_fingerprints_survive_edits_above() {
+        let before = fingerprints(
+            "a.Run();\n    a.Run();\n",
+            vec![result(0, 0, 5, "a.Run"), result(1, 4, 9, "a.Run")],
+        );
+        let after = fingerprints(
+            "// added\n\na.Run();\n    a.Run();\n",
+            vec![result(2, 0, 5, "a.Run"), result(3, 4, 9, "a.Run")],
+        );
+        assert_eq!(before, after);
+        // The same code twice is told apart by the occurrence.
+        assert_ne!(before[0], before[1]);
+    }
+
+    #[test]
+    fn test_relative_path() {
+        assert_eq!(
+            relative_path("file:///src/app/Controllers/Home.cs", Path::new("/src/app")),
+            "Controllers/Home.cs"
+        );
+    }
+}
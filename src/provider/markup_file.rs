@@ -0,0 +1,199 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Error};
+use regex::Regex;
+use serde_json::Value;
+use tracing::{debug, info, warn};
+use url::Url;
+use walkdir::WalkDir;
+
+use crate::c_sharp_graph::query::Query;
+use crate::c_sharp_graph::results::{Location, ResultNode};
+use crate::c_sharp_graph::web_forms_analyzer::{
+    MarkupElement, MarkupType, WebFormsFileAnalyzer, WebFormsPage,
+};
+use crate::provider::config_file::is_skipped;
+use crate::provider::xml_query::LineIndex;
+
+/// Queries the directives and server controls of the Web Forms pages of a project. The query is
+/// a regex on the referenced type, e.g. `^System\.Web\.UI\.WebControls\.GridView$`, or on the
+/// namespace for `Import` and `Register` directives.
+pub(crate) struct MarkupQuery {
+    pub(crate) location: PathBuf,
+    /// Regex on the directive name or the control tag, e.g. `^Page$` or `^asp:`.
+    pub(crate) element: Option<String>,
+}
+
+impl Query for MarkupQuery {
+    fn query(self, query: String) -> anyhow::Result<Vec<ResultNode>, Error> {
+        let reference = Regex::new(&query)?;
+        let element = self.element.as_deref().map(Regex::new).transpose()?;
+
+        let mut results = vec![];
+        for file in find_page_files(&self.location) {
+            let content = match std::fs::read_to_string(&file) {
+                Ok(c) => c,
+                Err(e) => {
+                    warn!("skipping page {:?}: {}", file, e);
+                    continue;
+                }
+            };
+            let content = content.strip_prefix('\u{feff}').unwrap_or(&content);
+            let page = WebFormsPage::parse(content);
+            let lines = LineIndex::new(content);
+            let file_uri = Url::from_file_path(&file)
+                .map_err(|_| anyhow!("unable to get the file url for file: {:?}", file))?
+                .to_string();
+            for mut markup in page.elements {
+                if element.as_ref().is_some_and(|e| !e.is_match(&markup.name)) {
+                    continue;
+                }
+                if markup.reference.is_none() {
+                    markup.reference = markup
+                        .user_control
+                        .as_deref()
+                        .and_then(|src| user_control_type(&self.location, &file, src));
+                }
+                if !markup
+                    .reference
+                    .as_deref()
+                    .is_some_and(|r| reference.is_match(r))
+                {
+                    continue;
+                }
+                results.push(to_result_node(&file_uri, &markup, &lines));
+            }
+        }
+        results.sort();
+        info!("found {} markup results for '{}'", results.len(), query);
+        Ok(results)
+    }
+}
+
+fn to_result_node(file_uri: &str, markup: &MarkupElement, lines: &LineIndex) -> ResultNode {
+    let mut var: BTreeMap<String, Value> = BTreeMap::new();
+    var.insert("file".to_string(), Value::from(file_uri));
+    var.insert(
+        "markup_type".to_string(),
+        Value::from(markup.markup_type.as_str()),
+    );
+    var.insert("element".to_string(), Value::from(markup.name.clone()));
+    if !markup.attributes.is_empty() {
+        let attributes: serde_json::Map<String, Value> = markup
+            .attributes
+            .iter()
+            .map(|(k, v)| (k.clone(), Value::from(v.clone())))
+            .collect();
+        var.insert("attributes".to_string(), Value::Object(attributes));
+    }
+    if let Some(reference) = &markup.reference {
+        var.insert("reference".to_string(), Value::from(reference.clone()));
+    }
+    let code_location = Location {
+        start_position: lines.position(markup.span.start),
+        end_position: lines.position(markup.span.end),
+    };
+    ResultNode {
+        file_uri: file_uri.to_string(),
+        line_number: code_location.start_position.line,
+        variables: var,
+        code_location,
+    }
+}
+
+fn find_page_files(location: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = WalkDir::new(location)
+        .into_iter()
+        .filter_entry(|e| !is_skipped(e))
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .map(|e| e.into_path())
+        .filter(|p| WebFormsFileAnalyzer::matches(p))
+        .collect();
+    files.sort();
+    debug!("found page files: {:?}", files);
+    files
+}
+
+/// The type of a user control is the `Inherits` of its `Control` directive. `~/` paths start at
+/// the application root, the closest directory of the page with a `web.config`.
+fn user_control_type(location: &Path, page: &Path, src: &str) -> Option<String> {
+    let page_dir = page.parent()?;
+    let path = match src.strip_prefix("~/") {
+        Some(rooted) => page_dir
+            .ancestors()
+            .take_while(|d| d.starts_with(location))
+            .find(|d| d.join("web.config").exists() || d.join("Web.config").exists())
+            .unwrap_or(location)
+            .join(rooted),
+        None => page_dir.join(src),
+    };
+    let content = std::fs::read_to_string(&path).ok()?;
+    WebFormsPage::parse(&content)
+        .elements
+        .into_iter()
+        .find(|e| e.markup_type == MarkupType::Directive && e.name.eq_ignore_ascii_case("Control"))
+        .and_then(|e| e.reference)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_markup_query() {
+        let dir = tempfile::tempdir().unwrap();
+        let location = dir.path().to_path_buf();
+        let controls = location.join("Controls");
+        std::fs::create_dir_all(&controls).unwrap();
+        std::fs::write(location.join("web.config"), "<configuration />").unwrap();
+        std::fs::write(
+            controls.join("Map.ascx"),
+            r#"<%@ Control Language="C#" Inherits="NerdDinner.Controls.Map" %>"#,
+        )
+        .unwrap();
+        std::fs::write(
+            location.join("Default.aspx"),
+            r#"<%@ Page Language="C#" Inherits="NerdDinner._Default" %>
+<%@ Register TagPrefix="uc" TagName="Map" Src="~/Controls/Map.ascx" %>
+<form runat="server">
+    <asp:GridView ID="dinners" runat="server" />
+    <uc:Map ID="map" runat="server" />
+</form>"#,
+        )
+        .unwrap();
+
+        let query = MarkupQuery {
+            location: location.clone(),
+            element: None,
+        };
+        let results = query
+            .query("^System\\.Web\\.UI\\.WebControls\\.GridView$".to_string())
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert!(results[0].file_uri.ends_with("Default.aspx"));
+        assert_eq!(results[0].line_number, 3);
+        assert_eq!(results[0].code_location.start_position.character, 4);
+        assert_eq!(results[0].variables["markup_type"], Value::from("control"));
+        assert_eq!(results[0].variables["element"], Value::from("asp:GridView"));
+
+        let query = MarkupQuery {
+            location: location.clone(),
+            element: Some("^uc:".to_string()),
+        };
+        let results = query.query("^NerdDinner\\.".to_string()).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(
+            results[0].variables["reference"],
+            Value::from("NerdDinner.Controls.Map")
+        );
+
+        let query = MarkupQuery {
+            location: location.clone(),
+            element: Some("^(Page|Control)$".to_string()),
+        };
+        let results = query.query("^NerdDinner\\.".to_string()).unwrap();
+        assert_eq!(results.len(), 2);
+    }
+}
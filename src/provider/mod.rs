@@ -1,9 +1,19 @@
+pub mod cache;
+pub(crate) mod cancellation;
 mod code_snip;
+mod config_file;
 mod csharp;
 mod dependency_resolution;
+pub(crate) mod fingerprint;
+mod markup_file;
+mod preprocessor;
+pub(crate) mod progress;
 mod project;
+mod project_file;
 pub(crate) mod sdk_detection;
+mod suppression;
 pub(crate) mod target_framework;
+mod xml_query;
 
 pub use csharp::CSharpProvider;
 pub use project::AnalysisMode;
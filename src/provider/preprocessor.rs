@@ -0,0 +1,611 @@
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+
+use prost_types::{value::Kind, Struct};
+use serde_json::Value;
+use tracing::{debug, info, warn};
+use url::Url;
+
+use crate::c_sharp_graph::results::ResultNode;
+use crate::provider::target_framework::{TargetFramework, TargetFrameworkHelper};
+use crate::provider::xml_query::XmlElement;
+
+const SYMBOLS_KEY: &str = "preprocessor_symbols";
+const DROP_INACTIVE_KEY: &str = "drop_inactive_code";
+const PREPROCESSOR_VARIABLE: &str = "preprocessor";
+
+const NET_FRAMEWORK_VERSIONS: &[&str] = &[
+    "20", "35", "40", "45", "451", "452", "46", "461", "462", "47", "471", "472", "48", "481",
+];
+const NET_CORE_VERSIONS: &[&str] = &["1.0", "1.1", "2.0", "2.1", "2.2", "3.0", "3.1"];
+const NET_STANDARD_VERSIONS: &[&str] = &[
+    "1.0", "1.1", "1.2", "1.3", "1.4", "1.5", "1.6", "2.0", "2.1",
+];
+
+/// The preprocessor settings from the provider specific config.
+#[derive(Debug, Default)]
+pub(crate) struct PreprocessorSettings {
+    /// Replaces the symbols of the projects, from `preprocessor_symbols`.
+    pub(crate) symbols: Option<BTreeSet<String>>,
+    /// Drop the incidents in inactive code instead of tagging them, from `drop_inactive_code`.
+    pub(crate) drop_inactive: bool,
+}
+
+impl PreprocessorSettings {
+    pub(crate) fn from_config(config: &Option<Struct>) -> PreprocessorSettings {
+        let Some(config) = config else {
+            return PreprocessorSettings::default();
+        };
+        let symbols = match config.fields.get(SYMBOLS_KEY).and_then(|v| v.kind.as_ref()) {
+            Some(Kind::StringValue(s)) => Some(split_symbols(s)),
+            Some(Kind::ListValue(list)) => Some(
+                list.values
+                    .iter()
+                    .filter_map(|v| match &v.kind {
+                        Some(Kind::StringValue(s)) => Some(s.trim().to_string()),
+                        _ => None,
+                    })
+                    .filter(|s| !s.is_empty())
+                    .collect(),
+            ),
+            None => None,
+            Some(_) => {
+                warn!(
+                    "Invalid {} configuration, using the symbols of the projects",
+                    SYMBOLS_KEY
+                );
+                None
+            }
+        };
+        let drop_inactive = match config
+            .fields
+            .get(DROP_INACTIVE_KEY)
+            .and_then(|v| v.kind.as_ref())
+        {
+            Some(Kind::BoolValue(b)) => *b,
+            Some(Kind::StringValue(s)) => s.eq_ignore_ascii_case("true"),
+            _ => false,
+        };
+        PreprocessorSettings {
+            symbols,
+            drop_inactive,
+        }
+    }
+}
+
+/// Decides which code is compiled for the configuration being migrated. The symbols of a file
+/// are the `DefineConstants` of its project and the implicit symbols of the target framework,
+/// unless they are set in the provider specific config.
+#[derive(Debug, Default)]
+pub(crate) struct Preprocessor {
+    /// The symbols of each project directory, the deepest directory comes first.
+    projects: Vec<(PathBuf, BTreeSet<String>)>,
+    /// The symbols of files outside of any project.
+    default: BTreeSet<String>,
+    drop_inactive: bool,
+}
+
+impl Preprocessor {
+    pub(crate) fn new(location: &Path, settings: PreprocessorSettings) -> Preprocessor {
+        let drop_inactive = settings.drop_inactive;
+        if let Some(symbols) = settings.symbols {
+            info!("Using configured preprocessor symbols: {:?}", symbols);
+            return Preprocessor {
+                projects: vec![],
+                default: symbols,
+                drop_inactive,
+            };
+        }
+
+        let files =
+            TargetFrameworkHelper::find_csproj_files(&location.to_path_buf()).unwrap_or_default();
+        let mut projects = vec![];
+        let mut frameworks = vec![];
+        for file in files {
+            let document = match XmlElement::from_file(&file) {
+                Ok(d) => d,
+                Err(e) => {
+                    warn!("skipping project file {:?}: {}", file, e);
+                    continue;
+                }
+            };
+            let mut symbols = define_constants(&document);
+            if let Some(framework) = project_framework(&document) {
+                symbols.extend(implicit_symbols(&framework));
+                frameworks.push(framework);
+            }
+            debug!("preprocessor symbols for {:?}: {:?}", file, symbols);
+            if let Some(dir) = file.parent() {
+                projects.push((dir.to_path_buf(), symbols));
+            }
+        }
+        projects.sort_by_key(|(dir, _)| std::cmp::Reverse(dir.components().count()));
+        frameworks.sort();
+        Preprocessor {
+            projects,
+            default: frameworks.first().map(implicit_symbols).unwrap_or_default(),
+            drop_inactive,
+        }
+    }
+
+    fn symbols(&self, file: &Path) -> &BTreeSet<String> {
+        self.projects
+            .iter()
+            .find(|(dir, _)| file.starts_with(dir))
+            .map_or(&self.default, |(_, symbols)| symbols)
+    }
+
+    /// Tag every result with whether it is in `active` or `inactive` code, results outside of
+    /// C# files are always active. Inactive results are removed when configured to drop them.
+    pub(crate) fn tag_results(&self, results: &mut Vec<ResultNode>) {
+        let mut files: HashMap<String, Option<Vec<bool>>> = HashMap::new();
+        for result in results.iter_mut() {
+            let inactive = files
+                .entry(result.file_uri.clone())
+                .or_insert_with(|| self.inactive_lines_of(&result.file_uri));
+            let is_inactive = inactive
+                .as_ref()
+                .and_then(|lines| lines.get(result.code_location.start_position.line))
+                .copied()
+                .unwrap_or(false);
+            result.variables.insert(
+                PREPROCESSOR_VARIABLE.to_string(),
+                Value::from(if is_inactive { "inactive" } else { "active" }),
+            );
+        }
+        if self.drop_inactive {
+            let inactive = Value::from("inactive");
+            results.retain(|r| r.variables.get(PREPROCESSOR_VARIABLE) != Some(&inactive));
+        }
+    }
+
+    fn inactive_lines_of(&self, file_uri: &str) -> Option<Vec<bool>> {
+        let path = Url::parse(file_uri).ok()?.to_file_path().ok()?;
+        if !matches!(path.extension(), Some(e) if e == "cs") {
+            return None;
+        }
+        let source = std::fs::read_to_string(&path).ok()?;
+        Some(inactive_lines(&source, self.symbols(&path)))
+    }
+}
+
+fn split_symbols(value: &str) -> BTreeSet<String> {
+    value
+        .split([';', ','])
+        .map(str::trim)
+        // `$(DefineConstants)` keeps the symbols of the SDK, which are added separately.
+        .filter(|s| !s.is_empty() && !s.starts_with("$("))
+        .map(String::from)
+        .collect()
+}
+
+/// The properties of a project that apply to a release build, properties that only apply to
+/// other configurations are skipped.
+fn release_properties<'a>(element: &'a XmlElement, found: &mut Vec<&'a XmlElement>) {
+    for child in element.children.iter() {
+        if child.name != "PropertyGroup" {
+            release_properties(child, found);
+            continue;
+        }
+        let release = child
+            .attributes
+            .get("Condition")
+            .is_none_or(|c| c.to_ascii_lowercase().contains("release"));
+        if release {
+            found.extend(child.children.iter());
+        }
+    }
+}
+
+fn define_constants(document: &XmlElement) -> BTreeSet<String> {
+    let mut properties = vec![];
+    release_properties(document, &mut properties);
+    let mut symbols: BTreeSet<String> = properties
+        .iter()
+        .filter(|p| p.name == "DefineConstants")
+        .flat_map(|p| split_symbols(&p.text))
+        .collect();
+    // SDK style projects define these for release builds without listing them.
+    let sdk_style = document
+        .children
+        .iter()
+        .any(|p| p.name == "Project" && p.attributes.contains_key("Sdk"));
+    if sdk_style {
+        symbols.insert("TRACE".to_string());
+        symbols.insert("RELEASE".to_string());
+    }
+    symbols
+}
+
+/// The earliest target framework of a project, a project that targets several frameworks is
+/// migrated from the oldest one.
+fn project_framework(document: &XmlElement) -> Option<TargetFramework> {
+    let mut properties = vec![];
+    release_properties(document, &mut properties);
+    let mut frameworks: Vec<TargetFramework> = properties
+        .iter()
+        .filter(|p| {
+            matches!(
+                p.name.as_str(),
+                "TargetFramework" | "TargetFrameworks" | "TargetFrameworkVersion"
+            )
+        })
+        .flat_map(|p| p.text.split(';'))
+        .filter(|tfm| !tfm.trim().is_empty())
+        .filter_map(|tfm| TargetFramework::from_str(tfm).ok())
+        .collect();
+    frameworks.sort();
+    frameworks.into_iter().next()
+}
+
+/// The symbols the SDK defines for a target framework.
+/// See: https://learn.microsoft.com/en-us/dotnet/csharp/language-reference/preprocessor-directives
+pub(crate) fn implicit_symbols(framework: &TargetFramework) -> BTreeSet<String> {
+    let tfm = framework.as_str();
+    let mut symbols = BTreeSet::new();
+    if let Some(version) = tfm.strip_prefix("netstandard") {
+        symbols.insert("NETSTANDARD".to_string());
+        versioned("NETSTANDARD", version, NET_STANDARD_VERSIONS, &mut symbols);
+    } else if let Some(version) = tfm.strip_prefix("netcoreapp") {
+        symbols.insert("NETCOREAPP".to_string());
+        versioned("NETCOREAPP", version, NET_CORE_VERSIONS, &mut symbols);
+    } else if let Some(version) = tfm.strip_prefix("net") {
+        if version.contains('.') {
+            // .NET 5 and later are also .NET Core.
+            symbols.insert("NET".to_string());
+            symbols.insert("NETCOREAPP".to_string());
+            for core in NET_CORE_VERSIONS {
+                symbols.insert(format!("NETCOREAPP{}_OR_GREATER", core.replace('.', "_")));
+            }
+            symbols.insert(format!("NET{}", version.replace('.', "_")));
+            let major = version
+                .split('.')
+                .next()
+                .and_then(|m| m.parse::<u32>().ok())
+                .unwrap_or(5);
+            for m in 5..=major {
+                symbols.insert(format!("NET{}_0_OR_GREATER", m));
+            }
+        } else {
+            symbols.insert("NETFRAMEWORK".to_string());
+            versioned("NET", version, NET_FRAMEWORK_VERSIONS, &mut symbols);
+        }
+    }
+    symbols
+}
+
+/// Add the symbol of the version and the `_OR_GREATER` symbols of it and every earlier version.
+fn versioned(prefix: &str, version: &str, known: &[&str], symbols: &mut BTreeSet<String>) {
+    let name = |v: &str| format!("{}{}", prefix, v.replace('.', "_"));
+    symbols.insert(name(version));
+    match known.iter().position(|k| *k == version) {
+        Some(i) => {
+            for k in &known[..=i] {
+                symbols.insert(format!("{}_OR_GREATER", name(k)));
+            }
+        }
+        None => {
+            symbols.insert(format!("{}_OR_GREATER", name(version)));
+        }
+    }
+}
+
+struct Branch {
+    /// Whether the code around the `#if` is active.
+    parent: bool,
+    /// Whether one of the branches so far was taken.
+    taken: bool,
+}
+
+/// Evaluate the conditional directives of a C# file, returns whether each line is excluded
+/// from the build. `#define` and `#undef` only change the symbols of the file.
+pub(crate) fn inactive_lines(source: &str, symbols: &BTreeSet<String>) -> Vec<bool> {
+    let mut defined = symbols.clone();
+    let mut stack: Vec<Branch> = vec![];
+    let mut active = true;
+    let mut lines = vec![];
+    for line in source.lines() {
+        let Some(directive) = line.trim_start().strip_prefix('#') else {
+            lines.push(!active);
+            continue;
+        };
+        let directive = directive.trim_start();
+        let keyword_end = directive
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(directive.len());
+        let (keyword, rest) = directive.split_at(keyword_end);
+        let rest = rest.split("//").next().unwrap_or_default().trim();
+        match keyword {
+            "if" => {
+                lines.push(!active);
+                let taken = active && evaluate(rest, &defined);
+                stack.push(Branch {
+                    parent: active,
+                    taken,
+                });
+                active = taken;
+            }
+            "elif" | "else" => match stack.last_mut() {
+                Some(branch) => {
+                    lines.push(!branch.parent);
+                    let condition = keyword == "else" || evaluate(rest, &defined);
+                    active = branch.parent && !branch.taken && condition;
+                    branch.taken |= active;
+                }
+                None => lines.push(!active),
+            },
+            "endif" => {
+                if let Some(branch) = stack.pop() {
+                    active = branch.parent;
+                }
+                lines.push(!active);
+            }
+            "define" if active => {
+                defined.insert(rest.to_string());
+                lines.push(false);
+            }
+            "undef" if active => {
+                defined.remove(rest);
+                lines.push(false);
+            }
+            _ => lines.push(!active),
+        }
+    }
+    lines
+}
+
+#[derive(Debug, PartialEq)]
+enum Token<'a> {
+    Symbol(&'a str),
+    Not,
+    And,
+    Or,
+    Equal,
+    NotEqual,
+    Open,
+    Close,
+}
+
+/// Evaluate an `#if` expression, an expression that can not be parsed counts as true so that no
+/// code is hidden by mistake.
+fn evaluate(expression: &str, symbols: &BTreeSet<String>) -> bool {
+    let Some(tokens) = tokenize(expression) else {
+        debug!("unable to parse preprocessor expression: {}", expression);
+        return true;
+    };
+    let mut parser = ExpressionParser {
+        tokens,
+        pos: 0,
+        symbols,
+    };
+    match parser.or() {
+        Some(value) if parser.pos == parser.tokens.len() => value,
+        _ => {
+            debug!("unable to parse preprocessor expression: {}", expression);
+            true
+        }
+    }
+}
+
+fn tokenize(expression: &str) -> Option<Vec<Token<'_>>> {
+    let mut tokens = vec![];
+    let mut rest = expression.trim_start();
+    while !rest.is_empty() {
+        let (token, len) = if rest.starts_with("&&") {
+            (Token::And, 2)
+        } else if rest.starts_with("||") {
+            (Token::Or, 2)
+        } else if rest.starts_with("==") {
+            (Token::Equal, 2)
+        } else if rest.starts_with("!=") {
+            (Token::NotEqual, 2)
+        } else if rest.starts_with('!') {
+            (Token::Not, 1)
+        } else if rest.starts_with('(') {
+            (Token::Open, 1)
+        } else if rest.starts_with(')') {
+            (Token::Close, 1)
+        } else {
+            let len = rest
+                .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            if len == 0 {
+                return None;
+            }
+            (Token::Symbol(&rest[..len]), len)
+        };
+        tokens.push(token);
+        rest = rest[len..].trim_start();
+    }
+    Some(tokens)
+}
+
+struct ExpressionParser<'a, 'b> {
+    tokens: Vec<Token<'a>>,
+    pos: usize,
+    symbols: &'b BTreeSet<String>,
+}
+
+impl ExpressionParser<'_, '_> {
+    fn next_if(&mut self, token: Token) -> bool {
+        if self.tokens.get(self.pos) == Some(&token) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn or(&mut self) -> Option<bool> {
+        let mut value = self.and()?;
+        while self.next_if(Token::Or) {
+            value |= self.and()?;
+        }
+        Some(value)
+    }
+
+    fn and(&mut self) -> Option<bool> {
+        let mut value = self.equality()?;
+        while self.next_if(Token::And) {
+            value &= self.equality()?;
+        }
+        Some(value)
+    }
+
+    fn equality(&mut self) -> Option<bool> {
+        let mut value = self.unary()?;
+        loop {
+            if self.next_if(Token::Equal) {
+                value = value == self.unary()?;
+            } else if self.next_if(Token::NotEqual) {
+                value = value != self.unary()?;
+            } else {
+                return Some(value);
+            }
+        }
+    }
+
+    fn unary(&mut self) -> Option<bool> {
+        if self.next_if(Token::Not) {
+            return Some(!self.unary()?);
+        }
+        if self.next_if(Token::Open) {
+            let value = self.or()?;
+            return self.next_if(Token::Close).then_some(value);
+        }
+        match self.tokens.get(self.pos)? {
+            Token::Symbol(symbol) => {
+                self.pos += 1;
+                Some(match *symbol {
+                    "true" => true,
+                    "false" => false,
+                    s => self.symbols.contains(s),
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn symbols(names: &[&str]) -> BTreeSet<String> {
+        names.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_inactive_lines() {
+        let source = r#"#define LEGACY
+using System;
+#if NET48 && !NETCOREAPP
+using System.Web;
+#elif NET8_0_OR_GREATER
+using Microsoft.AspNetCore.Http;
+#else
+using Other;
+#endif
+#if (LEGACY || DEBUG) == true
+class A {}
+  #if false // nested
+class B {}
+  #endif
+#endif
+#undef LEGACY
+#if LEGACY
+class C {}
+#endif
+#if ???
+class D {}
+#endif"#;
+        let lines = inactive_lines(source, &symbols(&["NET48", "NETFRAMEWORK"]));
+        let inactive: Vec<usize> = (0..lines.len()).filter(|i| lines[*i]).collect();
+        assert_eq!(inactive, vec![5, 7, 12, 17]);
+
+        let lines = inactive_lines(source, &symbols(&["NET8_0_OR_GREATER", "NETCOREAPP"]));
+        let inactive: Vec<usize> = (0..lines.len()).filter(|i| lines[*i]).collect();
+        assert_eq!(inactive, vec![3, 7, 12, 17]);
+    }
+
+    #[test]
+    fn test_implicit_symbols() {
+        let net48 = implicit_symbols(&TargetFramework::from_str("v4.8").unwrap());
+        assert!(net48.contains("NETFRAMEWORK"));
+        assert!(net48.contains("NET48"));
+        assert!(net48.contains("NET472_OR_GREATER"));
+        assert!(!net48.contains("NET481_OR_GREATER"));
+        assert!(!net48.contains("NETCOREAPP"));
+
+        let net8 = implicit_symbols(&TargetFramework::from_str("net8.0-windows").unwrap());
+        assert!(net8.contains("NET"));
+        assert!(net8.contains("NET8_0"));
+        assert!(net8.contains("NET6_0_OR_GREATER"));
+        assert!(net8.contains("NETCOREAPP3_1_OR_GREATER"));
+        assert!(!net8.contains("NET9_0_OR_GREATER"));
+
+        let standard = implicit_symbols(&TargetFramework::from_str("netstandard2.0").unwrap());
+        assert!(standard.contains("NETSTANDARD2_0"));
+        assert!(standard.contains("NETSTANDARD1_6_OR_GREATER"));
+        assert!(!standard.contains("NETSTANDARD2_1_OR_GREATER"));
+    }
+
+    #[test]
+    fn test_tag_results() {
+        let dir = tempfile::tempdir().unwrap();
+        let location = dir.path().to_path_buf();
+        let legacy = location.join("Legacy");
+        std::fs::create_dir_all(&legacy).unwrap();
+        std::fs::write(
+            legacy.join("Legacy.csproj"),
+            r#"<Project ToolsVersion="15.0">
+  <PropertyGroup>
+    <TargetFrameworkVersion>v4.7.2</TargetFrameworkVersion>
+  </PropertyGroup>
+  <PropertyGroup Condition=" '$(Configuration)|$(Platform)' == 'Debug|AnyCPU' ">
+    <DefineConstants>DEBUG;TRACE</DefineConstants>
+  </PropertyGroup>
+  <PropertyGroup Condition=" '$(Configuration)|$(Platform)' == 'Release|AnyCPU' ">
+    <DefineConstants>TRACE;LEGACY_AUTH</DefineConstants>
+  </PropertyGroup>
+</Project>"#,
+        )
+        .unwrap();
+        let file = legacy.join("Startup.cs");
+        std::fs::write(
+            &file,
+            "#if NETFRAMEWORK && LEGACY_AUTH\nA();\n#endif\n#if DEBUG || NETCOREAPP\nB();\n#endif\n",
+        )
+        .unwrap();
+        let file_uri = Url::from_file_path(&file).unwrap().to_string();
+        let result = |line: usize| {
+            let mut result = ResultNode {
+                file_uri: file_uri.clone(),
+                line_number: line,
+                variables: Default::default(),
+                code_location: Default::default(),
+            };
+            result.code_location.start_position.line = line;
+            result
+        };
+
+        let preprocessor = Preprocessor::new(&location, PreprocessorSettings::default());
+        let mut results = vec![result(1), result(4)];
+        preprocessor.tag_results(&mut results);
+        assert_eq!(results[0].variables["preprocessor"], Value::from("active"));
+        assert_eq!(
+            results[1].variables["preprocessor"],
+            Value::from("inactive")
+        );
+
+        let settings = PreprocessorSettings {
+            symbols: Some(symbols(&["NETCOREAPP"])),
+            drop_inactive: true,
+        };
+        let preprocessor = Preprocessor::new(&location, settings);
+        let mut results = vec![result(1), result(4)];
+        preprocessor.tag_results(&mut results);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].line_number, 4);
+    }
+}
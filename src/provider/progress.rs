@@ -0,0 +1,180 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use tokio::sync::{mpsc, watch};
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::Status;
+use tracing::{debug, error};
+
+use crate::analyzer_service::{ProgressEvent, ProgressEventType};
+
+const PROVIDER_NAME: &str = "c-sharp";
+
+/// The work the provider reports progress for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum ProgressKind {
+    /// Building the graph of the source files of the project during init.
+    IndexSource,
+    /// Loading the XML documentation of the SDK of the target framework during init.
+    LoadSdk,
+    /// Loading the dependencies into the database during init.
+    IndexDependencies,
+    /// Computing the searches of the conditions sent to prepare.
+    Prepare,
+}
+
+/// The analyzer only knows `Prepare` events, every kind of work is reported as one.
+impl From<ProgressKind> for ProgressEventType {
+    fn from(_kind: ProgressKind) -> Self {
+        ProgressEventType::Prepare
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+struct ProgressState {
+    /// The files processed and the total files of each kind of work of the operation, init
+    /// loads the SDK while the source is indexed.
+    counts: BTreeMap<ProgressKind, (usize, usize)>,
+    last: Option<ProgressKind>,
+    running: bool,
+    /// A stream opened after a prepare is done gets its final counts, instead of waiting for
+    /// the next operation.
+    prepared: bool,
+}
+
+impl ProgressState {
+    fn event(&self) -> ProgressEvent {
+        let (files_processed, total_files) = self
+            .counts
+            .values()
+            .fold((0, 0), |(p, t), (processed, total)| {
+                (p + processed, t + total)
+            });
+        ProgressEvent {
+            r#type: ProgressEventType::from(self.last.unwrap_or(ProgressKind::Prepare)) as i32,
+            provider_name: PROVIDER_NAME.to_string(),
+            files_processed: files_processed as i32,
+            total_files: total_files as i32,
+        }
+    }
+}
+
+/// The progress of the running init or prepare, shared with the `StreamPrepareProgress`
+/// streams. Clones report to the same streams.
+#[derive(Clone, Debug)]
+pub struct Progress {
+    state: Arc<watch::Sender<ProgressState>>,
+}
+
+/// Finishes the operation when dropped, so every way out of init or prepare closes the streams.
+pub(crate) struct Operation<'a> {
+    progress: &'a Progress,
+}
+
+impl Drop for Operation<'_> {
+    fn drop(&mut self) {
+        self.progress.state.send_modify(|state| {
+            state.running = false;
+            state.prepared = state.counts.contains_key(&ProgressKind::Prepare);
+        });
+    }
+}
+
+impl Progress {
+    pub(crate) fn new() -> Progress {
+        Progress {
+            state: Arc::new(watch::Sender::new(ProgressState::default())),
+        }
+    }
+
+    /// Start an operation, the counts of the previous one are cleared.
+    pub(crate) fn begin(&self) -> Operation<'_> {
+        self.state.send_replace(ProgressState {
+            running: true,
+            ..Default::default()
+        });
+        Operation { progress: self }
+    }
+
+    pub(crate) fn update(&self, kind: ProgressKind, files_processed: usize, total_files: usize) {
+        debug!("{:?} progress: {}/{}", kind, files_processed, total_files);
+        self.state.send_modify(|state| {
+            state.counts.insert(kind, (files_processed, total_files));
+            state.last = Some(kind);
+        });
+    }
+
+    /// Stream the events of the running operation, or of the next one when none is running,
+    /// and close once it is done.
+    pub(crate) fn stream(&self) -> ReceiverStream<Result<ProgressEvent, Status>> {
+        let (tx, rx) = mpsc::channel(32);
+        let mut state = self.state.subscribe();
+        // Read before the task runs, an operation that ends before then is still reported.
+        let mut seen_running = state.borrow().running;
+        tokio::spawn(async move {
+            loop {
+                let current = state.borrow_and_update().clone();
+                seen_running |= current.running;
+                let reported = seen_running || current.prepared;
+                if reported {
+                    if let Err(e) = tx.send(Ok(current.event())).await {
+                        error!(
+                            "Failed to send Prepare progress event for c-sharp provider: {:?}",
+                            e
+                        );
+                        return;
+                    }
+                }
+                if (reported && !current.running) || state.changed().await.is_err() {
+                    return;
+                }
+            }
+        });
+        ReceiverStream::new(rx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio_stream::StreamExt;
+
+    use super::*;
+
+    #[test]
+    fn test_stream() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let progress = Progress::new();
+            let during_init = {
+                let _operation = progress.begin();
+                let stream = progress.stream();
+                progress.update(ProgressKind::IndexSource, 10, 40);
+                progress.update(ProgressKind::LoadSdk, 5, 5);
+                stream
+            };
+            let events: Vec<ProgressEvent> = during_init.map(|e| e.unwrap()).collect().await;
+            let last = events.last().unwrap();
+            assert_eq!((last.files_processed, last.total_files), (15, 45));
+            assert_eq!(last.r#type, ProgressEventType::Prepare as i32);
+
+            // After init a stream waits for the prepare.
+            let before_prepare = progress.stream();
+            {
+                let _operation = progress.begin();
+                progress.update(ProgressKind::Prepare, 54, 54);
+            }
+            let events: Vec<ProgressEvent> = before_prepare.map(|e| e.unwrap()).collect().await;
+            let last = events.last().unwrap();
+            assert_eq!((last.files_processed, last.total_files), (54, 54));
+
+            // After a prepare the final counts are sent right away.
+            let after_prepare: Vec<ProgressEvent> =
+                progress.stream().map(|e| e.unwrap()).collect().await;
+            assert_eq!(after_prepare.len(), 1);
+            assert_eq!(after_prepare[0].files_processed, 54);
+        });
+    }
+}
@@ -10,27 +10,96 @@ use prost_types::{Struct, Value};
 use serde::Deserialize;
 use stack_graphs::{
     graph::StackGraph, serde::StackGraph as serialize_stack_graph, stitching::ForwardCandidates,
-    storage::SQLiteReader, NoCancellation,
+    storage::SQLiteReader,
 };
 use tokio::sync::{Mutex as TokioMutex, RwLock};
 use tracing::{debug, info, warn};
 use which::which;
 
+use crate::c_sharp_graph::call_graph::CallGraph;
 use crate::c_sharp_graph::language_config::SourceNodeLanguageConfiguration;
-use crate::c_sharp_graph::loader::{init_stack_graph, SourceType};
+use crate::c_sharp_graph::loader::{init_stack_graph, update_stack_graph, SourceType};
+use crate::c_sharp_graph::query::{prepare_searches, PreparedSearches, QueryOptions};
+use crate::c_sharp_graph::symbol_index::SymbolIndex;
+use crate::provider::cancellation::Cancellation;
 use crate::provider::dependency_resolution::Dependencies;
+use crate::provider::preprocessor::Preprocessor;
+use crate::provider::progress::{Progress, ProgressKind};
+use crate::provider::suppression::Suppressions;
 use crate::provider::target_framework::TargetFramework;
 
 pub struct Project {
     pub location: PathBuf,
     pub db_path: PathBuf,
     pub dependencies: Arc<TokioMutex<Option<Vec<Dependencies>>>>,
-    pub graph: Arc<Mutex<Option<StackGraph>>>,
+    /// The published graph, replaced as a whole whenever the graph is reloaded.
+    graph: Arc<Mutex<Option<Arc<GraphSnapshot>>>>,
     pub source_language_config: Arc<RwLock<Option<SourceNodeLanguageConfiguration>>>,
     pub analysis_mode: AnalysisMode,
     pub tools: Tools,
+    /// The number of threads that build the graph of the source files and prepare searches.
+    pub index_workers: usize,
     target_framework: Arc<Mutex<Option<TargetFramework>>>,
     sdk_path: Arc<Mutex<Option<PathBuf>>>,
+    preprocessor: Arc<Mutex<Option<Arc<Preprocessor>>>>,
+    suppressions: Arc<Mutex<Option<Arc<Suppressions>>>>,
+    pub(crate) progress: Progress,
+    pub(crate) cancellation: Cancellation,
+}
+
+/// A stack graph that is no longer modified, along with its symbol index, call graph and the
+/// searches prepared for it. Queries hold on to the snapshot they started with, so a reload never
+/// waits for them.
+pub struct GraphSnapshot {
+    pub graph: StackGraph,
+    pub index: SymbolIndex,
+    prepared: Mutex<Arc<PreparedSearches>>,
+    /// Built by the first `calls` query, the lock is held while it is built so concurrent
+    /// queries wait for it instead of building it again.
+    call_graph: Mutex<Option<Arc<CallGraph>>>,
+}
+
+impl GraphSnapshot {
+    pub fn new(graph: StackGraph) -> GraphSnapshot {
+        let index = SymbolIndex::new(&graph);
+        GraphSnapshot {
+            graph,
+            index,
+            prepared: Mutex::new(Arc::new(PreparedSearches::new())),
+            call_graph: Mutex::new(None),
+        }
+    }
+
+    /// The call graph of the snapshot, built on first use. A cancelled build is not kept.
+    pub(crate) fn call_graph(
+        &self,
+        source_type: &SourceType,
+        options: &QueryOptions,
+    ) -> anyhow::Result<Arc<CallGraph>> {
+        let mut guard = self.call_graph.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(call_graph) = guard.as_ref() {
+            return Ok(call_graph.clone());
+        }
+        let call_graph = Arc::new(CallGraph::new(&self.graph, source_type, options)?);
+        *guard = Some(call_graph.clone());
+        Ok(call_graph)
+    }
+
+    pub(crate) fn prepared_searches(&self) -> Arc<PreparedSearches> {
+        self.prepared
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Publish a copy of the prepared searches with `searches` added, queries that already
+    /// started keep the searches they were given.
+    fn add_prepared_searches(&self, searches: PreparedSearches) {
+        let mut guard = self.prepared.lock().unwrap_or_else(|e| e.into_inner());
+        let mut prepared = PreparedSearches::clone(&guard);
+        prepared.extend(searches);
+        *guard = Arc::new(prepared);
+    }
 }
 
 #[derive(Eq, PartialEq, Debug, Deserialize)]
@@ -39,6 +108,15 @@ pub enum AnalysisMode {
     SourceOnly,
 }
 
+impl AnalysisMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AnalysisMode::Full => "full",
+            AnalysisMode::SourceOnly => "source-only",
+        }
+    }
+}
+
 impl From<&str> for AnalysisMode {
     fn from(value: &str) -> Self {
         match value {
@@ -102,6 +180,9 @@ impl Project {
         db_path: PathBuf,
         analysis_mode: AnalysisMode,
         tools: Tools,
+        index_workers: usize,
+        progress: Progress,
+        cancellation: Cancellation,
     ) -> Project {
         Project {
             location,
@@ -111,8 +192,13 @@ impl Project {
             source_language_config: Arc::new(RwLock::new(None)),
             analysis_mode,
             tools,
+            index_workers,
             target_framework: Arc::new(Mutex::new(None)),
             sdk_path: Arc::new(Mutex::new(None)),
+            preprocessor: Arc::new(Mutex::new(None)),
+            suppressions: Arc::new(Mutex::new(None)),
+            progress,
+            cancellation,
         }
     }
 
@@ -122,6 +208,80 @@ impl Project {
         }
     }
 
+    pub(crate) fn set_preprocessor(&self, preprocessor: Preprocessor) {
+        if let Ok(mut guard) = self.preprocessor.lock() {
+            *guard = Some(Arc::new(preprocessor));
+        }
+    }
+
+    pub(crate) fn get_preprocessor(&self) -> Option<Arc<Preprocessor>> {
+        self.preprocessor
+            .lock()
+            .ok()
+            .and_then(|guard| guard.clone())
+    }
+
+    pub(crate) fn set_suppressions(&self, suppressions: Suppressions) {
+        if let Ok(mut guard) = self.suppressions.lock() {
+            *guard = Some(Arc::new(suppressions));
+        }
+    }
+
+    pub(crate) fn get_suppressions(&self) -> Option<Arc<Suppressions>> {
+        self.suppressions
+            .lock()
+            .ok()
+            .and_then(|guard| guard.clone())
+    }
+
+    /// The current graph, or `None` when the project has not been loaded.
+    pub fn graph_snapshot(&self) -> Option<Arc<GraphSnapshot>> {
+        self.graph.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Replace the graph with `graph`. Queries running against the previous graph finish on it.
+    pub(crate) fn publish_graph(&self, graph: StackGraph) {
+        let snapshot = Arc::new(GraphSnapshot::new(graph));
+        let previous = self
+            .graph
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .replace(snapshot);
+        // The last reference to a large graph may be this one, drop it outside of the lock.
+        drop(previous);
+    }
+
+    /// Prepare the searches of the patterns that are not prepared yet on `index_workers`
+    /// threads, returning how many were added. They are cached on the graph they were computed
+    /// from, so they always match it.
+    pub(crate) fn prepare_searches<F>(
+        &self,
+        patterns: Vec<String>,
+        on_pattern: F,
+    ) -> Result<usize, Error>
+    where
+        F: Fn(usize, usize) + Sync,
+    {
+        let Some(snapshot) = self.graph_snapshot() else {
+            return Err(anyhow!("project graph is not loaded"));
+        };
+        let prepared = snapshot.prepared_searches();
+        let patterns: Vec<String> = patterns
+            .into_iter()
+            .filter(|p| !prepared.contains_key(p))
+            .collect();
+        let searches = prepare_searches(
+            &snapshot.graph,
+            &snapshot.index,
+            patterns,
+            self.index_workers,
+            on_pattern,
+        );
+        let added = searches.len();
+        snapshot.add_prepared_searches(searches);
+        Ok(added)
+    }
+
     pub(crate) fn set_sdk_path(&self, path: PathBuf) {
         if let Ok(mut guard) = self.sdk_path.lock() {
             *guard = Some(path);
@@ -267,7 +427,28 @@ impl Project {
     }
 
     pub async fn get_project_graph(self: &Arc<Self>) -> Result<usize, Error> {
+        let lc_guard = self.source_language_config.read().await;
+        let lc = lc_guard.as_ref().expect("unable to get read lock");
         if self.db_path.exists() {
+            // Files edited, added or deleted since they were stored are indexed again first.
+            debug!("updating changed files in existing db: {:?}", &self.db_path);
+            let update = update_stack_graph(
+                &self.location,
+                &self.db_path,
+                &lc.source_type_node_info,
+                &lc.language_config,
+                self.index_workers,
+                &self.cancellation,
+                |files_processed, total_files| {
+                    self.progress
+                        .update(ProgressKind::IndexSource, files_processed, total_files)
+                },
+            )?;
+            info!(
+                "indexed {} new or changed files, removed {} deleted files, {} files unchanged",
+                update.indexed, update.removed, update.unchanged
+            );
+
             debug!("trying to load from existing db: {:?}", &self.db_path);
             // Load the stack_graph.
             let mut db_reader = match SQLiteReader::open(&self.db_path) {
@@ -278,13 +459,13 @@ impl Project {
             };
 
             // Load graphs from project location
-            db_reader.load_graphs_for_file_or_directory(&self.location, &NoCancellation)?;
+            db_reader.load_graphs_for_file_or_directory(&self.location, &self.cancellation)?;
 
             // Also load SDK XML files if target framework is set
             if let Some(sdk_path) = self.get_sdk_path() {
                 debug!("Loading SDK graphs from: {:?}", sdk_path);
                 // Ignore errors here - SDK might not be installed yet
-                let _ = db_reader.load_graphs_for_file_or_directory(&sdk_path, &NoCancellation);
+                let _ = db_reader.load_graphs_for_file_or_directory(&sdk_path, &self.cancellation);
             }
 
             let (stack_graph, _, _) = db_reader.get_graph_partials_and_db();
@@ -303,33 +484,33 @@ impl Project {
             if graph.iter_symbols().count() == 0 {
                 debug!("unable to load graph");
             } else {
-                debug!("trying to get guard");
-                if let Ok(mut graph_guard) = self.graph.lock() {
-                    graph_guard.replace(graph);
-                    drop(graph_guard);
-                    debug!("setting graph on project");
-                    return Ok(stack_graph.iter_files().count());
-                }
+                debug!("setting graph on project");
+                self.publish_graph(graph);
+                let files = stack_graph.iter_files().count();
+                self.progress
+                    .update(ProgressKind::IndexSource, files, files);
+                return Ok(files);
             }
             drop(graph);
         }
 
-        let lc_guard = self.source_language_config.read().await;
-        // If the databse is present we should consider use that and load into the graph
-        let lc = lc_guard.as_ref().expect("unable to get read lock");
         let initialized_results = match init_stack_graph(
             &self.location,
             &self.db_path,
             &lc.source_type_node_info,
             &lc.language_config,
+            self.index_workers,
+            &self.cancellation,
+            |files_processed, total_files| {
+                self.progress
+                    .update(ProgressKind::IndexSource, files_processed, total_files)
+            },
         ) {
             Ok(i) => i,
             Err(e) => return Err(anyhow!(e)),
         };
 
-        if let Ok(mut graph_guard) = self.graph.lock() {
-            graph_guard.replace(initialized_results.stack_graph);
-        }
+        self.publish_graph(initialized_results.stack_graph);
         Ok(initialized_results.files_loaded)
     }
 
@@ -346,3 +527,77 @@ impl Project {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn project() -> Project {
+        Project::new(
+            PathBuf::from("."),
+            PathBuf::from("test.db"),
+            AnalysisMode::SourceOnly,
+            Tools {
+                ilspy_cmd: PathBuf::new(),
+                paket_cmd: PathBuf::new(),
+                dotnet_install_cmd: None,
+                dotnet_sdk_path: None,
+            },
+            1,
+            Progress::new(),
+            Cancellation::default(),
+        )
+    }
+
+    fn graph(file: &str) -> StackGraph {
+        let mut graph = StackGraph::new();
+        graph.add_file(file).unwrap();
+        graph
+    }
+
+    #[test]
+    fn test_publish_graph_keeps_held_snapshot() {
+        let project = project();
+        assert!(project.graph_snapshot().is_none());
+        assert!(project.prepare_searches(vec![], |_, _| {}).is_err());
+
+        project.publish_graph(graph("first.cs"));
+        let first = project.graph_snapshot().unwrap();
+        let added = project
+            .prepare_searches(vec!["System.*".to_string()], |_, _| {})
+            .unwrap();
+        assert_eq!(added, 1);
+        let added = project
+            .prepare_searches(vec!["System.*".to_string()], |_, _| {})
+            .unwrap();
+        assert_eq!(added, 0);
+
+        project.publish_graph(graph("second.cs"));
+        let second = project.graph_snapshot().unwrap();
+        assert!(first.graph.get_file("first.cs").is_some());
+        assert!(first.prepared_searches().contains_key("System.*"));
+        assert!(second.graph.get_file("second.cs").is_some());
+        assert!(second.prepared_searches().is_empty());
+    }
+
+    #[test]
+    fn test_call_graph_built_once_per_snapshot() {
+        let mut graph = graph("first.cs");
+        let source_type = SourceType::Source {
+            symbol_handle: graph.add_symbol("source"),
+        };
+        let snapshot = GraphSnapshot::new(graph);
+        let cancellation = Cancellation::default();
+        cancellation.cancel();
+        let cancelled = QueryOptions {
+            cancellation: Some(&cancellation),
+            ..Default::default()
+        };
+        assert!(snapshot.call_graph(&source_type, &cancelled).is_err());
+
+        let options = QueryOptions::default();
+        let first = snapshot.call_graph(&source_type, &options).unwrap();
+        let second = snapshot.call_graph(&source_type, &options).unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+    }
+}
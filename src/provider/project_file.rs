@@ -0,0 +1,230 @@
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Error};
+use regex::Regex;
+use serde_json::Value;
+use tracing::{info, warn};
+use url::Url;
+
+use crate::c_sharp_graph::query::Query;
+use crate::c_sharp_graph::results::ResultNode;
+use crate::provider::config_file::to_result_node;
+use crate::provider::target_framework::TargetFrameworkHelper;
+use crate::provider::xml_query::{value_matches, XmlElement, XmlMatch};
+
+/// The kinds of project file elements that can be matched.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ElementType {
+    /// A child of a `PropertyGroup`, the value is the element text.
+    Property,
+    /// A child of an `ItemGroup`, the value is the `Include` (or `Update`/`Remove`) attribute.
+    Item,
+    /// An `Import` element, the value is the imported project.
+    Import,
+}
+
+impl ElementType {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ElementType::Property => "property",
+            ElementType::Item => "item",
+            ElementType::Import => "import",
+        }
+    }
+}
+
+/// Queries the properties, items and imports of the `.csproj` files of a project. The query is a
+/// regex on the element name, e.g. `^OutputType$` or `^PackageReference$`.
+pub(crate) struct ProjectQuery {
+    pub(crate) location: PathBuf,
+    /// Regex on the property text, the item `Include` or the imported project.
+    pub(crate) value: Option<String>,
+    /// Regex on the item version, from the `Version` attribute or metadata, or from the assembly
+    /// name of a `Reference`.
+    pub(crate) version: Option<String>,
+}
+
+impl Query for ProjectQuery {
+    fn query(self, query: String) -> anyhow::Result<Vec<ResultNode>, Error> {
+        let name = Regex::new(&query)?;
+        let value = self.value.as_deref().map(Regex::new).transpose()?;
+        let version = self.version.as_deref().map(Regex::new).transpose()?;
+
+        let mut files = TargetFrameworkHelper::find_csproj_files(&self.location)?;
+        files.sort();
+        let mut results = vec![];
+        for file in files {
+            let document = match XmlElement::from_file(&file) {
+                Ok(d) => d,
+                Err(e) => {
+                    warn!("skipping project file {:?}: {}", file, e);
+                    continue;
+                }
+            };
+            let file_uri = Url::from_file_path(&file)
+                .map_err(|_| anyhow!("unable to get the file url for file: {:?}", file))?
+                .to_string();
+            let mut elements = vec![];
+            project_elements(&document, &mut elements);
+            for (element_type, element) in elements {
+                if !name.is_match(&element.name) {
+                    continue;
+                }
+                let m = XmlMatch {
+                    element,
+                    value: element_value(element_type, element),
+                };
+                if !value_matches(value.as_ref(), m.value.as_deref()) {
+                    continue;
+                }
+                let item_version = match element_type {
+                    ElementType::Item => item_version(element),
+                    _ => None,
+                };
+                if !value_matches(version.as_ref(), item_version.as_deref()) {
+                    continue;
+                }
+                let mut result = to_result_node(&file_uri, &m, None);
+                result.variables.insert(
+                    "element_type".to_string(),
+                    Value::from(element_type.as_str()),
+                );
+                if let Some(item_version) = item_version {
+                    result
+                        .variables
+                        .insert("version".to_string(), Value::from(item_version));
+                }
+                results.push(result);
+            }
+        }
+        results.sort();
+        info!("found {} project results for '{}'", results.len(), query);
+        Ok(results)
+    }
+}
+
+/// Collect the properties, items and imports of a project, including the ones nested in
+/// `Choose`/`When` and `Target` elements.
+fn project_elements<'a>(element: &'a XmlElement, found: &mut Vec<(ElementType, &'a XmlElement)>) {
+    for child in element.children.iter() {
+        match element.name.as_str() {
+            "PropertyGroup" => found.push((ElementType::Property, child)),
+            "ItemGroup" => found.push((ElementType::Item, child)),
+            _ if child.name == "Import" => found.push((ElementType::Import, child)),
+            _ => project_elements(child, found),
+        }
+    }
+}
+
+fn element_value(element_type: ElementType, element: &XmlElement) -> Option<String> {
+    match element_type {
+        ElementType::Property => Some(element.text.clone()).filter(|t| !t.is_empty()),
+        ElementType::Item => ["Include", "Update", "Remove"]
+            .iter()
+            .find_map(|a| element.attributes.get(*a).cloned()),
+        ElementType::Import => element.attributes.get("Project").cloned(),
+    }
+}
+
+/// The version of an item, `<PackageReference Include="x" Version="1.0" />` and
+/// `<PackageReference Include="x"><Version>1.0</Version></PackageReference>` are both valid,
+/// assembly references carry it in the assembly name.
+fn item_version(element: &XmlElement) -> Option<String> {
+    if let Some(version) = element.attributes.get("Version") {
+        return Some(version.clone());
+    }
+    if let Some(version) = element.children.iter().find(|c| c.name == "Version") {
+        return Some(version.text.clone());
+    }
+    element
+        .attributes
+        .get("Include")?
+        .split(',')
+        .skip(1)
+        .find_map(|part| part.trim().strip_prefix("Version="))
+        .map(|v| v.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_project_elements() {
+        let document = XmlElement::parse(
+            r#"<Project Sdk="Microsoft.NET.Sdk">
+  <PropertyGroup>
+    <OutputType>Exe</OutputType>
+    <LangVersion>7.3</LangVersion>
+  </PropertyGroup>
+  <ItemGroup>
+    <PackageReference Include="Newtonsoft.Json" Version="12.0.1" />
+    <PackageReference Include="Serilog">
+      <Version>2.10.0</Version>
+    </PackageReference>
+    <Reference Include="System.Web.Mvc, Version=4.0.0.0, Culture=neutral" />
+  </ItemGroup>
+  <Import Project="$(VSToolsPath)\WebApplications\Microsoft.WebApplication.targets" />
+</Project>"#,
+        )
+        .unwrap();
+        let mut elements = vec![];
+        project_elements(&document, &mut elements);
+        let found: Vec<(ElementType, &str, Option<String>)> = elements
+            .iter()
+            .map(|(t, e)| (*t, e.name.as_str(), element_value(*t, e)))
+            .collect();
+        assert_eq!(found.len(), 6);
+        assert_eq!(
+            found[0],
+            (ElementType::Property, "OutputType", Some("Exe".to_string()))
+        );
+        assert_eq!(
+            found[2],
+            (
+                ElementType::Item,
+                "PackageReference",
+                Some("Newtonsoft.Json".to_string())
+            )
+        );
+        assert_eq!(found[5].0, ElementType::Import);
+
+        let versions: Vec<Option<String>> = elements[2..5]
+            .iter()
+            .map(|(_, e)| item_version(e))
+            .collect();
+        assert_eq!(
+            versions,
+            vec![
+                Some("12.0.1".to_string()),
+                Some("2.10.0".to_string()),
+                Some("4.0.0.0".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_project_query_on_nerd_dinner() {
+        let location = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("testdata/nerd-dinner");
+        let query = ProjectQuery {
+            location: location.clone(),
+            value: Some("^System\\.Web$".to_string()),
+            version: None,
+        };
+        let results = query.query("^Reference$".to_string()).unwrap();
+        assert_eq!(results.len(), 1);
+        assert!(results[0].file_uri.ends_with("NerdDinner.csproj"));
+        assert_eq!(results[0].line_number, 108);
+        assert_eq!(results[0].code_location.start_position.character, 4);
+        assert_eq!(results[0].variables["element_type"], Value::from("item"));
+
+        let query = ProjectQuery {
+            location,
+            value: Some("Microsoft\\.WebApplication\\.targets$".to_string()),
+            version: None,
+        };
+        let results = query.query("^Import$".to_string()).unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].line_number, 376);
+    }
+}
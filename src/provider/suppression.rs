@@ -0,0 +1,386 @@
+use std::collections::{BTreeSet, HashMap};
+use std::ops::RangeInclusive;
+use std::path::Path;
+
+use prost_types::{value::Kind, Struct};
+use regex::Regex;
+use serde_json::Value;
+use tracing::{info, warn};
+use url::Url;
+
+use crate::c_sharp_graph::results::ResultNode;
+use crate::provider::fingerprint::FINGERPRINT_VARIABLE;
+
+const BASELINE_FILE_KEY: &str = "baseline_file";
+const KEEP_SUPPRESSED_KEY: &str = "keep_suppressed";
+const SUPPRESSED_VARIABLE: &str = "suppressed";
+const DIRECTIVE: &str = "konveyor-ignore:";
+
+/// A `konveyor-ignore:` comment and the lines it applies to.
+#[derive(Debug, PartialEq)]
+struct Directive {
+    lines: RangeInclusive<usize>,
+    /// Rule IDs or FQDN patterns, an empty list suppresses every incident.
+    patterns: Vec<String>,
+}
+
+/// Suppresses the incidents that are accepted, either with a `konveyor-ignore:` comment in the
+/// code or by listing their fingerprint in a baseline file.
+#[derive(Debug, Default)]
+pub(crate) struct Suppressions {
+    baseline: BTreeSet<String>,
+    /// Tag suppressed incidents instead of removing them, from `keep_suppressed`.
+    keep: bool,
+}
+
+impl Suppressions {
+    pub(crate) fn new(location: &Path, config: &Option<Struct>) -> Suppressions {
+        let Some(config) = config else {
+            return Suppressions::default();
+        };
+        let keep = match config
+            .fields
+            .get(KEEP_SUPPRESSED_KEY)
+            .and_then(|v| v.kind.as_ref())
+        {
+            Some(Kind::BoolValue(b)) => *b,
+            Some(Kind::StringValue(s)) => s.eq_ignore_ascii_case("true"),
+            _ => false,
+        };
+        let baseline = match config
+            .fields
+            .get(BASELINE_FILE_KEY)
+            .and_then(|v| v.kind.as_ref())
+        {
+            Some(Kind::StringValue(file)) => {
+                // A relative baseline is in the project, next to the code it accepts.
+                let path = location.join(file);
+                match std::fs::read_to_string(&path) {
+                    Ok(content) => {
+                        let baseline = parse_baseline(&content);
+                        info!(
+                            "Loaded {} baseline fingerprints from {:?}",
+                            baseline.len(),
+                            path
+                        );
+                        baseline
+                    }
+                    Err(e) => {
+                        warn!("unable to read baseline file {:?}: {}", path, e);
+                        BTreeSet::new()
+                    }
+                }
+            }
+            _ => BTreeSet::new(),
+        };
+        Suppressions { baseline, keep }
+    }
+
+    /// Remove the suppressed results, or tag them with a `suppressed` variable of `inline` or
+    /// `baseline` when configured to keep them. The patterns of a comment match the ID of the
+    /// rule, when it is known, or the FQDN of the incident.
+    pub(crate) fn apply(&self, results: &mut Vec<ResultNode>, rule_id: Option<&str>) {
+        let mut files: HashMap<String, Vec<Directive>> = HashMap::new();
+        for result in results.iter_mut() {
+            let directives = files
+                .entry(result.file_uri.clone())
+                .or_insert_with(|| directives_of(&result.file_uri));
+            let line = result.code_location.start_position.line;
+            let symbol = result.get_fqdn_symbol();
+            let inline = directives.iter().any(|d| {
+                d.lines.contains(&line)
+                    && (d.patterns.is_empty()
+                        || d.patterns
+                            .iter()
+                            .any(|p| Some(p.as_str()) == rule_id || pattern_matches(p, &symbol)))
+            });
+            let baseline = result
+                .variables
+                .get(FINGERPRINT_VARIABLE)
+                .and_then(|v| v.as_str())
+                .is_some_and(|f| self.baseline.contains(f));
+            let suppressed = if inline {
+                "inline"
+            } else if baseline {
+                "baseline"
+            } else {
+                continue;
+            };
+            result
+                .variables
+                .insert(SUPPRESSED_VARIABLE.to_string(), Value::from(suppressed));
+        }
+        if !self.keep {
+            results.retain(|r| !r.variables.contains_key(SUPPRESSED_VARIABLE));
+        }
+    }
+}
+
+/// One fingerprint per line, anything after it and lines starting with `#` are comments.
+fn parse_baseline(content: &str) -> BTreeSet<String> {
+    content
+        .lines()
+        .filter_map(|l| l.split_whitespace().next())
+        .filter(|f| !f.starts_with('#'))
+        .map(String::from)
+        .collect()
+}
+
+/// A pattern with `*` wildcards, matched against the whole FQDN.
+fn pattern_matches(pattern: &str, symbol: &str) -> bool {
+    let regex = format!(
+        "^{}$",
+        pattern
+            .split('*')
+            .map(regex::escape)
+            .collect::<Vec<_>>()
+            .join(".*")
+    );
+    Regex::new(&regex).is_ok_and(|r| r.is_match(symbol))
+}
+
+fn directives_of(file_uri: &str) -> Vec<Directive> {
+    let Some(path) = Url::parse(file_uri)
+        .ok()
+        .and_then(|u| u.to_file_path().ok())
+    else {
+        return vec![];
+    };
+    let Ok(source) = std::fs::read_to_string(&path) else {
+        return vec![];
+    };
+    let is_csharp = matches!(path.extension(), Some(e) if e == "cs");
+    directives(&source, is_csharp)
+}
+
+/// Find the `konveyor-ignore:` comments of a file. A comment after code applies to its own
+/// line, a comment on a line of its own applies to the declaration or statement that follows
+/// it. Only C# code is followed to the end of the construct, in other files the comment applies
+/// to the next line.
+fn directives(source: &str, is_csharp: bool) -> Vec<Directive> {
+    let lines: Vec<&str> = source.lines().collect();
+    let mut line_starts = vec![0];
+    line_starts.extend(source.match_indices('\n').map(|(i, _)| i + 1));
+    let mut directives = vec![];
+    for (number, line) in lines.iter().enumerate() {
+        let Some(index) = line.find(DIRECTIVE) else {
+            continue;
+        };
+        let patterns = split_patterns(&line[index + DIRECTIVE.len()..]);
+        let code_before = ["//", "/*", "@*", "<%--", "<!--"]
+            .iter()
+            .fold(line[..index].trim_end(), |code, opener| {
+                code.trim_end_matches(opener)
+            })
+            .trim();
+        if !code_before.is_empty() {
+            directives.push(Directive {
+                lines: number..=number,
+                patterns,
+            });
+            continue;
+        }
+        // The construct starts at the next line that is not a comment.
+        let Some(start) = (number + 1..lines.len()).find(|n| {
+            let l = lines[*n].trim();
+            !l.is_empty() && !l.starts_with("//")
+        }) else {
+            continue;
+        };
+        let end = if is_csharp {
+            construct_end(&source[line_starts[start]..])
+                .map(|offset| {
+                    line_starts.partition_point(|s| *s <= line_starts[start] + offset) - 1
+                })
+                .unwrap_or(start)
+        } else {
+            start
+        };
+        directives.push(Directive {
+            lines: number..=end,
+            patterns,
+        });
+    }
+    directives
+}
+
+/// The rule IDs and patterns after the directive, without the end of the comment.
+fn split_patterns(text: &str) -> Vec<String> {
+    let text = ["*/", "*@", "--%>", "-->"]
+        .iter()
+        .fold(text.trim_end(), |text, terminator| {
+            text.trim_end_matches(terminator)
+        });
+    text.split([',', ' ', '\t'])
+        .filter(|p| !p.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// The byte offset of the `;` or `}` that ends the declaration or statement the code starts
+/// with, strings, characters and comments are skipped.
+fn construct_end(code: &str) -> Option<usize> {
+    let bytes = code.as_bytes();
+    let mut braces = 0usize;
+    let mut parens = 0usize;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'/' if bytes.get(i + 1) == Some(&b'/') => {
+                i = code[i..].find('\n').map_or(bytes.len(), |n| i + n);
+                continue;
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i = code[i + 2..].find("*/").map_or(bytes.len(), |n| i + n + 4);
+                continue;
+            }
+            b'"' | b'\'' => {
+                let quote = bytes[i];
+                let verbatim = i > 0 && bytes[i - 1] == b'@';
+                i += 1;
+                while i < bytes.len() && bytes[i] != quote {
+                    if bytes[i] == b'\\' && !verbatim {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+            }
+            b'(' | b'[' => parens += 1,
+            b')' | b']' => parens = parens.saturating_sub(1),
+            b'{' => braces += 1,
+            b'}' => {
+                braces = braces.saturating_sub(1);
+                if braces == 0 && parens == 0 {
+                    return Some(i);
+                }
+            }
+            b';' if braces == 0 && parens == 0 => return Some(i),
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_directives() {
+        let source = r#"public class Home
+{
+    // konveyor-ignore: System.Web.*
+    [HttpPost]
+    public void Index()
+    {
+        var s = "}";
+        Run(() => { Stop(); });
+    }
+
+    public void About()
+    {
+        Session.Clear(); // konveyor-ignore:
+        /* konveyor-ignore: rule-001, System.Web.HttpContext.Current */
+        var user = HttpContext.Current
+            .User;
+        Other();
+    }
+}
+"#;
+        assert_eq!(
+            directives(source, true),
+            vec![
+                Directive {
+                    lines: 2..=8,
+                    patterns: vec!["System.Web.*".to_string()],
+                },
+                Directive {
+                    lines: 12..=12,
+                    patterns: vec![],
+                },
+                Directive {
+                    lines: 13..=15,
+                    patterns: vec![
+                        "rule-001".to_string(),
+                        "System.Web.HttpContext.Current".to_string()
+                    ],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_apply() {
+        let dir = tempfile::tempdir().unwrap();
+        let location = dir.path().to_path_buf();
+        let file = location.join("Home.cs");
+        std::fs::write(
+            &file,
+            "// konveyor-ignore: System.Web.*\nHttpContext.Current.User;\nSession.Clear();\n",
+        )
+        .unwrap();
+        std::fs::write(
+            location.join("baseline.txt"),
+            "# accepted\nabc123 Session.Clear\n",
+        )
+        .unwrap();
+        let file_uri = Url::from_file_path(&file).unwrap().to_string();
+        let result = |line: usize, fqdn: &str, fingerprint: &str| {
+            let mut result = ResultNode {
+                file_uri: file_uri.clone(),
+                line_number: line,
+                variables: Default::default(),
+                code_location: Default::default(),
+            };
+            result.code_location.start_position.line = line;
+            result
+                .variables
+                .insert("symbol".to_string(), Value::from(fqdn));
+            result
+                .variables
+                .insert(FINGERPRINT_VARIABLE.to_string(), Value::from(fingerprint));
+            result
+        };
+        let results = vec![
+            result(1, "System.Web.HttpContext.Current", "def456"),
+            result(2, "System.Web.SessionState.Clear", "abc123"),
+            result(2, "Other.Clear", "xyz789"),
+        ];
+
+        let config = Struct {
+            fields: [
+                (
+                    BASELINE_FILE_KEY.to_string(),
+                    prost_types::Value {
+                        kind: Some(Kind::StringValue("baseline.txt".to_string())),
+                    },
+                ),
+                (
+                    KEEP_SUPPRESSED_KEY.to_string(),
+                    prost_types::Value {
+                        kind: Some(Kind::BoolValue(true)),
+                    },
+                ),
+            ]
+            .into_iter()
+            .collect(),
+        };
+        let suppressions = Suppressions::new(&location, &Some(config));
+        let mut kept = results.clone();
+        suppressions.apply(&mut kept, None);
+        assert_eq!(
+            kept[0].variables[SUPPRESSED_VARIABLE],
+            Value::from("inline")
+        );
+        assert_eq!(
+            kept[1].variables[SUPPRESSED_VARIABLE],
+            Value::from("baseline")
+        );
+        assert!(!kept[2].variables.contains_key(SUPPRESSED_VARIABLE));
+
+        let mut dropped = results.clone();
+        Suppressions::new(&location, &None).apply(&mut dropped, None);
+        assert_eq!(dropped.len(), 2);
+    }
+}
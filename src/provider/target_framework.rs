@@ -67,10 +67,8 @@ impl TargetFramework {
                         _ => {}
                     }
                 }
-                Ok(Event::End(e)) => {
-                    if e.name().as_ref() == b"PropertyGroup" {
-                        in_property_group = false;
-                    }
+                Ok(Event::End(e)) if e.name().as_ref() == b"PropertyGroup" => {
+                    in_property_group = false;
                 }
                 Ok(Event::Eof) => break,
                 Err(e) => {
@@ -0,0 +1,461 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::{anyhow, Error};
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use regex::Regex;
+
+use crate::c_sharp_graph::results::{Location, Position};
+
+/// An element of an XML document, with the location of the element in the file.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct XmlElement {
+    pub(crate) name: String,
+    pub(crate) attributes: BTreeMap<String, String>,
+    pub(crate) text: String,
+    pub(crate) children: Vec<XmlElement>,
+    /// The path of element names from the root, e.g. `/configuration/system.web`.
+    pub(crate) path: String,
+    pub(crate) location: Location,
+}
+
+impl XmlElement {
+    /// Parse an XML file, the returned element is the document, its children are the top level
+    /// elements.
+    pub(crate) fn from_file(path: &Path) -> Result<XmlElement, Error> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read XML file {:?}: {}", path, e))?;
+        Self::parse(&content)
+    }
+
+    pub(crate) fn parse(content: &str) -> Result<XmlElement, Error> {
+        // Strip the BOM so that offsets line up with what editors show.
+        let content = content.strip_prefix('\u{feff}').unwrap_or(content);
+        let lines = LineIndex::new(content);
+        let mut reader = Reader::from_str(content);
+
+        // The stack of open elements, the document is always at the bottom.
+        let mut stack: Vec<XmlElement> = vec![XmlElement::default()];
+        loop {
+            let start = reader.buffer_position() as usize;
+            match reader.read_event() {
+                Ok(Event::Start(e)) => {
+                    let parent_path = &stack.last().unwrap().path;
+                    let mut element = Self::from_start(&e, parent_path)?;
+                    element.location.start_position = lines.position(start);
+                    stack.push(element);
+                }
+                Ok(Event::Empty(e)) => {
+                    let parent = stack.last_mut().unwrap();
+                    let mut element = Self::from_start(&e, &parent.path)?;
+                    element.location = Location {
+                        start_position: lines.position(start),
+                        end_position: lines.position(reader.buffer_position() as usize),
+                    };
+                    parent.children.push(element);
+                }
+                Ok(Event::Text(e)) => {
+                    let text = e
+                        .decode()
+                        .map_err(|e| anyhow!("XML decoding error at position {}: {}", start, e))?;
+                    stack.last_mut().unwrap().text.push_str(text.trim());
+                }
+                Ok(Event::CData(e)) => {
+                    let text = String::from_utf8_lossy(&e).to_string();
+                    stack.last_mut().unwrap().text.push_str(text.trim());
+                }
+                Ok(Event::End(_)) => {
+                    if stack.len() < 2 {
+                        return Err(anyhow!("unexpected closing tag at position {}", start));
+                    }
+                    let mut element = stack.pop().unwrap();
+                    element.location.end_position =
+                        lines.position(reader.buffer_position() as usize);
+                    stack.last_mut().unwrap().children.push(element);
+                }
+                Ok(Event::Eof) => break,
+                Err(e) => {
+                    return Err(anyhow!(
+                        "XML parsing error at position {}: {}",
+                        reader.buffer_position(),
+                        e
+                    ));
+                }
+                _ => {}
+            }
+        }
+        if stack.len() != 1 {
+            return Err(anyhow!("unclosed element in XML document"));
+        }
+        Ok(stack.pop().unwrap())
+    }
+
+    fn from_start(e: &BytesStart, parent_path: &str) -> Result<XmlElement, Error> {
+        let name = String::from_utf8_lossy(e.name().as_ref()).to_string();
+        let mut attributes = BTreeMap::new();
+        for attribute in e.attributes() {
+            let attribute = attribute.map_err(|e| anyhow!("invalid XML attribute: {}", e))?;
+            let key = String::from_utf8_lossy(attribute.key.as_ref()).to_string();
+            let value = attribute
+                .unescape_value()
+                .map_err(|e| anyhow!("invalid XML attribute value: {}", e))?
+                .to_string();
+            attributes.insert(key, value);
+        }
+        Ok(XmlElement {
+            path: format!("{}/{}", parent_path, name),
+            name,
+            attributes,
+            ..Default::default()
+        })
+    }
+
+    fn descendants<'a>(&'a self, found: &mut Vec<&'a XmlElement>) {
+        for child in self.children.iter() {
+            found.push(child);
+            child.descendants(found);
+        }
+    }
+}
+
+/// Maps byte offsets to zero based line and character positions.
+pub(crate) struct LineIndex {
+    line_starts: Vec<usize>,
+}
+
+impl LineIndex {
+    pub(crate) fn new(content: &str) -> LineIndex {
+        let mut line_starts = vec![0];
+        line_starts.extend(content.match_indices('\n').map(|(i, _)| i + 1));
+        LineIndex { line_starts }
+    }
+
+    pub(crate) fn position(&self, offset: usize) -> Position {
+        let line = self.line_starts.partition_point(|start| *start <= offset) - 1;
+        Position {
+            line,
+            character: offset - self.line_starts[line],
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Predicate {
+    HasAttribute(String),
+    AttributeEquals(String, String),
+    TextEquals(String),
+}
+
+#[derive(Debug, PartialEq)]
+struct Step {
+    /// The step was preceded by `//` and matches any descendant.
+    descendant: bool,
+    name: String,
+    predicates: Vec<Predicate>,
+}
+
+/// An XPath-like query over an XML document.
+///
+/// Supports absolute (`/configuration/system.web`) and descendant (`//httpModules/add`) steps,
+/// `*` for any element, the predicates `[@attr]`, `[@attr='value']` and `[text()='value']`, and
+/// a trailing `/@attr` to select an attribute value.
+#[derive(Debug)]
+pub(crate) struct XmlPath {
+    steps: Vec<Step>,
+    attribute: Option<String>,
+}
+
+/// An element selected by an [`XmlPath`], with the selected attribute value or text.
+#[derive(Debug)]
+pub(crate) struct XmlMatch<'a> {
+    pub(crate) element: &'a XmlElement,
+    pub(crate) value: Option<String>,
+}
+
+impl XmlPath {
+    pub(crate) fn parse(query: &str) -> Result<XmlPath, Error> {
+        let query = query.trim();
+        if !query.starts_with('/') {
+            return Err(anyhow!("XML path must start with '/' or '//': {}", query));
+        }
+        let mut steps = vec![];
+        let mut attribute = None;
+        let mut descendant = false;
+        for part in split_steps(query)? {
+            if part.is_empty() {
+                if descendant {
+                    return Err(anyhow!("invalid XML path: {}", query));
+                }
+                descendant = true;
+                continue;
+            }
+            if attribute.is_some() {
+                return Err(anyhow!(
+                    "attribute selection must be the last step of the XML path: {}",
+                    query
+                ));
+            }
+            if let Some(name) = part.strip_prefix('@') {
+                attribute = Some(name.to_string());
+                continue;
+            }
+            steps.push(Self::parse_step(part, descendant)?);
+            descendant = false;
+        }
+        if steps.is_empty() {
+            return Err(anyhow!("XML path must select an element: {}", query));
+        }
+        Ok(XmlPath { steps, attribute })
+    }
+
+    fn parse_step(part: &str, descendant: bool) -> Result<Step, Error> {
+        let (name, mut rest) = match part.find('[') {
+            Some(i) => (&part[..i], &part[i..]),
+            None => (part, ""),
+        };
+        let mut predicates = vec![];
+        while !rest.is_empty() {
+            let end = rest
+                .find(']')
+                .ok_or_else(|| anyhow!("unclosed predicate in XML path step: {}", part))?;
+            let predicate = rest[1..end].trim();
+            rest = &rest[end + 1..];
+            predicates.push(match predicate.split_once('=') {
+                None => Predicate::HasAttribute(
+                    predicate
+                        .strip_prefix('@')
+                        .ok_or_else(|| anyhow!("unsupported predicate: {}", predicate))?
+                        .to_string(),
+                ),
+                Some((left, right)) => {
+                    let value = unquote(right.trim())
+                        .ok_or_else(|| anyhow!("predicate value must be quoted: {}", predicate))?;
+                    match left.trim() {
+                        "text()" => Predicate::TextEquals(value),
+                        left => Predicate::AttributeEquals(
+                            left.strip_prefix('@')
+                                .ok_or_else(|| anyhow!("unsupported predicate: {}", predicate))?
+                                .to_string(),
+                            value,
+                        ),
+                    }
+                }
+            });
+        }
+        Ok(Step {
+            descendant,
+            name: name.to_string(),
+            predicates,
+        })
+    }
+
+    /// Select the matching elements of the document, the value is the selected attribute when
+    /// the path ends with `/@attr`, otherwise the element text when it has any.
+    pub(crate) fn select<'a>(&self, document: &'a XmlElement) -> Vec<XmlMatch<'a>> {
+        let mut context: Vec<&XmlElement> = vec![document];
+        for step in self.steps.iter() {
+            let mut candidates: Vec<&XmlElement> = vec![];
+            for element in context {
+                if step.descendant {
+                    element.descendants(&mut candidates);
+                } else {
+                    candidates.extend(element.children.iter());
+                }
+            }
+            // Nested context elements can find the same descendant more than once.
+            let mut seen = std::collections::BTreeSet::new();
+            context = candidates
+                .into_iter()
+                .filter(|e| step.matches(e) && seen.insert(e.location.clone()))
+                .collect();
+        }
+        context
+            .into_iter()
+            .filter_map(|element| match &self.attribute {
+                Some(attribute) => element.attributes.get(attribute).map(|v| XmlMatch {
+                    element,
+                    value: Some(v.clone()),
+                }),
+                None => Some(XmlMatch {
+                    element,
+                    value: Some(element.text.clone()).filter(|t| !t.is_empty()),
+                }),
+            })
+            .collect()
+    }
+}
+
+impl Step {
+    fn matches(&self, element: &XmlElement) -> bool {
+        if self.name != "*" && self.name != element.name {
+            return false;
+        }
+        self.predicates.iter().all(|p| match p {
+            Predicate::HasAttribute(name) => element.attributes.contains_key(name),
+            Predicate::AttributeEquals(name, value) => element.attributes.get(name) == Some(value),
+            Predicate::TextEquals(value) => &element.text == value,
+        })
+    }
+}
+
+/// Split the path on `/` outside of predicates, an empty part marks a `//`.
+fn split_steps(query: &str) -> Result<Vec<&str>, Error> {
+    let mut parts = vec![];
+    let mut depth = 0;
+    let mut quote: Option<char> = None;
+    let mut start = 1;
+    for (i, c) in query.char_indices().skip(1) {
+        match (c, quote) {
+            ('\'' | '"', None) => quote = Some(c),
+            (c, Some(q)) if c == q => quote = None,
+            ('[', None) => depth += 1,
+            (']', None) => depth -= 1,
+            ('/', None) if depth == 0 => {
+                parts.push(&query[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if quote.is_some() || depth != 0 {
+        return Err(anyhow!(
+            "unbalanced quotes or brackets in XML path: {}",
+            query
+        ));
+    }
+    parts.push(&query[start..]);
+    Ok(parts)
+}
+
+fn unquote(value: &str) -> Option<String> {
+    ['\'', '"'].iter().find_map(|q| {
+        value
+            .strip_prefix(*q)
+            .and_then(|v| v.strip_suffix(*q))
+            .map(|v| v.to_string())
+    })
+}
+
+/// Optionally match a selected value against a regex.
+pub(crate) fn value_matches(value_regex: Option<&Regex>, value: Option<&str>) -> bool {
+    match value_regex {
+        None => true,
+        Some(r) => value.is_some_and(|v| r.is_match(v)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WEB_CONFIG: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<configuration>
+  <connectionStrings>
+    <add name="NerdDinners" connectionString="Data Source=.;Initial Catalog=NerdDinner" providerName="System.Data.SqlClient" />
+  </connectionStrings>
+  <appSettings>
+    <add key="webpages:Version" value="2.0.0.0" />
+    <add key="ClientValidationEnabled" value="true" />
+  </appSettings>
+  <system.web>
+    <authentication mode="Forms">
+      <forms loginUrl="~/Account/Login" timeout="2880" />
+    </authentication>
+    <httpModules>
+      <add name="ErrorLog" type="Elmah.ErrorLogModule, Elmah" />
+    </httpModules>
+  </system.web>
+</configuration>
+"#;
+
+    #[test]
+    fn test_parse_positions() {
+        let document = XmlElement::parse(WEB_CONFIG).unwrap();
+        assert_eq!(document.children.len(), 1);
+        let configuration = &document.children[0];
+        assert_eq!(configuration.name, "configuration");
+        assert_eq!(configuration.location.start_position.line, 1);
+        assert_eq!(configuration.location.end_position.line, 17);
+
+        let system_web = &configuration.children[2];
+        assert_eq!(system_web.path, "/configuration/system.web");
+        let authentication = &system_web.children[0];
+        assert_eq!(authentication.attributes["mode"], "Forms");
+        assert_eq!(authentication.location.start_position.line, 10);
+        assert_eq!(authentication.location.start_position.character, 4);
+    }
+
+    #[test]
+    fn test_select_absolute_path_with_predicate() {
+        let document = XmlElement::parse(WEB_CONFIG).unwrap();
+        let path =
+            XmlPath::parse("/configuration/system.web/authentication[@mode='Forms']").unwrap();
+        let matches = path.select(&document);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].element.name, "authentication");
+
+        let path =
+            XmlPath::parse("/configuration/system.web/authentication[@mode='Windows']").unwrap();
+        assert!(path.select(&document).is_empty());
+    }
+
+    #[test]
+    fn test_select_descendant_and_attribute() {
+        let document = XmlElement::parse(WEB_CONFIG).unwrap();
+        let path = XmlPath::parse("//appSettings/add/@key").unwrap();
+        let values: Vec<Option<String>> = path
+            .select(&document)
+            .into_iter()
+            .map(|m| m.value)
+            .collect();
+        assert_eq!(
+            values,
+            vec![
+                Some("webpages:Version".to_string()),
+                Some("ClientValidationEnabled".to_string())
+            ]
+        );
+
+        let path = XmlPath::parse("//*[@type]").unwrap();
+        let matches = path.select(&document);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(
+            matches[0].element.path,
+            "/configuration/system.web/httpModules/add"
+        );
+    }
+
+    #[test]
+    fn test_select_text_predicate() {
+        let document = XmlElement::parse(
+            "<Project><PropertyGroup><OutputType>Library</OutputType></PropertyGroup></Project>",
+        )
+        .unwrap();
+        let path = XmlPath::parse("//OutputType[text()='Library']").unwrap();
+        let matches = path.select(&document);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].value, Some("Library".to_string()));
+    }
+
+    #[test]
+    fn test_parse_invalid_paths() {
+        assert!(XmlPath::parse("configuration").is_err());
+        assert!(XmlPath::parse("//add[@key='x'").is_err());
+        assert!(XmlPath::parse("//add/@key/value").is_err());
+        assert!(XmlPath::parse("//add[@key=x]").is_err());
+    }
+
+    #[test]
+    fn test_split_steps_keeps_slash_in_predicate() {
+        let path = XmlPath::parse("//forms[@loginUrl='~/Account/Login']").unwrap();
+        assert_eq!(path.steps.len(), 1);
+        assert_eq!(
+            path.steps[0].predicates,
+            vec![Predicate::AttributeEquals(
+                "loginUrl".to_string(),
+                "~/Account/Login".to_string()
+            )]
+        );
+    }
+}
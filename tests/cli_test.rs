@@ -0,0 +1,296 @@
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use fs_extra::dir::CopyOptions;
+use serde_json::{json, Value};
+
+use c_sharp_analyzer_provider_cli::provider::cache::{self, CacheManifest};
+use c_sharp_analyzer_provider_cli::provider::AnalysisMode;
+
+fn provider() -> Command {
+    Command::new(env!("CARGO_BIN_EXE_c-sharp-analyzer-provider-cli"))
+}
+
+/// Run the command line of the provider and return what it printed, the test fails when the
+/// command does.
+fn run<I, S>(args: I) -> String
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    run_command(provider().args(args))
+}
+
+fn run_command(command: &mut Command) -> String {
+    let output = command.output().expect("Failed to run the provider");
+    assert!(
+        output.status.success(),
+        "command failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+/// Copy `source` into `dir` so that init can analyze it offline, returning the project and the
+/// `--provider-config` to pass.
+fn offline_project(source: &Path, dir: &Path) -> (PathBuf, String) {
+    fs_extra::dir::copy(source, dir, &CopyOptions::new()).unwrap();
+    let project = dir.join(source.file_name().unwrap());
+    let config = offline_config(&project, dir);
+    (project, config)
+}
+
+/// The `--provider-config` of a project in `dir` that init can analyze offline: with an empty
+/// paket.dependencies there are no dependencies to resolve, and the tools only have to exist.
+fn offline_config(project: &Path, dir: &Path) -> String {
+    std::fs::write(project.join("paket.dependencies"), "").unwrap();
+    let tools = dir.join("tools");
+    std::fs::create_dir(&tools).unwrap();
+    for tool in ["ilspycmd", "paket"] {
+        std::fs::write(tools.join(tool), "").unwrap();
+    }
+    json!({
+        "ilspy_cmd": tools.join("ilspycmd"),
+        "paket_cmd": tools.join("paket"),
+    })
+    .to_string()
+}
+
+#[test]
+fn test_index_and_query() {
+    let dir = tempfile::tempdir().unwrap();
+    let (project, config) = offline_project(
+        &PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("testdata/nerd-dinner"),
+        dir.path(),
+    );
+    let db_path = dir.path().join("graph.db");
+    let common = |command: &str| {
+        vec![
+            "--db-path".into(),
+            db_path.clone().into_os_string(),
+            command.into(),
+            project.clone().into_os_string(),
+            "--provider-config".into(),
+            config.clone().into(),
+        ]
+    };
+
+    let indexed = run(common("index"));
+    assert!(
+        indexed.contains(&db_path.display().to_string()),
+        "{}",
+        indexed
+    );
+    assert!(db_path.exists());
+
+    let query = |output: &str| {
+        let mut args = common("query");
+        args.extend(
+            [
+                "--pattern",
+                "NerdDinner.Models.Dinner",
+                "--location",
+                "CLASS",
+                "--output",
+                output,
+            ]
+            .map(Into::into),
+        );
+        serde_json::from_str::<Value>(&run(args)).unwrap()
+    };
+
+    let results = query("json");
+    let results = results.as_array().unwrap();
+    assert!(!results.is_empty());
+    let project_uri = url::Url::from_directory_path(&project).unwrap();
+    for result in results {
+        let uri = result["fileURI"].as_str().unwrap();
+        assert!(uri.starts_with(project_uri.as_str()), "{}", uri);
+    }
+    let declaration = results.iter().find(|r| {
+        r["fileURI"]
+            .as_str()
+            .unwrap()
+            .ends_with("/Models/Dinner.cs")
+    });
+    assert!(declaration.is_some(), "{:?}", results);
+
+    let log = query("sarif");
+    assert_eq!(log["version"], "2.1.0");
+    let run = &log["runs"][0];
+    assert_eq!(run["tool"]["driver"]["rules"][0]["id"], "csharp.referenced");
+    let sarif_results = run["results"].as_array().unwrap();
+    assert_eq!(sarif_results.len(), results.len());
+    for result in sarif_results {
+        assert_eq!(result["ruleId"], "csharp.referenced");
+        let location = &result["locations"][0]["physicalLocation"];
+        // The files are relative to the project.
+        assert_eq!(location["artifactLocation"]["uriBaseId"], "SRCROOT");
+        assert!(location["artifactLocation"]["uri"]
+            .as_str()
+            .unwrap()
+            .starts_with("mvc4/"));
+    }
+}
+
+#[test]
+fn test_analyze() {
+    let source = r#"using Demo;
+
+namespace Demo
+{
+    public class Settings
+    {
+        public static void Load() { }
+    }
+}
+
+namespace Demo.App
+{
+    public class Startup
+    {
+        public void Run()
+        {
+            Settings.Load(); // konveyor-ignore: demo-00010
+            Settings.Load();
+        }
+    }
+}
+"#;
+    let line_of = |text: &str| source.lines().position(|l| l.trim() == text).unwrap() as u64;
+    let dir = tempfile::tempdir().unwrap();
+    let project = dir.path().join("project");
+    std::fs::create_dir(&project).unwrap();
+    std::fs::write(project.join("Startup.cs"), source).unwrap();
+    let config = offline_config(&project, dir.path());
+    let output_file = dir.path().join("output.json");
+    run([
+        OsStr::new("--db-path"),
+        dir.path().join("graph.db").as_os_str(),
+        OsStr::new("analyze"),
+        project.as_os_str(),
+        OsStr::new("--provider-config"),
+        OsStr::new(&config),
+        OsStr::new("--rules"),
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/rules/demo")
+            .as_os_str(),
+        OsStr::new("--output-file"),
+        output_file.as_os_str(),
+        OsStr::new("--output"),
+        OsStr::new("json"),
+    ]);
+
+    let output: Value =
+        serde_json::from_str(&std::fs::read_to_string(&output_file).unwrap()).unwrap();
+    let rulesets = output.as_array().unwrap();
+    assert_eq!(rulesets.len(), 1);
+    let ruleset = &rulesets[0];
+    assert_eq!(ruleset["name"], "demo");
+    let lines = |incidents: &Value| -> Vec<u64> {
+        incidents
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|i| i["lineNumber"].as_u64().unwrap())
+            .collect()
+    };
+
+    // Rules with an effort are violations, the comment naming the rule suppresses one call.
+    let violation = &ruleset["violations"]["demo-00010"];
+    assert_eq!(violation["category"], "mandatory");
+    assert_eq!(violation["effort"], 3);
+    assert_eq!(
+        violation["incidents"][0]["message"],
+        "Replace the call to Settings.Load"
+    );
+    assert_eq!(
+        lines(&violation["incidents"]),
+        vec![line_of("Settings.Load();")]
+    );
+
+    // Rules without one are insights, and the comment does not apply to other rules.
+    let insight = &ruleset["insights"]["demo-00020"];
+    let insight_lines = lines(&insight["incidents"]);
+    assert!(insight_lines.contains(&line_of("Settings.Load(); // konveyor-ignore: demo-00010")));
+    assert!(ruleset["violations"].get("demo-00020").is_none());
+    assert!(ruleset["insights"].get("demo-00010").is_none());
+
+    assert_eq!(ruleset["skipped"], json!(["demo-00030"]));
+    assert_eq!(ruleset["unmatched"], json!(["demo-00040"]));
+}
+
+#[test]
+fn test_cache() {
+    // The cache directory is in the temporary directory of the commands.
+    let dir = tempfile::tempdir().unwrap();
+    let cache_dir = dir.path().join("c_sharp_provider");
+    let cache =
+        |args: &[&OsStr]| run_command(provider().env("TMPDIR", dir.path()).arg("cache").args(args));
+    let listed = cache(&["list".as_ref()]);
+    assert!(listed.starts_with("no graph databases"), "{}", listed);
+
+    // The database of a project with its manifest, and one without a manifest.
+    let project = dir.path().join("project");
+    let db_path = cache_dir.join(
+        cache::default_db_path(&project, &AnalysisMode::SourceOnly)
+            .file_name()
+            .unwrap(),
+    );
+    let manifest = CacheManifest::new(&project, &AnalysisMode::SourceOnly);
+    std::fs::create_dir_all(&cache_dir).unwrap();
+    std::fs::write(&db_path, "graph").unwrap();
+    std::fs::write(
+        CacheManifest::path(&db_path),
+        serde_json::to_string(&manifest).unwrap(),
+    )
+    .unwrap();
+    let orphan = cache_dir.join("orphan.db");
+    std::fs::write(&orphan, "graph").unwrap();
+
+    let listed = cache(&["list".as_ref()]);
+    assert!(
+        listed.contains(&format!("{}\t5\t{}", db_path.display(), project.display())),
+        "{}",
+        listed
+    );
+    assert!(
+        listed.contains(&format!("{}\t5\tunknown project", orphan.display())),
+        "{}",
+        listed
+    );
+
+    let inspected = cache(&["inspect".as_ref(), project.as_os_str()]);
+    assert!(inspected.contains(&format!("database: {}", db_path.display())));
+    assert!(inspected.contains(&format!("location: {}", project.display())));
+    assert!(inspected.contains("analysis mode: source-only"));
+    assert!(inspected.contains(&format!("provider version: {}", env!("CARGO_PKG_VERSION"))));
+
+    // `--db-path` replaces the database of the project.
+    let inspected = cache(&[
+        "inspect".as_ref(),
+        project.as_os_str(),
+        "--db-path".as_ref(),
+        orphan.as_os_str(),
+    ]);
+    assert!(inspected.contains("manifest: missing"), "{}", inspected);
+
+    let purged = cache(&["purge".as_ref(), project.as_os_str()]);
+    assert_eq!(purged.trim(), format!("removed {}", db_path.display()));
+    assert!(!db_path.exists());
+    assert!(!CacheManifest::path(&db_path).exists());
+    let purged = cache(&["purge".as_ref(), project.as_os_str()]);
+    assert!(purged.contains("has not been indexed"), "{}", purged);
+    let output = provider()
+        .env("TMPDIR", dir.path())
+        .args(["cache".as_ref(), "inspect".as_ref(), project.as_os_str()])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+
+    let purged = cache(&["purge".as_ref(), "--all".as_ref()]);
+    assert_eq!(purged.trim(), format!("removed {}", orphan.display()));
+    let listed = cache(&["list".as_ref()]);
+    assert!(listed.starts_with("no graph databases"), "{}", listed);
+}
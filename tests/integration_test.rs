@@ -388,8 +388,7 @@ async fn integration_test_net8() {
         result
     );
 
-    let mut file_uri: String = String::new();
-    match result.response {
+    let file_uri: String = match result.response {
         None => panic!("No response from evaluate"),
         Some(response) => {
             println!("Found {} incidents", response.incident_contexts.len());
@@ -407,9 +406,9 @@ async fn integration_test_net8() {
                 response.incident_contexts.len()
             );
             println!("✓ {} references in Program.cs", program_incidents.len());
-            file_uri = program_incidents.first().unwrap().clone().file_uri.clone();
+            program_incidents.first().unwrap().file_uri.clone()
         }
-    }
+    };
 
     // Notify File Change for Program and make sure that System.Console is still found
     let result = client